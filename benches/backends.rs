@@ -0,0 +1,48 @@
+// Compares the tree-walking interpreter with the bytecode VM. Run with `cargo bench`
+use std::io::{empty, sink};
+use std::time::{Duration, Instant};
+
+use perry::{Interpreter, Vm, pipeline::check_source};
+
+const ITERATIONS: u32 = 20;
+
+fn main() {
+    // The examples are scaled up so that running dominates setup
+    let factorial = include_str!("../working_examples/factorial.pry")
+        .replace("var result: int = factorial(5);", "var result: int = 0;")
+        .replace(
+            "println(result);",
+            "var i: int = 0;\nwhile (i < 2000) { result = factorial(12); i = i + 1; }\nprintln(result);",
+        );
+    let fizzbuzz = include_str!("../working_examples/fizzbuzz.pry")
+        .replace("fizzbuzz(15);", "fizzbuzz(20000);");
+
+    for (name, source) in [("factorial", factorial), ("fizzbuzz", fizzbuzz)] {
+        let program = check_source(&source).expect("benchmark programs are valid");
+        let interpreter = time(|| {
+            let mut interpreter = Interpreter::with_io(Box::new(sink()), Box::new(empty()));
+            interpreter.execute(&program).unwrap();
+        });
+        let vm = time(|| {
+            let mut vm = Vm::with_io(Box::new(sink()), Box::new(empty()));
+            vm.execute(&program).unwrap();
+        });
+        println!(
+            "{:<10} interpreter {:>9.3?}  vm {:>9.3?}  speedup {:.2}x",
+            name,
+            interpreter,
+            vm,
+            interpreter.as_secs_f64() / vm.as_secs_f64()
+        );
+    }
+}
+
+// Returns the mean time of a run, after one warm-up run
+fn time(mut run: impl FnMut()) -> Duration {
+    run();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        run();
+    }
+    start.elapsed() / ITERATIONS
+}
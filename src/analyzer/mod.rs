@@ -4,7 +4,13 @@ pub mod test;
 use std::{collections::HashMap, fmt::Display};
 
 use crate::common::{
-    ast::{self, LiteralValue, Parameter, Span, Spannable, SpannedStatement, Type},
+    ast::{
+        self, BinaryExpression, Expression, FunctionCall, FunctionDecl, IfStatement,
+        ImportStatement, LiteralValue, LoopStatement, Parameter, ReturnStatement, Span,
+        SpannedStatement, Statement, Type, VariableAssignment, VariableDecl, VariableRef,
+    },
+    diagnostic::Label,
+    error::{ErrorKind, PerryError},
     token::BinaryOperator,
 };
 
@@ -13,10 +19,41 @@ struct SymbolTable {
     tables: Vec<HashMap<String, Symbol>>,
 }
 
-#[derive(Clone)]
-struct Symbol {
-    type_: Type,
-    params: Vec<Parameter>,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    Function,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub type_: Type,
+    pub params: Vec<Parameter>,
+    pub span: Option<Span>, // The declaring statement, None for built-ins
+}
+
+// A declared function, variable or parameter. Parameters are declared by their function
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name: String,
+    pub symbol: Symbol,
+}
+
+// A use of a name resolved to the symbol it refers to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub name: String,
+    pub span: Span, // The name itself
+    pub symbol: Symbol,
+}
+
+// Every declaration and resolved reference of the last analyzed program, in source order. Used by
+// editor tooling to answer questions about names
+#[derive(Debug, Clone, Default)]
+pub struct SymbolIndex {
+    pub declarations: Vec<Declaration>,
+    pub references: Vec<Reference>,
 }
 
 impl SymbolTable {
@@ -24,18 +61,49 @@ impl SymbolTable {
         SymbolTable { tables: Vec::new() }
     }
 
-    fn add_symbol(&mut self, name: String, type_: Type) {
+    fn add_symbol(&mut self, name: String, type_: Type, span: Option<Span>) {
         self.tables[0].insert(
             name,
             Symbol {
+                kind: SymbolKind::Variable,
                 type_,
                 params: Vec::new(),
+                span,
+            },
+        );
+    }
+
+    fn add_function_signature(
+        &mut self,
+        name: String,
+        type_: Type,
+        params: Vec<Parameter>,
+        span: Option<Span>,
+    ) {
+        self.tables[0].insert(
+            name,
+            Symbol {
+                kind: SymbolKind::Function,
+                type_,
+                params,
+                span,
             },
         );
     }
 
-    fn add_function_signature(&mut self, name: String, type_: Type, params: Vec<Parameter>) {
-        self.tables[0].insert(name, Symbol { type_, params });
+    // Adds a symbol to the outermost scope, regardless of the current scope
+    fn add_global(&mut self, name: String, type_: Type) {
+        if let Some(globals) = self.tables.last_mut() {
+            globals.insert(
+                name,
+                Symbol {
+                    kind: SymbolKind::Variable,
+                    type_,
+                    params: Vec::new(),
+                    span: None,
+                },
+            );
+        }
     }
 
     fn enter_scope(&mut self) {
@@ -52,45 +120,110 @@ impl SymbolTable {
 }
 
 #[derive(Clone)]
-pub struct Analyzer<'a> {
+pub struct Analyzer {
     symbol_table: SymbolTable,
-    program_ast: &'a ast::Program,
+    index: SymbolIndex,
+    modules: HashMap<String, Vec<(String, Symbol)>>, // Public functions of importable files by path
+    imported: HashMap<String, Option<Span>>, // Imported names to the function each was exported as
 }
 
-impl<'a> Analyzer<'a> {
-    pub fn new(program_ast: &'a ast::Program) -> Self {
-        Self {
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        let mut analyzer = Self {
             symbol_table: SymbolTable::new(),
-            program_ast,
-        }
+            index: SymbolIndex::default(),
+            modules: HashMap::new(),
+            imported: HashMap::new(),
+        };
+        analyzer.symbol_table.enter_scope(); // Global scope
+        analyzer.initialize_built_ins();
+        analyzer
     }
 
-    // Calls analyze_body on the top-level program AST
-    pub fn analyze(&mut self) -> Result<(), SemanticError> {
-        self.symbol_table.enter_scope();
-        self.initialize_built_ins();
-        self.analyze_body(&self.program_ast.body)?;
-        self.symbol_table.exit_scope();
+    // Calls analyze_body on the top-level program AST. Global declarations are kept, so several
+    // programs can be analyzed one after another against the same analyzer
+    pub fn analyze(&mut self, program_ast: &ast::Program) -> Result<(), SemanticError> {
+        self.index = SymbolIndex::default();
+        self.analyze_body(&program_ast.body)?;
+        Ok(())
+    }
+
+    // Returns the declarations and references found by the last call to analyze. After an error
+    // it covers the program up to the error
+    pub fn index(&self) -> &SymbolIndex {
+        &self.index
+    }
+
+    // Returns the symbols declared in the global scope, sorted by name
+    pub fn globals(&self) -> Vec<(&str, &Symbol)> {
+        let mut globals: Vec<(&str, &Symbol)> = self
+            .symbol_table
+            .tables
+            .last()
+            .into_iter()
+            .flat_map(|table| table.iter())
+            .map(|(name, symbol)| (name.as_str(), symbol))
+            .collect();
+        globals.sort_by_key(|(name, _)| *name);
+        globals
+    }
+
+    // Declares a built-in function implemented by the host
+    pub fn register_built_in(&mut self, name: String, params: Vec<Parameter>, type_: Type) {
+        self.symbol_table
+            .add_function_signature(name, type_, params, None);
+    }
+
+    // Declares a global variable provided by the host. Fails if the name is already declared
+    // with a different type
+    pub fn declare_global(&mut self, name: String, type_: Type) -> Result<(), SemanticError> {
+        if let Some(existing) = self.symbol_table.lookup(&name) {
+            if existing.kind != SymbolKind::Variable || existing.type_ != type_ {
+                return Err(SemanticError::new(
+                    format!("global {} is already declared as {}", name, existing.type_),
+                    existing.span.unwrap_or_default(),
+                ));
+            }
+            return Ok(());
+        }
+        self.symbol_table.add_global(name, type_);
         Ok(())
     }
 
+    // Makes a file importable under `path`, exactly as the path is written in import statements.
+    // `exports` are its public functions
+    pub fn add_module(&mut self, path: String, exports: Vec<(String, Symbol)>) {
+        self.modules.insert(path, exports);
+    }
+
+    // Returns the parameters and return type of a declared function
+    pub fn function_signature(&self, name: &str) -> Option<(&[Parameter], &Type)> {
+        self.symbol_table
+            .lookup(name)
+            .filter(|symbol| symbol.kind == SymbolKind::Function)
+            .map(|symbol| (symbol.params.as_slice(), &symbol.type_))
+    }
+
+    // Declares the built-ins of `interpreter::standard_built_ins`
     pub fn initialize_built_ins(&mut self) {
-        self.symbol_table.add_function_signature(
-            "print".to_string(),
-            Type::Void,
-            vec![Parameter {
-                name: "arg".to_string(),
-                type_: Type::Any,
-            }],
-        );
-        self.symbol_table.add_function_signature(
-            "println".to_string(),
-            Type::Void,
-            vec![Parameter {
-                name: "arg".to_string(),
-                type_: Type::Any,
-            }],
-        );
+        for (name, params, type_) in built_in_signatures() {
+            let params = params
+                .into_iter()
+                // Built-ins aren't declared in source, so their parameters have no span
+                .map(|(name, type_)| Parameter {
+                    name: name.to_string(),
+                    type_,
+                    span: Span::default(),
+                })
+                .collect();
+            self.register_built_in(name.to_string(), params, type_);
+        }
     }
 
     // Analyzes a body of statements. Returns a bool indicating if any statement was a return
@@ -109,241 +242,468 @@ impl<'a> Analyzer<'a> {
         &mut self,
         statement: &ast::SpannedStatement,
     ) -> Result<bool, SemanticError> {
-        match statement.node.clone() {
-            ast::Statement::VarDecl(var_decl) => {
-                let identifier = var_decl.name.clone();
-                let type_ = var_decl.type_;
-                if self.symbol_table.lookup(&identifier).is_some() {
-                    return Err(SemanticError {
-                        message: format!("duplicate declaration of {}", identifier),
-                        span: statement.span,
-                    });
-                }
-                self.symbol_table
-                    .add_symbol(var_decl.name.clone(), type_.clone());
-                let expression_type = self.analyze_expression(&var_decl.value)?;
-                if &expression_type != &type_ {
-                    return Err(SemanticError {
-                        message: format!(
-                            "variable {} declared with type {} but assigned with type {}",
-                            identifier, type_, expression_type
-                        ),
-                        span: statement.span,
-                    });
-                }
-            }
-            ast::Statement::VarAssignment(var_assignment) => {
-                let identifier = var_assignment.name.clone();
-                let expression_type = self.analyze_expression(&var_assignment.value)?;
-
-                // Verify variable is already declared
-                let Some(symbol) = self.symbol_table.lookup(&identifier) else {
-                    return Err(SemanticError {
-                        message: format!("cannot assign to undeclared identifier {}", identifier),
-                        span: statement.span,
-                    });
-                };
-
-                // Verify variable type matches assignment type
-                if symbol.type_ != expression_type {
-                    return Err(SemanticError {
-                        message: format!(
-                            "Type mismatch: expected {}, got {}",
-                            symbol.type_, expression_type
-                        ),
-                        span: statement.span,
-                    });
-                }
-            }
-            ast::Statement::If(if_statement) => {
-                self.symbol_table.enter_scope();
-                let condition_type = self.analyze_expression(&if_statement.condition)?;
-                if condition_type != Type::Bool {
-                    return Err(SemanticError::new(
-                        format!(
-                            "if statement condition must resolve to bool, got {}",
-                            condition_type
-                        ),
-                        if_statement.condition.span,
-                    ));
-                }
-                let mut returned = self.analyze_body(&if_statement.then_body)?;
-                self.symbol_table.exit_scope();
-                if let Some(else_body) = if_statement.else_body {
-                    self.symbol_table.enter_scope();
-                    returned = self.analyze_statement(&else_body)?;
-                    self.symbol_table.exit_scope();
-                }
-                return Ok(returned);
-            }
-            ast::Statement::Function(function) => {
-                self.symbol_table.add_function_signature(
-                    function.name.clone(),
-                    function.type_.clone(),
-                    function.params.clone(),
-                );
-                self.symbol_table.enter_scope();
-                for param in &function.params {
-                    self.symbol_table
-                        .add_symbol(param.name.clone(), param.type_.clone());
-                }
-                self.symbol_table
-                    .add_symbol("return".to_string(), function.type_.clone());
-                let returned = self.analyze_body(&function.body)?;
-                if function.type_ != Type::Void && !returned {
-                    return Err(SemanticError::new(
-                        format!("function {} must return a value", function.name),
-                        statement.span,
-                    ));
-                }
-                self.symbol_table.exit_scope();
-                return Ok(returned);
-            }
-            ast::Statement::Return(return_statement) => {
-                let Some(expected_return) = &self.symbol_table.lookup("return") else {
-                    return Err(SemanticError::new(
-                        format!("return statement outside of function"),
-                        statement.span,
-                    ));
-                };
-                let return_value_type = match &return_statement.value {
-                    Some(return_value) => self.clone().analyze_expression(return_value)?,
-                    None => Type::Void,
-                };
-                if return_value_type != expected_return.type_ {
-                    return Err(SemanticError::new(
-                        format!(
-                            "return type mismatch: expected {}, got {}",
-                            expected_return.type_, return_value_type
-                        ),
-                        statement.span,
-                    ));
-                }
-                return Ok(true);
-            }
-            ast::Statement::Loop(loop_statement) => {
-                self.symbol_table.enter_scope();
-                let condition_type = self.analyze_expression(&loop_statement.condition)?;
-                if condition_type != Type::Bool {
-                    return Err(SemanticError::new(
-                        format!(
-                            "loop condition must resolve to bool, got {}",
-                            condition_type
-                        ),
-                        loop_statement.condition.span,
-                    ));
-                }
-                let returned = self.analyze_body(&loop_statement.body)?;
-                self.symbol_table.exit_scope();
-                return Ok(returned);
-            }
-            ast::Statement::Expr(expr) => {
-                self.analyze_expression(&expr.spanned(statement.span))?;
+        let span = statement.span;
+        match &statement.node {
+            Statement::VarDecl(var_decl) => self.analyze_var_decl(var_decl, span),
+            Statement::VarAssignment(var_assignment) => {
+                self.analyze_var_assignment(var_assignment, span)
             }
+            Statement::If(if_statement) => self.analyze_if(if_statement),
+            Statement::Function(function) => self.analyze_function_decl(function, span),
+            Statement::Return(return_statement) => self.analyze_return(return_statement, span),
+            Statement::Loop(loop_statement) => self.analyze_loop(loop_statement),
+            // An expression statement shares its span with the expression
+            Statement::Expr(expression) => self.expression_type(expression, span).map(|_| false),
+            Statement::Import(import) => self.analyze_import(import, span),
         }
-        Ok(false)
     }
 
+    // Analyzes an expression, returning its type
     pub fn analyze_expression(
         &mut self,
         expression: &ast::SpannedExpression,
     ) -> Result<Type, SemanticError> {
-        match expression.node.clone() {
-            ast::Expression::Literal(literal) => {
-                return match literal.value {
-                    LiteralValue::String(_) => Ok(Type::String),
-                    LiteralValue::Number(_) => Ok(Type::Int),
-                    LiteralValue::Bool(_) => Ok(Type::Bool),
-                    LiteralValue::Void => Ok(Type::Void),
-                };
+        self.expression_type(&expression.node, expression.span)
+    }
+
+    fn expression_type(
+        &mut self,
+        expression: &Expression,
+        span: Span,
+    ) -> Result<Type, SemanticError> {
+        match expression {
+            Expression::Literal(literal) => Ok(match literal.value {
+                LiteralValue::String(_) => Type::String,
+                LiteralValue::Number(_) => Type::Int,
+                LiteralValue::Bool(_) => Type::Bool,
+                LiteralValue::Void => Type::Void,
+            }),
+            Expression::VariableRef(variable_ref) => self.analyze_variable_ref(variable_ref, span),
+            Expression::FunctionCall(function_call) => {
+                self.analyze_function_call(function_call, span)
+            }
+            Expression::Binary(binary_expression) => self.analyze_binary(binary_expression, span),
+        }
+    }
+
+    fn analyze_var_decl(
+        &mut self,
+        var_decl: &VariableDecl,
+        span: Span,
+    ) -> Result<bool, SemanticError> {
+        let identifier = &var_decl.name;
+        let type_ = &var_decl.type_;
+        self.check_undeclared(identifier, span)?;
+        self.symbol_table
+            .add_symbol(identifier.clone(), type_.clone(), Some(span));
+        self.record_declaration(identifier);
+        let expression_type = self.analyze_expression(&var_decl.value)?;
+        if expression_type != *type_ {
+            return Err(SemanticError::new(
+                format!(
+                    "variable {} declared with type {} but assigned with type {}",
+                    identifier, type_, expression_type
+                ),
+                var_decl.value.span,
+            ));
+        }
+        Ok(false)
+    }
+
+    fn analyze_var_assignment(
+        &mut self,
+        var_assignment: &VariableAssignment,
+        span: Span,
+    ) -> Result<bool, SemanticError> {
+        let identifier = &var_assignment.name;
+        let expression_type = self.analyze_expression(&var_assignment.value)?;
+
+        // Verify variable is already declared
+        let Some(symbol) = self.symbol_table.lookup(identifier) else {
+            return Err(SemanticError::new(
+                format!("cannot assign to undeclared identifier {}", identifier),
+                span,
+            ));
+        };
+
+        if symbol.kind != SymbolKind::Variable {
+            return Err(SemanticError::new(
+                format!("cannot assign to function {}", identifier),
+                span,
+            ));
+        }
+
+        // Verify variable type matches assignment type
+        if symbol.type_ != expression_type {
+            return Err(SemanticError::new(
+                format!(
+                    "Type mismatch: expected {}, got {}",
+                    symbol.type_, expression_type
+                ),
+                span,
+            ));
+        }
+        self.record_reference(identifier, name_span(span, identifier));
+        Ok(false)
+    }
+
+    fn analyze_if(&mut self, if_statement: &IfStatement) -> Result<bool, SemanticError> {
+        self.symbol_table.enter_scope();
+        let condition_type = self.analyze_expression(&if_statement.condition)?;
+        if condition_type != Type::Bool {
+            return Err(SemanticError::new(
+                format!(
+                    "if statement condition must resolve to bool, got {}",
+                    condition_type
+                ),
+                if_statement.condition.span,
+            ));
+        }
+        let mut returned = self.analyze_body(&if_statement.then_body)?;
+        self.symbol_table.exit_scope();
+        if let Some(else_body) = &if_statement.else_body {
+            self.symbol_table.enter_scope();
+            returned = self.analyze_statement(else_body)?;
+            self.symbol_table.exit_scope();
+        }
+        Ok(returned)
+    }
+
+    fn analyze_function_decl(
+        &mut self,
+        function: &FunctionDecl,
+        span: Span,
+    ) -> Result<bool, SemanticError> {
+        if function.public && self.symbol_table.tables.len() > 1 {
+            return Err(SemanticError::new(
+                format!("nested function {} cannot be pub", function.name),
+                span,
+            ));
+        }
+        self.check_undeclared(&function.name, span)?;
+        self.symbol_table.add_function_signature(
+            function.name.clone(),
+            function.type_.clone(),
+            function.params.clone(),
+            Some(span),
+        );
+        self.record_declaration(&function.name);
+        self.symbol_table.enter_scope();
+        for param in &function.params {
+            self.symbol_table
+                .add_symbol(param.name.clone(), param.type_.clone(), Some(param.span));
+            self.record_declaration(&param.name);
+        }
+        self.symbol_table
+            .add_symbol("return".to_string(), function.type_.clone(), None);
+        let returned = self.analyze_body(&function.body)?;
+        if function.type_ != Type::Void && !returned {
+            return Err(SemanticError::new(
+                format!("function {} must return a value", function.name),
+                span,
+            ));
+        }
+        self.symbol_table.exit_scope();
+        Ok(returned)
+    }
+
+    fn analyze_return(
+        &mut self,
+        return_statement: &ReturnStatement,
+        span: Span,
+    ) -> Result<bool, SemanticError> {
+        let Some(expected_return) = self
+            .symbol_table
+            .lookup("return")
+            .map(|symbol| symbol.type_.clone())
+        else {
+            return Err(SemanticError::new(
+                "return statement outside of function".to_string(),
+                span,
+            ));
+        };
+        let return_value_type = match &return_statement.value {
+            Some(return_value) => self.analyze_expression(return_value)?,
+            None => Type::Void,
+        };
+        if return_value_type != expected_return {
+            return Err(SemanticError::new(
+                format!(
+                    "return type mismatch: expected {}, got {}",
+                    expected_return, return_value_type
+                ),
+                span,
+            ));
+        }
+        Ok(true)
+    }
+
+    fn analyze_loop(&mut self, loop_statement: &LoopStatement) -> Result<bool, SemanticError> {
+        self.symbol_table.enter_scope();
+        let condition_type = self.analyze_expression(&loop_statement.condition)?;
+        if condition_type != Type::Bool {
+            return Err(SemanticError::new(
+                format!(
+                    "loop condition must resolve to bool, got {}",
+                    condition_type
+                ),
+                loop_statement.condition.span,
+            ));
+        }
+        let returned = self.analyze_body(&loop_statement.body)?;
+        self.symbol_table.exit_scope();
+        Ok(returned)
+    }
+
+    fn analyze_import(
+        &mut self,
+        import: &ImportStatement,
+        span: Span,
+    ) -> Result<bool, SemanticError> {
+        if self.symbol_table.tables.len() > 1 {
+            return Err(SemanticError::new(
+                "imports must be at the top level of a file".to_string(),
+                span,
+            ));
+        }
+        let Some(exports) = self.modules.get(&import.path).cloned() else {
+            return Err(SemanticError::new(
+                format!("unresolved import \"{}\"", import.path),
+                span,
+            ));
+        };
+        let names = match &import.names {
+            Some(names) => names.clone(),
+            None => exports.iter().map(|(name, _)| name.clone()).collect(),
+        };
+        for name in names {
+            let Some((_, symbol)) = exports.iter().find(|(export, _)| *export == name) else {
+                return Err(SemanticError::new(
+                    format!("{} has no public function {}", import.path, name),
+                    span,
+                ));
+            };
+            // Importing a function again, e.g. by importing its file twice, declares nothing new
+            if self.imported.get(&name) == Some(&symbol.span) {
+                continue;
             }
-            ast::Expression::VariableRef(variable_ref) => {
-                // Verify variable is already declared
-                let Some(symbol) = self.symbol_table.lookup(&variable_ref.name) else {
-                    return Err(SemanticError {
-                        message: format!("use of undefined variable {}", variable_ref.name),
-                        span: expression.span,
-                    });
-                };
-                Ok(symbol.clone().type_)
+            // Imported functions are declared by the import, as far as this file can tell
+            self.check_undeclared(&name, span)?;
+            self.symbol_table.add_function_signature(
+                name.clone(),
+                symbol.type_.clone(),
+                symbol.params.clone(),
+                Some(span),
+            );
+            self.record_declaration(&name);
+            self.imported.insert(name, symbol.span);
+        }
+        Ok(false)
+    }
+
+    fn analyze_variable_ref(
+        &mut self,
+        variable_ref: &VariableRef,
+        span: Span,
+    ) -> Result<Type, SemanticError> {
+        // Verify variable is already declared
+        let Some(symbol) = self.symbol_table.lookup(&variable_ref.name) else {
+            return Err(SemanticError::new(
+                format!("use of undefined variable {}", variable_ref.name),
+                span,
+            ));
+        };
+        if symbol.kind != SymbolKind::Variable {
+            return Err(SemanticError::new(
+                format!("{} is not a variable", variable_ref.name),
+                span,
+            ));
+        }
+        let type_ = symbol.type_.clone();
+        self.record_reference(&variable_ref.name, span);
+        Ok(type_)
+    }
+
+    fn analyze_function_call(
+        &mut self,
+        function_call: &FunctionCall,
+        span: Span,
+    ) -> Result<Type, SemanticError> {
+        // Verify function is already declared
+        let (expected_params, return_type, declared_at) = {
+            let Some(symbol) = self.symbol_table.lookup(&function_call.callee) else {
+                return Err(SemanticError::new(
+                    format!("use of undefined function {}", &function_call.callee),
+                    span,
+                ));
+            };
+            if symbol.kind != SymbolKind::Function {
+                return Err(SemanticError::new(
+                    format!("{} is not a function", &function_call.callee),
+                    span,
+                ));
             }
-            ast::Expression::FunctionCall(function_call) => {
-                // Verify function is already declared
-                let (expected_params, return_type) = {
-                    let Some(symbol) = self.symbol_table.lookup(&function_call.callee) else {
-                        return Err(SemanticError {
-                            message: format!("use of undefined function {}", &function_call.callee),
-                            span: expression.span,
-                        });
-                    };
-
-                    // Clone only what’s needed and drop the borrow immediately
-                    (symbol.params.clone(), symbol.type_.clone())
-                };
-
-                // Check for number of arguments
-                if function_call.args.len() != expected_params.len() {
-                    return Err(SemanticError {
-                        message: format!(
-                            "function {} expects {} arguments, got {}",
-                            function_call.callee,
-                            expected_params.len(),
-                            function_call.args.len()
-                        ),
-                        span: expression.span,
-                    });
-                }
-
-                // Check for type of arguments
-                for (index, param) in expected_params.iter().enumerate() {
-                    let arg_type = self.analyze_expression(&function_call.args[index])?;
-                    if arg_type != param.type_ && param.type_ != Type::Any {
-                        return Err(SemanticError {
-                            message: format!(
-                                "type mismatch: expected {}, got {}",
-                                param.type_, arg_type
-                            ),
-                            span: expression.span,
-                        });
-                    }
-                }
-                Ok(return_type)
+
+            // Clone only what’s needed and drop the borrow immediately
+            (symbol.params.clone(), symbol.type_.clone(), symbol.span)
+        };
+        self.record_reference(
+            &function_call.callee,
+            name_span(span, &function_call.callee),
+        );
+        let signature_note = || {
+            format!(
+                "expected signature: {}",
+                format_signature(&function_call.callee, &expected_params, &return_type)
+            )
+        };
+
+        // Check for number of arguments
+        if function_call.args.len() != expected_params.len() {
+            let mut err = SemanticError::new(
+                format!(
+                    "function {} expects {} arguments, got {}",
+                    function_call.callee,
+                    expected_params.len(),
+                    function_call.args.len()
+                ),
+                span,
+            )
+            .with_note(signature_note());
+            if let Some(span) = declared_at {
+                err = err.with_label(span, "function declared here".to_string());
             }
-            ast::Expression::Binary(binary_expression) => {
-                let left_type = self.analyze_expression(&binary_expression.left)?;
-                let right_type = self.analyze_expression(&binary_expression.right)?;
-                if left_type != right_type {
-                    return Err(SemanticError {
-                        message: format!(
-                            "type mismatch: expected {}, got {}",
-                            left_type, right_type
-                        ),
-                        span: expression.span,
-                    });
-                }
-                match binary_expression.operator {
-                    BinaryOperator::LessThan
-                    | BinaryOperator::LessThanOrEqual
-                    | BinaryOperator::GreaterThan
-                    | BinaryOperator::GreaterThanOrEqual
-                    | BinaryOperator::Equal
-                    | BinaryOperator::NotEqual => Ok(Type::Bool),
-                    _ => Ok(left_type),
-                }
+            return Err(err);
+        }
+
+        // Check for type of arguments
+        for (index, param) in expected_params.iter().enumerate() {
+            let arg_type = self.analyze_expression(&function_call.args[index])?;
+            if arg_type != param.type_ && param.type_ != Type::Any {
+                return Err(SemanticError::new(
+                    format!("type mismatch: expected {}, got {}", param.type_, arg_type),
+                    function_call.args[index].span,
+                )
+                .with_note(signature_note()));
             }
         }
+        Ok(return_type)
+    }
+
+    fn analyze_binary(
+        &mut self,
+        binary_expression: &BinaryExpression,
+        span: Span,
+    ) -> Result<Type, SemanticError> {
+        let left_type = self.analyze_expression(&binary_expression.left)?;
+        let right_type = self.analyze_expression(&binary_expression.right)?;
+        if left_type != right_type {
+            return Err(SemanticError::new(
+                format!("type mismatch: expected {}, got {}", left_type, right_type),
+                span,
+            ));
+        }
+        match binary_expression.operator {
+            BinaryOperator::LessThan
+            | BinaryOperator::LessThanOrEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterThanOrEqual
+            | BinaryOperator::Equal
+            | BinaryOperator::NotEqual => Ok(Type::Bool),
+            _ => Ok(left_type),
+        }
+    }
+}
+
+impl Analyzer {
+    // Fails if `name` is already declared in any enclosing scope. Built-in functions aren't
+    // reserved, so declaring one of their names shadows them. Globals set by the host are, since
+    // the host keeps setting them by name
+    fn check_undeclared(&self, name: &str, span: Span) -> Result<(), SemanticError> {
+        let Some(existing) = self.symbol_table.lookup(name) else {
+            return Ok(());
+        };
+        if existing.kind == SymbolKind::Function && existing.span.is_none() {
+            return Ok(());
+        }
+        let mut err = SemanticError::new(format!("duplicate declaration of {}", name), span);
+        if let Some(span) = existing.span {
+            err = err.with_label(span, "previously declared here".to_string());
+        }
+        Err(err)
+    }
+
+    // Adds the symbol just declared under `name` to the index
+    fn record_declaration(&mut self, name: &str) {
+        if let Some(symbol) = self.symbol_table.lookup(name) {
+            self.index.declarations.push(Declaration {
+                name: name.to_string(),
+                symbol: symbol.clone(),
+            });
+        }
     }
+
+    // Adds a use of `name` to the index, resolved in the current scope
+    fn record_reference(&mut self, name: &str, span: Span) {
+        if let Some(symbol) = self.symbol_table.lookup(name) {
+            self.index.references.push(Reference {
+                name: name.to_string(),
+                span,
+                symbol: symbol.clone(),
+            });
+        }
+    }
+}
+
+// The span of a name at the start of `span`. Names are single tokens, so they never span lines
+fn name_span(span: Span, name: &str) -> Span {
+    let mut end = span.start;
+    end.column += name.chars().count();
+    Span {
+        offset: span.offset,
+        len: name.len(),
+        start: span.start,
+        end,
+    }
+}
+
+// Formats a function signature the way it is written in source, e.g. `name(n: int): int`
+fn format_signature(name: &str, params: &[Parameter], return_type: &Type) -> String {
+    let params: Vec<String> = params
+        .iter()
+        .map(|param| format!("{}: {}", param.name, param.type_))
+        .collect();
+    format!("{}({}): {}", name, params.join(", "), return_type)
 }
 
 #[derive(Debug, PartialEq)]
 pub struct SemanticError {
     pub message: String,
     pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
 }
 
 impl SemanticError {
     pub fn new(message: String, span: Span) -> Self {
-        Self { message, span }
+        Self {
+            message,
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    // Attaches a secondary location to the error, e.g. where a symbol was first declared
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.labels.push(Label { span, message });
+        self
+    }
+
+    // Attaches extra context that has no location of its own
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
     }
 }
 
@@ -352,3 +712,94 @@ impl Display for SemanticError {
         write!(f, "SemanticError: {} (at {})", self.message, self.span)
     }
 }
+
+impl From<SemanticError> for PerryError {
+    fn from(err: SemanticError) -> Self {
+        PerryError::new(ErrorKind::Semantic, err.message, err.span)
+            .with_labels(err.labels)
+            .with_notes(err.notes)
+    }
+}
+
+// A built-in's name, named parameters and return type
+type BuiltInSignature = (&'static str, Vec<(&'static str, Type)>, Type);
+
+// The signature of every standard built-in
+fn built_in_signatures() -> Vec<BuiltInSignature> {
+    use Type::{Any, Bool, Int, String, Void};
+    vec![
+        ("print", vec![("arg", Any)], Void),
+        ("println", vec![("arg", Any)], Void),
+        // Strings
+        ("len", vec![("text", String)], Int),
+        (
+            "substring",
+            vec![("text", String), ("start", Int), ("end", Int)],
+            String,
+        ),
+        ("index_of", vec![("text", String), ("search", String)], Int),
+        ("contains", vec![("text", String), ("search", String)], Bool),
+        (
+            "starts_with",
+            vec![("text", String), ("prefix", String)],
+            Bool,
+        ),
+        (
+            "ends_with",
+            vec![("text", String), ("suffix", String)],
+            Bool,
+        ),
+        ("to_upper", vec![("text", String)], String),
+        ("to_lower", vec![("text", String)], String),
+        ("trim", vec![("text", String)], String),
+        (
+            "split_count",
+            vec![("text", String), ("separator", String)],
+            Int,
+        ),
+        (
+            "split_part",
+            vec![("text", String), ("separator", String), ("index", Int)],
+            String,
+        ),
+        ("repeat", vec![("text", String), ("count", Int)], String),
+        ("char_at", vec![("text", String), ("index", Int)], String),
+        // Conversions
+        ("to_string", vec![("value", Any)], String),
+        ("parse_int", vec![("text", String)], Int),
+        ("to_bool", vec![("value", Any)], Bool),
+        // Math
+        ("abs", vec![("n", Int)], Int),
+        ("min", vec![("a", Int), ("b", Int)], Int),
+        ("max", vec![("a", Int), ("b", Int)], Int),
+        ("pow", vec![("base", Int), ("exponent", Int)], Int),
+        ("isqrt", vec![("n", Int)], Int),
+        ("clamp", vec![("n", Int), ("low", Int), ("high", Int)], Int),
+        ("gcd", vec![("a", Int), ("b", Int)], Int),
+        ("sign", vec![("n", Int)], Int),
+        // Input
+        ("read_line", vec![], String),
+        ("input", vec![("prompt", String)], String),
+        ("read_all", vec![], String),
+        ("eof", vec![], Bool),
+        // Process
+        ("arg", vec![("index", Int)], String),
+        ("arg_count", vec![], Int),
+        ("exit", vec![("code", Int)], Void),
+        // Files
+        ("read_file", vec![("path", String)], String),
+        (
+            "write_file",
+            vec![("path", String), ("contents", String)],
+            Void,
+        ),
+        ("file_exists", vec![("path", String)], Bool),
+        // Assertions
+        ("assert", vec![("condition", Bool)], Void),
+        ("assert_eq", vec![("left", Any), ("right", Any)], Void),
+        ("assert_ne", vec![("left", Any), ("right", Any)], Void),
+        // Random
+        ("random_int", vec![("low", Int), ("high", Int)], Int),
+        ("seed", vec![("n", Int)], Void),
+    ]
+}
@@ -1,6 +1,6 @@
 #![cfg(test)]
 
-use crate::{parser::Parser, tokenizer::Tokenizer};
+use crate::{common::ast::Type, parser::Parser, tokenizer::Tokenizer};
 
 use super::Analyzer;
 
@@ -15,7 +15,7 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        Analyzer::new(&ast).analyze().unwrap();
+        Analyzer::new().analyze(&ast).unwrap();
     }
 
     #[test]
@@ -25,7 +25,7 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let result = Analyzer::new(&ast).analyze();
+        let result = Analyzer::new().analyze(&ast);
         assert!(result.is_err());
     }
 
@@ -36,10 +36,79 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let result = Analyzer::new(&ast).analyze();
+        let result = Analyzer::new().analyze(&ast);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn duplicate_declaration_points_at_original() {
+        let input = "var testvar: int = 1;\nvar testvar: int = 2;";
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse().unwrap();
+        let err = Analyzer::new().analyze(&ast).unwrap_err();
+        assert_eq!(err.labels.len(), 1);
+        assert_ne!(err.labels[0].span, err.span);
+        assert_eq!(err.labels[0].message, "previously declared here");
+    }
+
+    #[test]
+    fn duplicate_parameter_points_at_parameter() {
+        let input = "func f(a: int, n: int): void {\n    var n: int = 1;\n}";
+        let tokens = Tokenizer::new(input.to_string()).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let err = Analyzer::new().analyze(&ast).unwrap_err();
+        let label = err.labels[0].span;
+        assert_eq!(&input[label.offset..label.offset + label.len], "n: int");
+    }
+
+    #[test]
+    fn declarations_shadow_built_ins() {
+        let input = "func abs(n: string): string {\n    return n;\n}\nvar max: int = 1;\nvar s: string = abs(\"x\");";
+        let tokens = Tokenizer::new(input.to_string()).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&ast).unwrap();
+        // The user's abs replaced the built-in, and max is no longer a function
+        let (params, _) = analyzer.function_signature("abs").unwrap();
+        assert_eq!(params[0].name, "n");
+        assert!(analyzer.function_signature("max").is_none());
+        // Host globals are still reserved
+        let mut analyzer = Analyzer::new();
+        analyzer
+            .declare_global("limit".to_string(), Type::Int)
+            .unwrap();
+        let tokens = Tokenizer::new("var limit: int = 1;".to_string())
+            .tokenize()
+            .unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let err = analyzer.analyze(&ast).unwrap_err();
+        assert_eq!(err.message, "duplicate declaration of limit");
+    }
+
+    #[test]
+    // Functions can only be called, never read or assigned like variables
+    fn functions_used_as_variables() {
+        let cases = [
+            (
+                "func f(): int { return 1; } f = 2;",
+                "cannot assign to function f",
+            ),
+            (
+                "func f(): int { return 1; } var x: int = f;",
+                "f is not a variable",
+            ),
+            ("var y: int = abs;", "abs is not a variable"),
+        ];
+        for (input, message) in cases {
+            let tokens = Tokenizer::new(input.to_string()).tokenize().unwrap();
+            let ast = Parser::new(tokens).parse().unwrap();
+            let err = Analyzer::new().analyze(&ast).unwrap_err();
+            assert_eq!(err.message, message, "{}", input);
+        }
+    }
+
     #[test]
     fn undefined_variable_reference() {
         let input = r#"if(a == b) {}"#;
@@ -47,7 +116,7 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let result = Analyzer::new(&ast).analyze();
+        let result = Analyzer::new().analyze(&ast);
         assert!(result.is_err());
     }
 
@@ -63,7 +132,7 @@ mod tests {
             let tokens = tokenizer.tokenize().unwrap();
             let mut parser = Parser::new(tokens);
             let ast = parser.parse().unwrap();
-            let result = Analyzer::new(&ast).analyze();
+            let result = Analyzer::new().analyze(&ast);
             assert!(result.is_err());
         }
     }
@@ -79,8 +148,8 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        analyzer.analyze().unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&ast).unwrap();
     }
 
     #[test]
@@ -92,8 +161,8 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        analyzer.analyze().unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&ast).unwrap();
     }
 
     #[test]
@@ -105,7 +174,7 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let result = Analyzer::new(&ast).analyze();
+        let result = Analyzer::new().analyze(&ast);
         assert!(result.is_err());
     }
 
@@ -118,7 +187,7 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let result = Analyzer::new(&ast).analyze();
+        let result = Analyzer::new().analyze(&ast);
         assert!(result.is_err());
     }
 
@@ -130,7 +199,7 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let result = Analyzer::new(&ast).analyze();
+        let result = Analyzer::new().analyze(&ast);
         assert!(result.is_err());
     }
 
@@ -141,8 +210,8 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        let result = analyzer.analyze();
+        let mut analyzer = Analyzer::new();
+        let result = analyzer.analyze(&ast);
         assert!(result.is_err());
     }
 
@@ -155,8 +224,8 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        analyzer.analyze().unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&ast).unwrap();
     }
 
     #[test]
@@ -168,8 +237,8 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        let result = analyzer.analyze();
+        let mut analyzer = Analyzer::new();
+        let result = analyzer.analyze(&ast);
         assert!(result.is_err());
     }
 
@@ -183,8 +252,8 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        analyzer.analyze().unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&ast).unwrap();
     }
 
     #[test]
@@ -204,8 +273,8 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        analyzer.analyze().unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&ast).unwrap();
     }
 
     #[test]
@@ -218,11 +287,38 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        let result = analyzer.analyze();
+        let mut analyzer = Analyzer::new();
+        let result = analyzer.analyze(&ast);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn built_in_signatures() {
+        let valid = r#"var n: int = len(trim(" a ")) + index_of("abc", "c");
+        var b: bool = contains(to_upper("a"), substring("abc", 0, n));
+        var s: string = to_string(parse_int("1") + 1);
+        var t: bool = to_bool(to_string(s));
+        var m: int = clamp(pow(abs(n), 2), min(1, 2), max(gcd(4, 6), isqrt(sign(n))));"#;
+        let tokens = Tokenizer::new(valid.to_string()).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        Analyzer::new().analyze(&ast).unwrap();
+        let invalid = [
+            r#"len(1);"#,
+            r#"var s: int = to_lower("A");"#,
+            r#"char_at("abc");"#,
+            r#"var len: int = 1; len("a");"#,
+            r#"var n: int = to_string(1);"#,
+            r#"var b: bool = parse_int(true);"#,
+            r#"var m: int = max(1);"#,
+            r#"var m: bool = abs(1);"#,
+        ];
+        for input in invalid {
+            let tokens = Tokenizer::new(input.to_string()).tokenize().unwrap();
+            let ast = Parser::new(tokens).parse().unwrap();
+            assert!(Analyzer::new().analyze(&ast).is_err(), "{}", input);
+        }
+    }
+
     #[test]
     fn too_mant_args_function_call() {
         let input = r#"func name(n: int): int {
@@ -233,8 +329,8 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        let result = analyzer.analyze();
+        let mut analyzer = Analyzer::new();
+        let result = analyzer.analyze(&ast);
         assert!(result.is_err());
     }
 
@@ -248,8 +344,40 @@ mod tests {
         let tokens = tokenizer.tokenize().unwrap();
         let mut parser = Parser::new(tokens);
         let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        let result = analyzer.analyze();
+        let mut analyzer = Analyzer::new();
+        let result = analyzer.analyze(&ast);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn index_records_names() {
+        let input = "func f(n: int): int { return n; } var x: int = f(1); x = 2;";
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse().unwrap();
+        let mut analyzer = Analyzer::new();
+        analyzer.analyze(&ast).unwrap();
+        let index = analyzer.index();
+        let declared: Vec<&str> = index
+            .declarations
+            .iter()
+            .map(|declaration| declaration.name.as_str())
+            .collect();
+        assert_eq!(declared, vec!["f", "n", "x"]);
+        let referenced: Vec<(&str, &str)> = index
+            .references
+            .iter()
+            .map(|reference| {
+                let span = reference.span;
+                (
+                    reference.name.as_str(),
+                    &input[span.offset..span.offset + span.len],
+                )
+            })
+            .collect();
+        assert_eq!(referenced, vec![("n", "n"), ("f", "f"), ("x", "x")]);
+        // References point at the declaring statement
+        assert_eq!(index.references[1].symbol.span, Some(ast.body[0].span));
+    }
 }
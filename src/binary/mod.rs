@@ -0,0 +1,228 @@
+pub mod program;
+pub mod test;
+
+use std::fmt::Display;
+
+use crate::common::ast::Program;
+
+use program::{Decode, Encode};
+
+// Layout of a compiled program file: the magic number, the format version as a little-endian u16,
+// a checksum of the payload as a little-endian u32, then the encoded `Program`
+pub const MAGIC: [u8; 4] = *b"PRYB";
+pub const VERSION: u16 = 3;
+const HEADER_LEN: usize = 10;
+
+// Nodes nested deeper than this are rejected rather than risking the decoder's stack
+const MAX_DEPTH: usize = 512;
+
+// Why a file could not be decoded, with the byte offset where decoding stopped
+#[derive(Debug, PartialEq, Clone)]
+pub struct DecodeError {
+    pub message: String,
+    pub offset: usize,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+// Encodes an analyzed program, spans included
+pub fn encode_program(program: &Program) -> Vec<u8> {
+    let mut payload = Writer::default();
+    program.encode(&mut payload);
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.bytes.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&checksum(&payload.bytes).to_le_bytes());
+    bytes.extend_from_slice(&payload.bytes);
+    bytes
+}
+
+// Decodes a file written by `encode_program`. The header is checked before anything else, so
+// files from other tools or other versions fail with a message saying so
+pub fn decode_program(bytes: &[u8]) -> Result<Program, DecodeError> {
+    if bytes.len() < MAGIC.len() || bytes[..MAGIC.len()] != MAGIC {
+        return Err(DecodeError {
+            message: "not a compiled perry program".to_string(),
+            offset: 0,
+        });
+    }
+    if bytes.len() < HEADER_LEN {
+        return Err(DecodeError {
+            message: "truncated header".to_string(),
+            offset: bytes.len(),
+        });
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != VERSION {
+        return Err(DecodeError {
+            message: format!(
+                "unsupported format version {} (expected {}), rebuild the program",
+                version, VERSION
+            ),
+            offset: 4,
+        });
+    }
+    let expected = u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]);
+    let payload = &bytes[HEADER_LEN..];
+    if checksum(payload) != expected {
+        return Err(DecodeError {
+            message: "checksum mismatch, the file is corrupt".to_string(),
+            offset: 6,
+        });
+    }
+    let mut reader = Reader {
+        bytes,
+        position: HEADER_LEN,
+        depth: 0,
+    };
+    let program = Program::decode(&mut reader)?;
+    if reader.position != bytes.len() {
+        return Err(reader.error("trailing bytes after program"));
+    }
+    Ok(program)
+}
+
+// FNV-1a, which is enough to catch truncation and flipped bits
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+// Appends primitive values. Unsigned integers are LEB128 varints, since most spans are small
+#[derive(Default)]
+pub struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn usize(&mut self, mut value: usize) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    pub fn i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn str(&mut self, value: &str) {
+        self.usize(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+}
+
+// Reads primitive values back, failing instead of reading past the end
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl Reader<'_> {
+    pub fn error(&self, message: impl Into<String>) -> DecodeError {
+        DecodeError {
+            message: message.into(),
+            offset: self.position,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], DecodeError> {
+        if self.bytes.len() - self.position < len {
+            return Err(self.error("unexpected end of file"));
+        }
+        let bytes = &self.bytes[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn usize(&mut self) -> Result<usize, DecodeError> {
+        let start = self.position;
+        let mut value: usize = 0;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.u8()?;
+            let bits = (byte & 0x7f) as usize;
+            if bits << shift >> shift != bits {
+                break;
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        self.position = start;
+        Err(self.error("integer out of range"))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, DecodeError> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, DecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => {
+                self.position -= 1;
+                Err(self.error(format!("invalid bool {}", byte)))
+            }
+        }
+    }
+
+    pub fn string(&mut self) -> Result<String, DecodeError> {
+        let len = self.usize()?;
+        let start = self.position;
+        let bytes = self.take(len)?.to_vec();
+        String::from_utf8(bytes).map_err(|_| DecodeError {
+            message: "invalid UTF-8 in string".to_string(),
+            offset: start,
+        })
+    }
+
+    // Reads a tag byte, failing with the name of the enum if it isn't below `count`
+    pub fn tag(&mut self, what: &str, count: u8) -> Result<u8, DecodeError> {
+        let tag = self.u8()?;
+        if tag >= count {
+            self.position -= 1;
+            return Err(self.error(format!("invalid {} tag {}", what, tag)));
+        }
+        Ok(tag)
+    }
+
+    // Decodes a nested node, bounding how deep nodes may nest
+    pub fn nested<T>(
+        &mut self,
+        decode: impl FnOnce(&mut Self) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nodes nested too deeply"));
+        }
+        self.depth += 1;
+        let result = decode(self);
+        self.depth -= 1;
+        result
+    }
+}
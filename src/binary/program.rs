@@ -0,0 +1,391 @@
+use crate::common::{
+    ast::{
+        BinaryExpression, Expression, FunctionCall, FunctionDecl, IfStatement, ImportStatement,
+        LiteralExpression, LiteralValue, LoopStatement, Parameter, Position, Program,
+        ReturnStatement, Span, Spannable, SpannedExpression, SpannedStatement, Statement, Type,
+        VariableAssignment, VariableDecl, VariableRef,
+    },
+    token::BinaryOperator,
+};
+
+use super::{DecodeError, Reader, Writer};
+
+// The binary encoding of the syntax tree. Enums are a tag byte followed by their fields in
+// declaration order. Tags are positions in the tables below, so reordering or removing an entry
+// changes the format and needs a new `VERSION`
+
+const TYPES: [Type; 5] = [Type::Void, Type::Bool, Type::Int, Type::String, Type::Any];
+
+const BINARY_OPERATORS: [BinaryOperator; 11] = [
+    BinaryOperator::Add,
+    BinaryOperator::Subtract,
+    BinaryOperator::Multiply,
+    BinaryOperator::Divide,
+    BinaryOperator::Modulo,
+    BinaryOperator::NotEqual,
+    BinaryOperator::Equal,
+    BinaryOperator::GreaterThan,
+    BinaryOperator::LessThan,
+    BinaryOperator::GreaterThanOrEqual,
+    BinaryOperator::LessThanOrEqual,
+];
+
+pub trait Encode {
+    fn encode(&self, writer: &mut Writer);
+}
+
+pub trait Decode: Sized {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError>;
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, writer: &mut Writer) {
+        writer.usize(self.len());
+        for item in self {
+            item.encode(writer);
+        }
+    }
+}
+
+// Items are read one by one rather than preallocated, so a corrupt length fails at the end of
+// the file instead of allocating
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let len = reader.usize()?;
+        let mut items = Vec::new();
+        for _ in 0..len {
+            items.push(T::decode(reader)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, writer: &mut Writer) {
+        writer.bool(self.is_some());
+        if let Some(value) = self {
+            value.encode(writer);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(if reader.bool()? {
+            Some(T::decode(reader)?)
+        } else {
+            None
+        })
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    fn encode(&self, writer: &mut Writer) {
+        (**self).encode(writer);
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        T::decode(reader).map(Box::new)
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, writer: &mut Writer) {
+        writer.str(self);
+    }
+}
+
+impl Decode for String {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.string()
+    }
+}
+
+impl Encode for Program {
+    fn encode(&self, writer: &mut Writer) {
+        self.body.encode(writer);
+    }
+}
+
+impl Decode for Program {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Program {
+            body: Vec::decode(reader)?,
+        })
+    }
+}
+
+impl Encode for Position {
+    fn encode(&self, writer: &mut Writer) {
+        writer.usize(self.line);
+        writer.usize(self.column);
+    }
+}
+
+impl Decode for Position {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Position {
+            line: reader.usize()?,
+            column: reader.usize()?,
+        })
+    }
+}
+
+impl Encode for Span {
+    fn encode(&self, writer: &mut Writer) {
+        writer.usize(self.offset);
+        writer.usize(self.len);
+        self.start.encode(writer);
+        self.end.encode(writer);
+    }
+}
+
+impl Decode for Span {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Span {
+            offset: reader.usize()?,
+            len: reader.usize()?,
+            start: Position::decode(reader)?,
+            end: Position::decode(reader)?,
+        })
+    }
+}
+
+impl Encode for Type {
+    fn encode(&self, writer: &mut Writer) {
+        writer.u8(tag_of(&TYPES, self));
+    }
+}
+
+impl Decode for Type {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(TYPES[reader.tag("type", TYPES.len() as u8)? as usize].clone())
+    }
+}
+
+impl Encode for BinaryOperator {
+    fn encode(&self, writer: &mut Writer) {
+        writer.u8(tag_of(&BINARY_OPERATORS, self));
+    }
+}
+
+impl Decode for BinaryOperator {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let tag = reader.tag("operator", BINARY_OPERATORS.len() as u8)?;
+        Ok(BINARY_OPERATORS[tag as usize])
+    }
+}
+
+impl Encode for LiteralValue {
+    fn encode(&self, writer: &mut Writer) {
+        match self {
+            LiteralValue::String(value) => {
+                writer.u8(0);
+                writer.str(value);
+            }
+            LiteralValue::Number(value) => {
+                writer.u8(1);
+                writer.i32(*value);
+            }
+            LiteralValue::Bool(value) => {
+                writer.u8(2);
+                writer.bool(*value);
+            }
+            LiteralValue::Void => writer.u8(3),
+        }
+    }
+}
+
+impl Decode for LiteralValue {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(match reader.tag("literal", 4)? {
+            0 => LiteralValue::String(reader.string()?),
+            1 => LiteralValue::Number(reader.i32()?),
+            2 => LiteralValue::Bool(reader.bool()?),
+            _ => LiteralValue::Void,
+        })
+    }
+}
+
+impl Encode for Parameter {
+    fn encode(&self, writer: &mut Writer) {
+        writer.str(&self.name);
+        self.type_.encode(writer);
+        self.span.encode(writer);
+    }
+}
+
+impl Decode for Parameter {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Parameter {
+            name: reader.string()?,
+            type_: Type::decode(reader)?,
+            span: Span::decode(reader)?,
+        })
+    }
+}
+
+impl Encode for SpannedStatement {
+    fn encode(&self, writer: &mut Writer) {
+        self.span.encode(writer);
+        match &self.node {
+            Statement::Function(func) => {
+                writer.u8(0);
+                writer.str(&func.name);
+                func.params.encode(writer);
+                func.type_.encode(writer);
+                func.body.encode(writer);
+                writer.bool(func.public);
+            }
+            Statement::VarAssignment(var_assignment) => {
+                writer.u8(1);
+                writer.str(&var_assignment.name);
+                var_assignment.value.encode(writer);
+            }
+            Statement::VarDecl(var_decl) => {
+                writer.u8(2);
+                writer.str(&var_decl.name);
+                var_decl.value.encode(writer);
+                var_decl.type_.encode(writer);
+            }
+            Statement::If(if_statement) => {
+                writer.u8(3);
+                if_statement.condition.encode(writer);
+                if_statement.then_body.encode(writer);
+                if_statement.else_body.encode(writer);
+            }
+            Statement::Loop(loop_statement) => {
+                writer.u8(4);
+                loop_statement.condition.encode(writer);
+                loop_statement.body.encode(writer);
+            }
+            Statement::Return(return_statement) => {
+                writer.u8(5);
+                return_statement.value.encode(writer);
+            }
+            // The expression shares the statement's span
+            Statement::Expr(expression) => {
+                writer.u8(6);
+                encode_expression(expression, writer);
+            }
+            Statement::Import(import) => {
+                writer.u8(7);
+                writer.str(&import.path);
+                import.names.encode(writer);
+            }
+        }
+    }
+}
+
+impl Decode for SpannedStatement {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        reader.nested(|reader| {
+            let span = Span::decode(reader)?;
+            let statement = match reader.tag("statement", 8)? {
+                0 => Statement::Function(FunctionDecl {
+                    name: reader.string()?,
+                    params: Vec::decode(reader)?,
+                    type_: Type::decode(reader)?,
+                    body: Vec::decode(reader)?,
+                    public: reader.bool()?,
+                }),
+                1 => Statement::VarAssignment(VariableAssignment {
+                    name: reader.string()?,
+                    value: SpannedExpression::decode(reader)?,
+                }),
+                2 => Statement::VarDecl(VariableDecl {
+                    name: reader.string()?,
+                    value: SpannedExpression::decode(reader)?,
+                    type_: Type::decode(reader)?,
+                }),
+                3 => Statement::If(IfStatement {
+                    condition: SpannedExpression::decode(reader)?,
+                    then_body: Vec::decode(reader)?,
+                    else_body: Option::decode(reader)?,
+                }),
+                4 => Statement::Loop(LoopStatement {
+                    condition: SpannedExpression::decode(reader)?,
+                    body: Vec::decode(reader)?,
+                }),
+                5 => Statement::Return(ReturnStatement {
+                    value: Option::decode(reader)?,
+                }),
+                6 => Statement::Expr(decode_expression(reader)?),
+                _ => Statement::Import(ImportStatement {
+                    path: reader.string()?,
+                    names: Option::decode(reader)?,
+                }),
+            };
+            Ok(statement.spanned(span))
+        })
+    }
+}
+
+impl Encode for SpannedExpression {
+    fn encode(&self, writer: &mut Writer) {
+        self.span.encode(writer);
+        encode_expression(&self.node, writer);
+    }
+}
+
+impl Decode for SpannedExpression {
+    fn decode(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let span = Span::decode(reader)?;
+        Ok(decode_expression(reader)?.spanned(span))
+    }
+}
+
+fn encode_expression(expression: &Expression, writer: &mut Writer) {
+    match expression {
+        Expression::Binary(binary) => {
+            writer.u8(0);
+            binary.left.encode(writer);
+            binary.right.encode(writer);
+            binary.operator.encode(writer);
+        }
+        Expression::Literal(literal) => {
+            writer.u8(1);
+            literal.value.encode(writer);
+        }
+        Expression::FunctionCall(call) => {
+            writer.u8(2);
+            writer.str(&call.callee);
+            call.args.encode(writer);
+        }
+        Expression::VariableRef(var_ref) => {
+            writer.u8(3);
+            writer.str(&var_ref.name);
+        }
+    }
+}
+
+fn decode_expression(reader: &mut Reader) -> Result<Expression, DecodeError> {
+    reader.nested(|reader| {
+        Ok(match reader.tag("expression", 4)? {
+            0 => Expression::Binary(Box::new(BinaryExpression {
+                left: SpannedExpression::decode(reader)?,
+                right: SpannedExpression::decode(reader)?,
+                operator: BinaryOperator::decode(reader)?,
+            })),
+            1 => Expression::Literal(LiteralExpression {
+                value: LiteralValue::decode(reader)?,
+            }),
+            2 => Expression::FunctionCall(Box::new(FunctionCall {
+                callee: reader.string()?,
+                args: Vec::decode(reader)?,
+            })),
+            _ => Expression::VariableRef(Box::new(VariableRef {
+                name: reader.string()?,
+            })),
+        })
+    })
+}
+
+fn tag_of<T: PartialEq>(table: &[T], value: &T) -> u8 {
+    table
+        .iter()
+        .position(|entry| entry == value)
+        .expect("every variant is in its table") as u8
+}
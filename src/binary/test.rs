@@ -0,0 +1,131 @@
+#![cfg(test)]
+
+use super::*;
+use crate::interpreter::test::run_with_output;
+use crate::interpreter::{Interpreter, OutputBuffer};
+use crate::pipeline::{build_source, check_source};
+
+const EXAMPLES: [&str; 4] = [
+    include_str!("../../working_examples/complex.pry"),
+    include_str!("../../working_examples/factorial.pry"),
+    include_str!("../../working_examples/fizzbuzz.pry"),
+    include_str!("../json/golden/program.pry"),
+];
+
+// Builds a file around a payload with a valid header, as if it had been written by a bad encoder
+pub fn with_header(payload: &[u8]) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&checksum(payload).to_le_bytes());
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for source in EXAMPLES {
+            let program = check_source(source).unwrap();
+            let bytes = encode_program(&program);
+            assert_eq!(&bytes[..4], b"PRYB");
+            assert_eq!(decode_program(&bytes).unwrap().body, program.body);
+        }
+    }
+
+    #[test]
+    fn built_program_runs() {
+        for source in EXAMPLES {
+            let program = decode_program(&build_source(source).unwrap()).unwrap();
+            let output = OutputBuffer::new();
+            let mut interpreter =
+                Interpreter::with_io(Box::new(output.clone()), Box::new(&b""[..]));
+            interpreter.execute(&program).unwrap();
+            assert_eq!(output.contents(), run_with_output(source));
+        }
+    }
+
+    #[test]
+    fn varints() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as usize, usize::MAX] {
+            let mut writer = Writer::default();
+            writer.usize(value);
+            let mut reader = Reader {
+                bytes: &writer.bytes,
+                position: 0,
+                depth: 0,
+            };
+            assert_eq!(reader.usize().unwrap(), value);
+            assert_eq!(reader.position, writer.bytes.len());
+        }
+    }
+
+    #[test]
+    fn reject_bad_headers() {
+        let bytes = build_source("println(1);").unwrap();
+        let cases = vec![
+            (
+                b"println(1);".to_vec(),
+                "not a compiled perry program at byte 0",
+            ),
+            (bytes[..7].to_vec(), "truncated header at byte 7"),
+            (
+                [&bytes[..4], &[9, 0], &bytes[6..]].concat(),
+                "unsupported format version 9 (expected 3), rebuild the program at byte 4",
+            ),
+            (
+                [&bytes[..bytes.len() - 1], b"2"].concat(),
+                "checksum mismatch, the file is corrupt at byte 6",
+            ),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(decode_program(&bytes).unwrap_err().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn reject_bad_payloads() {
+        let cases = vec![
+            (vec![], "unexpected end of file at byte 10"),
+            (vec![0, 0], "trailing bytes after program at byte 11"),
+            (
+                vec![1, 0, 0, 0, 0, 0, 0, 9],
+                "invalid statement tag 9 at byte 17",
+            ),
+            (vec![0xff; 11], "integer out of range at byte 10"),
+        ];
+        for (payload, expected) in cases {
+            let err = decode_program(&with_header(&payload)).unwrap_err();
+            assert_eq!(err.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn reject_deep_nesting() {
+        // A return of a binary expression whose left operand is another binary expression, and so on
+        let mut payload = vec![1, 0, 0, 0, 0, 0, 0, 5, 1];
+        for _ in 0..MAX_DEPTH {
+            payload.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0]);
+        }
+        let err = decode_program(&with_header(&payload)).unwrap_err();
+        assert_eq!(err.message, "nodes nested too deeply");
+    }
+
+    #[test]
+    // Truncated or altered payloads fail to decode instead of panicking, even with a valid checksum
+    fn corrupt_payloads_never_panic() {
+        let bytes = encode_program(&check_source(EXAMPLES[0]).unwrap());
+        let payload = &bytes[HEADER_LEN..];
+        for len in 0..payload.len() {
+            assert!(decode_program(&with_header(&payload[..len])).is_err());
+        }
+        for index in 0..payload.len() {
+            for value in [0x00, 0x01, 0x07, 0x7f, 0x80, 0xff] {
+                let mut altered = payload.to_vec();
+                altered[index] = value;
+                let _ = decode_program(&with_header(&altered));
+            }
+        }
+    }
+}
@@ -0,0 +1,522 @@
+pub mod test;
+pub mod tree;
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+
+use perry::{
+    ErrorKind, PerryError, Repl, format_source, lsp,
+    pipeline::{self, RunOptions},
+    testing::{self, TestOutcome},
+};
+
+pub const USAGE: &str = "Usage: perry [command] [<file> | -e <code> | -] [args...]
+
+Commands:
+  run     Run a program (default). Files ending in .pbc are loaded as built programs
+          --json         Read a syntax tree written by `ast --json` instead of source
+          --allow-files  Let the program read and write files under its directory
+  build   Check a program and write it in a binary form that runs without re-parsing
+          -o <file>  Where to write it, by default the input file with a .pbc extension
+  check   Tokenize, parse and analyze a program without running it
+          --json   Read a syntax tree written by `ast --json` instead of source
+  tokens  Print the tokens of a program
+          --json   Print them as JSON
+  ast     Print the syntax tree of a program
+          --json   Print it as JSON
+  fmt     Format a program. Files are rewritten in place, other input is printed
+          --check  Only report whether the program is formatted
+  test    Run the test_ functions in a file, or in every .pry file under a directory
+  lsp     Start a language server speaking LSP over stdin and stdout
+
+Without arguments, perry starts an interactive session.
+Source is read from <file>, from <code> after -e, or from stdin with -.
+Arguments after the input are passed to the program being run.";
+
+// Extension of programs written by `build`
+pub const BUILT_EXTENSION: &str = "pbc";
+
+// Exit codes. Failing programs exit with the code of the stage that rejected them
+pub const EXIT_IO: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_UNFORMATTED: i32 = 7;
+pub const EXIT_TESTS_FAILED: i32 = 8;
+
+pub fn exit_code(kind: &ErrorKind) -> i32 {
+    match kind {
+        ErrorKind::Lexical => 3,
+        ErrorKind::Syntax => 4,
+        ErrorKind::Semantic => 5,
+        ErrorKind::Runtime => 6,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run {
+        json: bool,
+        args: Vec<String>,
+        allow_files: bool, // Lets the program access files under its own directory
+    },
+    Build {
+        output: Option<String>,
+    },
+    Check {
+        json: bool,
+    },
+    Tokens {
+        json: bool,
+    },
+    Ast {
+        json: bool,
+    },
+    Fmt {
+        check: bool,
+    },
+    Test,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    File(String),
+    Inline(String),
+    Stdin,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Invocation {
+    Repl,
+    Lsp,
+    Command(Command, Input),
+}
+
+// Parses the arguments after the program name. A bare input without a command is run
+pub fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    let Some(first) = args.first() else {
+        return Ok(Invocation::Repl);
+    };
+    if first == "lsp" {
+        return match &args[1..] {
+            [] => Ok(Invocation::Lsp),
+            rest => Err(format!("unexpected arguments: {}", rest.join(" "))),
+        };
+    }
+    let (name, rest) = match first.as_str() {
+        "run" | "build" | "check" | "tokens" | "ast" | "fmt" | "test" => {
+            (first.as_str(), &args[1..])
+        }
+        _ => ("run", args),
+    };
+    // Everything after the input of `run` is passed to the program
+    let (rest, program_args) = match name {
+        "run" => rest.split_at(input_end(rest)),
+        _ => (rest, &[][..]),
+    };
+    let (options, mut rest): (Vec<String>, Vec<String>) =
+        rest.iter().cloned().partition(|arg| arg.starts_with("--"));
+    let mut output = None;
+    if name == "build"
+        && let Some(index) = rest.iter().position(|arg| arg == "-o")
+    {
+        if index + 1 == rest.len() {
+            return Err("-o expects a file".to_string());
+        }
+        output = Some(rest.remove(index + 1));
+        rest.remove(index);
+    }
+    let allowed: &[&str] = match name {
+        "fmt" => &["--check"],
+        "build" | "test" => &[],
+        "run" => &["--json", "--allow-files"],
+        _ => &["--json"],
+    };
+    if let Some(option) = options
+        .iter()
+        .find(|option| !allowed.contains(&option.as_str()))
+    {
+        return Err(format!("unknown option {} for {}", option, name));
+    }
+    let flag = options.iter().any(|option| option != "--allow-files");
+    let command = match name {
+        "check" => Command::Check { json: flag },
+        "tokens" => Command::Tokens { json: flag },
+        "ast" => Command::Ast { json: flag },
+        "fmt" => Command::Fmt { check: flag },
+        "build" => Command::Build { output },
+        "test" => Command::Test,
+        _ => Command::Run {
+            json: flag,
+            args: program_args.to_vec(),
+            allow_files: options.iter().any(|option| option == "--allow-files"),
+        },
+    };
+    let input = match rest.as_slice() {
+        [flag, code] if flag == "-e" => Input::Inline(code.clone()),
+        [flag] if flag == "-e" => return Err("-e expects source code".to_string()),
+        [dash] if dash == "-" => Input::Stdin,
+        [path] if !path.starts_with('-') => Input::File(path.clone()),
+        [] => return Err("missing input".to_string()),
+        _ => return Err(format!("unexpected arguments: {}", rest.join(" "))),
+    };
+    if command == Command::Test && !matches!(input, Input::File(_)) {
+        return Err("test expects a file or directory".to_string());
+    }
+    Ok(Invocation::Command(command, input))
+}
+
+// Finds where the input ends in the arguments of a command: after the code following -e, or
+// after the first argument that isn't an option
+fn input_end(args: &[String]) -> usize {
+    for (index, arg) in args.iter().enumerate() {
+        match arg.as_str() {
+            "-e" => return (index + 2).min(args.len()),
+            arg if arg.starts_with("--") => continue,
+            _ => return index + 1,
+        }
+    }
+    args.len()
+}
+
+// Runs the CLI and returns the process exit code
+pub fn main(args: &[String]) -> i32 {
+    let invocation = match parse_args(args) {
+        Ok(invocation) => invocation,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    let (command, input) = match invocation {
+        Invocation::Repl => {
+            let color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+            return match Repl::new().run(color) {
+                Ok(code) => code,
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    EXIT_IO
+                }
+            };
+        }
+        Invocation::Lsp => {
+            return match lsp::run(io::stdin().lock(), io::stdout().lock()) {
+                Ok(()) => 0,
+                Err(err) => {
+                    eprintln!("error: {}", err);
+                    EXIT_IO
+                }
+            };
+        }
+        Invocation::Command(command, input) => (command, input),
+    };
+    if let (Command::Run { json: false, .. }, Input::File(path)) = (&command, &input)
+        && Path::new(path).extension() == Some(OsStr::new(BUILT_EXTENSION))
+    {
+        return run_built(path, &run_options(&command, Path::new(path)));
+    }
+    if let (Command::Test, Input::File(path)) = (&command, &input) {
+        return test(Path::new(path));
+    }
+
+    let source = match read_input(&input) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("error: cannot read input: {}", err);
+            return EXIT_IO;
+        }
+    };
+    if let Command::Build { output } = &command {
+        return build(&source, &input, output.as_deref());
+    }
+    if let Command::Run { json, .. } = &command {
+        let options = run_options(&command, input_path(&input));
+        let result = if *json {
+            pipeline::run_ast(&source, &options)
+        } else {
+            pipeline::run_file(input_path(&input), &source, &options)
+        };
+        return match result {
+            Ok(code) => code,
+            Err(errors) => report(&errors, &source, *json),
+        };
+    }
+    let output = match run_command(&command, input_path(&input), &source) {
+        Ok(output) => output,
+        Err(errors) => {
+            let reads_json = matches!(command, Command::Check { json: true });
+            return report(&errors, &source, reads_json);
+        }
+    };
+    match (&command, &input) {
+        (Command::Fmt { check: true }, _) => {
+            if output == source {
+                0
+            } else {
+                eprintln!("{} is not formatted", input_name(&input));
+                EXIT_UNFORMATTED
+            }
+        }
+        (Command::Fmt { check: false }, Input::File(path)) if output != source => {
+            match fs::write(path, output) {
+                Ok(()) => 0,
+                Err(err) => {
+                    eprintln!("error: cannot write {}: {}", path, err);
+                    EXIT_IO
+                }
+            }
+        }
+        (Command::Fmt { check: false }, Input::File(_)) => 0,
+        _ => {
+            print!("{}", output);
+            0
+        }
+    }
+}
+
+// The settings a run command gives the program at `path`. Files can be accessed under the
+// directory of the input file, or the current directory for other input
+fn run_options(command: &Command, path: &Path) -> RunOptions {
+    let Command::Run {
+        args, allow_files, ..
+    } = command
+    else {
+        return RunOptions::default();
+    };
+    let directory = path.parent().unwrap_or(Path::new(""));
+    RunOptions {
+        args: args.clone(),
+        file_root: allow_files.then(|| {
+            if directory.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                directory.to_path_buf()
+            }
+        }),
+    }
+}
+
+// Prints errors against the source they point into and returns the exit code for them
+fn report(errors: &[PerryError], source: &str, reads_json: bool) -> i32 {
+    let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    for err in errors {
+        // Only load errors point into the JSON. The spans in a loaded tree point into source we
+        // don't have
+        if reads_json && err.kind != ErrorKind::Syntax {
+            eprintln!("{}", err);
+        } else {
+            eprint!("{}", render_error(err, source, color));
+        }
+    }
+    errors.first().map_or(0, |err| exit_code(&err.kind))
+}
+
+// Runs a command on source code and returns what it prints on success. Imports are resolved
+// relative to `path`
+pub fn run_command(
+    command: &Command,
+    path: &Path,
+    source: &str,
+) -> Result<String, Vec<PerryError>> {
+    match command {
+        Command::Run { json: false, .. } => {
+            pipeline::run_file(path, source, &run_options(command, path)).map(|_| String::new())
+        }
+        Command::Run { json: true, .. } => {
+            pipeline::run_ast(source, &run_options(command, path)).map(|_| String::new())
+        }
+        Command::Check { json: false } => pipeline::check_file(path, source).map(|_| String::new()),
+        Command::Check { json: true } => pipeline::check_ast(source).map(|_| String::new()),
+        Command::Tokens { json: true } => {
+            let tokens = pipeline::tokenize_source(source)?;
+            Ok(format!("{}\n", pipeline::tokens_to_json(&tokens)))
+        }
+        Command::Tokens { json: false } => {
+            let tokens = pipeline::tokenize_source(source)?;
+            Ok(tokens
+                .iter()
+                .map(|token| {
+                    let (start, end) = (token.span.start, token.span.end);
+                    format!(
+                        "{}:{}-{}:{}\t{:?}\n",
+                        start.line, start.column, end.line, end.column, token.node
+                    )
+                })
+                .collect())
+        }
+        Command::Ast { json: false } => {
+            pipeline::parse_source(source).map(|program| tree::render_tree(&program))
+        }
+        Command::Ast { json: true } => pipeline::parse_source(source)
+            .map(|program| format!("{}\n", pipeline::ast_to_json(&program))),
+        Command::Fmt { .. } => format_source(source),
+        Command::Build { .. } | Command::Test => {
+            pipeline::check_file(path, source).map(|_| String::new())
+        }
+    }
+}
+
+// Writes the built form of a program next to its source, or to `output`
+fn build(source: &str, input: &Input, output: Option<&str>) -> i32 {
+    let path = match (output, input) {
+        (Some(output), _) => PathBuf::from(output),
+        (None, Input::File(path)) => Path::new(path).with_extension(BUILT_EXTENSION),
+        (None, _) => {
+            eprintln!(
+                "error: -o is required when building from {}",
+                input_name(input)
+            );
+            return EXIT_USAGE;
+        }
+    };
+    let bytes = match pipeline::build_file(input_path(input), source) {
+        Ok(bytes) => bytes,
+        Err(errors) => {
+            let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+            for err in &errors {
+                eprint!("{}", render_error(err, source, color));
+            }
+            return errors.first().map_or(0, |err| exit_code(&err.kind));
+        }
+    };
+    match fs::write(&path, bytes) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: cannot write {}: {}", path.display(), err);
+            EXIT_IO
+        }
+    }
+}
+
+// Runs a program written by `build`. Its spans point into source we don't have, so errors are
+// printed without a snippet
+fn run_built(path: &str, options: &RunOptions) -> i32 {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("error: cannot read input: {}", err);
+            return EXIT_IO;
+        }
+    };
+    let program = match pipeline::load_built(&bytes) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("error: cannot load {}: {}", path, err);
+            return EXIT_IO;
+        }
+    };
+    match pipeline::run_built(&program, options) {
+        Ok(code) => code,
+        Err(errors) => {
+            for err in &errors {
+                eprintln!("{}", err);
+            }
+            errors.first().map_or(0, |err| exit_code(&err.kind))
+        }
+    }
+}
+
+// Runs the tests in a file or directory and prints a line for each, then what failing tests
+// printed and their errors, then a summary
+fn test(path: &Path) -> i32 {
+    let files = match testing::find_test_files(path) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("error: cannot read {}: {}", path.display(), err);
+            return EXIT_IO;
+        }
+    };
+    let mut outcomes = Vec::new();
+    for file in files {
+        match fs::read_to_string(&file) {
+            Ok(source) => outcomes.extend(testing::run_tests(&file, &source)),
+            Err(err) => {
+                eprintln!("error: cannot read {}: {}", file.display(), err);
+                return EXIT_IO;
+            }
+        }
+    }
+    let color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+    print!("{}", test_report(&outcomes, color));
+    if outcomes.iter().all(TestOutcome::passed) {
+        0
+    } else {
+        EXIT_TESTS_FAILED
+    }
+}
+
+// Lays out test results the way `cargo test` does
+fn test_report(outcomes: &[TestOutcome], color: bool) -> String {
+    let mut report = format!("running {} tests\n", outcomes.len());
+    for outcome in outcomes {
+        let status = if outcome.passed() { "ok" } else { "FAILED" };
+        report += &format!("test {} ... {}\n", outcome.name, status);
+    }
+    let failures: Vec<_> = outcomes
+        .iter()
+        .filter(|outcome| !outcome.passed())
+        .collect();
+    if !failures.is_empty() {
+        report += "\nfailures:\n";
+        for outcome in &failures {
+            report += &format!("\n---- {} ----\n", outcome.name);
+            report += &outcome.output;
+            if !outcome.output.is_empty() && !outcome.output.ends_with('\n') {
+                report.push('\n');
+            }
+            for err in &outcome.errors {
+                report += &render_error(err, "", color);
+            }
+        }
+    }
+    report += &format!(
+        "\ntest result: {}. {} passed; {} failed\n",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        outcomes.len() - failures.len(),
+        failures.len()
+    );
+    report
+}
+
+// Renders an error against the source it points into, which for errors in imported files is
+// the imported file
+fn render_error(err: &PerryError, source: &str, color: bool) -> String {
+    match &err.file {
+        Some(file) => match fs::read_to_string(file) {
+            Ok(imported) => err.render(&imported, color),
+            Err(_) => format!("{}\n", err),
+        },
+        None => err.render(source, color),
+    }
+}
+
+// The path imports are resolved against. Source that isn't from a file imports from the current
+// directory
+fn input_path(input: &Input) -> &Path {
+    match input {
+        Input::File(path) => Path::new(path),
+        _ => Path::new(pipeline::INLINE_PATH),
+    }
+}
+
+// Names the input in messages
+fn input_name(input: &Input) -> &str {
+    match input {
+        Input::File(path) => path,
+        Input::Inline(_) => "<inline source>",
+        Input::Stdin => "<stdin>",
+    }
+}
+
+fn read_input(input: &Input) -> io::Result<String> {
+    match input {
+        Input::File(path) => fs::read_to_string(path),
+        Input::Inline(code) => Ok(code.clone()),
+        Input::Stdin => {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content)?;
+            Ok(content)
+        }
+    }
+}
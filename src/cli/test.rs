@@ -0,0 +1,293 @@
+#![cfg(test)]
+
+use super::*;
+
+// Splits a command line into owned arguments
+pub fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(str::to_string).collect()
+}
+
+// The path of source given inline
+pub fn inline() -> &'static Path {
+    Path::new(pipeline::INLINE_PATH)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        let cases = vec![
+            ("", Invocation::Repl),
+            ("lsp", Invocation::Lsp),
+            (
+                "main.pry",
+                Invocation::Command(
+                    Command::Run {
+                        json: false,
+                        args: vec![],
+                        allow_files: false,
+                    },
+                    Input::File("main.pry".to_string()),
+                ),
+            ),
+            (
+                "run main.pry",
+                Invocation::Command(
+                    Command::Run {
+                        json: false,
+                        args: vec![],
+                        allow_files: false,
+                    },
+                    Input::File("main.pry".to_string()),
+                ),
+            ),
+            (
+                "check -",
+                Invocation::Command(Command::Check { json: false }, Input::Stdin),
+            ),
+            (
+                "tokens -e x",
+                Invocation::Command(
+                    Command::Tokens { json: false },
+                    Input::Inline("x".to_string()),
+                ),
+            ),
+            (
+                "ast main.pry",
+                Invocation::Command(
+                    Command::Ast { json: false },
+                    Input::File("main.pry".to_string()),
+                ),
+            ),
+            (
+                "ast --json main.pry",
+                Invocation::Command(
+                    Command::Ast { json: true },
+                    Input::File("main.pry".to_string()),
+                ),
+            ),
+            (
+                "run --json -",
+                Invocation::Command(
+                    Command::Run {
+                        json: true,
+                        args: vec![],
+                        allow_files: false,
+                    },
+                    Input::Stdin,
+                ),
+            ),
+            (
+                "fmt --check main.pry",
+                Invocation::Command(
+                    Command::Fmt { check: true },
+                    Input::File("main.pry".to_string()),
+                ),
+            ),
+            (
+                "build main.pry",
+                Invocation::Command(
+                    Command::Build { output: None },
+                    Input::File("main.pry".to_string()),
+                ),
+            ),
+            (
+                "build -o out.pbc -e x",
+                Invocation::Command(
+                    Command::Build {
+                        output: Some("out.pbc".to_string()),
+                    },
+                    Input::Inline("x".to_string()),
+                ),
+            ),
+            // Arguments after the input belong to the program, options included
+            (
+                "main.pry a --json b.pry",
+                Invocation::Command(
+                    Command::Run {
+                        json: false,
+                        args: args("a --json b.pry"),
+                        allow_files: false,
+                    },
+                    Input::File("main.pry".to_string()),
+                ),
+            ),
+            (
+                "run --json -e x -e 1",
+                Invocation::Command(
+                    Command::Run {
+                        json: true,
+                        args: args("-e 1"),
+                        allow_files: false,
+                    },
+                    Input::Inline("x".to_string()),
+                ),
+            ),
+            (
+                "run --allow-files main.pry --allow-files",
+                Invocation::Command(
+                    Command::Run {
+                        json: false,
+                        args: args("--allow-files"),
+                        allow_files: true,
+                    },
+                    Input::File("main.pry".to_string()),
+                ),
+            ),
+            (
+                "fmt -",
+                Invocation::Command(Command::Fmt { check: false }, Input::Stdin),
+            ),
+            (
+                "test tests/",
+                Invocation::Command(Command::Test, Input::File("tests/".to_string())),
+            ),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parse_args(&args(line)).unwrap(), expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn parse_invalid_args() {
+        for line in [
+            "run",
+            "lsp x",
+            "check -e",
+            "check a.pry b.pry",
+            "run -o out.pbc main.pry a",
+            "check --allow-files main.pry",
+            "ast --verbose",
+            "fmt --json main.pry",
+            "run --check main.pry",
+            "build main.pry -o",
+            "build --json main.pry",
+            "run -o out.pbc main.pry",
+            "test",
+            "test -e x",
+            "test -",
+            "test --json tests",
+            "test a.pry b.pry",
+        ] {
+            assert!(parse_args(&args(line)).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn exit_codes_per_stage() {
+        let cases = vec![
+            (r#"var x: int = 1 ? 2;"#, 3),
+            (r#"var x: int = ;"#, 4),
+            (r#"var x: string = 1;"#, 5),
+        ];
+        for (source, expected) in cases {
+            let errors =
+                run_command(&Command::Check { json: false }, inline(), source).unwrap_err();
+            assert_eq!(exit_code(&errors[0].kind), expected, "{}", source);
+        }
+        assert_eq!(exit_code(&ErrorKind::Runtime), 6);
+    }
+
+    #[test]
+    fn check_does_not_run() {
+        assert_eq!(
+            run_command(
+                &Command::Check { json: false },
+                inline(),
+                r#"println("hi");"#
+            )
+            .unwrap(),
+            ""
+        );
+    }
+
+    #[test]
+    fn dump_tokens() {
+        let output = run_command(
+            &Command::Tokens { json: false },
+            inline(),
+            "var x: int = 1;",
+        )
+        .unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "1:1-1:4\tIdentifier(\"var\")");
+        assert_eq!(lines[5], "1:14-1:15\tNumericLiteral(1)");
+        assert_eq!(lines.last(), Some(&"1:16-1:16\tEOF"));
+    }
+
+    #[test]
+    fn print_ast() {
+        let source = r#"func f(n: int): int { if (n > 1) { return n * 2; } else { println("no"); } return 0; }"#;
+        let expected = r#"Program
+  Function f(n: int): int
+    If
+      Condition
+        Binary >
+          Variable n
+          Literal 1
+      Then
+        Return
+          Binary *
+            Variable n
+            Literal 2
+      Else
+        If
+          Condition
+            Literal true
+          Then
+            Expr
+              Call println
+                Literal "no"
+    Return
+      Literal 0
+"#;
+        assert_eq!(
+            run_command(&Command::Ast { json: false }, inline(), source).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn report_tests() {
+        let directory = std::env::temp_dir().join(format!("perry-cli-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("math.pry");
+        let source = r#"func test_add(): void {
+    assert_eq(1 + 1, 2);
+}
+
+func test_sub(): void {
+    print("checking");
+    assert_eq(2 - 1, 2);
+}
+"#;
+        fs::write(&path, source).unwrap();
+        let report = test_report(&testing::run_tests(&path, source), false);
+        let name = path.display();
+        let expected = format!(
+            r#"running 2 tests
+test {name}::test_add ... ok
+test {name}::test_sub ... FAILED
+
+failures:
+
+---- {name}::test_sub ----
+checking
+RuntimeError[E04]: assert_eq failed: 1 != 2
+ --> {name}:7:5
+  |
+7 |     assert_eq(2 - 1, 2);
+  |     ^^^^^^^^^^^^^^^^^^^^
+
+test result: FAILED. 1 passed; 1 failed
+"#
+        );
+        assert_eq!(report, expected);
+        assert_eq!(
+            test_report(&[], false),
+            "running 0 tests\n\ntest result: ok. 0 passed; 0 failed\n"
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+use perry::ast::{
+    self, BinaryExpression, FunctionCall, FunctionDecl, IfStatement, ImportStatement,
+    LiteralExpression, LiteralValue, LoopStatement, Program, ReturnStatement, Span, Statement,
+    VariableAssignment, VariableDecl, VariableRef, Visitor,
+};
+
+// Renders a program as an indented tree, one node per line
+pub fn render_tree(program: &Program) -> String {
+    let mut printer = TreePrinter::default();
+    printer.line("Program");
+    printer.nested(|printer| printer.visit_program(program));
+    printer.out
+}
+
+#[derive(Default)]
+struct TreePrinter {
+    out: String,
+    depth: usize,
+}
+
+impl TreePrinter {
+    fn line(&mut self, text: &str) {
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    // Prints whatever `f` prints one level deeper
+    fn nested(&mut self, f: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        f(self);
+        self.depth -= 1;
+    }
+
+    // Prints a labelled group of child nodes
+    fn group(&mut self, label: &str, f: impl FnOnce(&mut Self)) {
+        self.line(label);
+        self.nested(f);
+    }
+}
+
+impl Visitor for TreePrinter {
+    fn visit_statement(&mut self, statement: &Statement, span: Span) {
+        if let Statement::Expr(expression) = statement {
+            self.line("Expr");
+            self.nested(|printer| printer.visit_expression(expression, span));
+        } else {
+            ast::walk_statement(self, statement, span);
+        }
+    }
+
+    fn visit_function_decl(&mut self, func: &FunctionDecl, span: Span) {
+        let params = func
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, param.type_))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let visibility = if func.public { "pub " } else { "" };
+        self.line(&format!(
+            "{}Function {}({}): {}",
+            visibility, func.name, params, func.type_
+        ));
+        self.nested(|printer| ast::walk_function_decl(printer, func, span));
+    }
+
+    fn visit_import(&mut self, import: &ImportStatement, _span: Span) {
+        match &import.names {
+            Some(names) => self.line(&format!(
+                "Import {} from {:?}",
+                names.join(", "),
+                import.path
+            )),
+            None => self.line(&format!("Import {:?}", import.path)),
+        }
+    }
+
+    fn visit_var_decl(&mut self, var_decl: &VariableDecl, span: Span) {
+        self.line(&format!("VarDecl {}: {}", var_decl.name, var_decl.type_));
+        self.nested(|printer| ast::walk_var_decl(printer, var_decl, span));
+    }
+
+    fn visit_var_assignment(&mut self, var_assignment: &VariableAssignment, span: Span) {
+        self.line(&format!("VarAssignment {}", var_assignment.name));
+        self.nested(|printer| ast::walk_var_assignment(printer, var_assignment, span));
+    }
+
+    fn visit_if(&mut self, if_statement: &IfStatement, _span: Span) {
+        self.line("If");
+        self.nested(|printer| {
+            let condition = &if_statement.condition;
+            printer.group("Condition", |printer| {
+                printer.visit_expression(&condition.node, condition.span)
+            });
+            printer.group("Then", |printer| {
+                ast::walk_statements(printer, &if_statement.then_body)
+            });
+            if let Some(else_body) = &if_statement.else_body {
+                printer.group("Else", |printer| {
+                    printer.visit_statement(&else_body.node, else_body.span)
+                });
+            }
+        });
+    }
+
+    fn visit_loop(&mut self, loop_statement: &LoopStatement, _span: Span) {
+        self.line("While");
+        self.nested(|printer| {
+            let condition = &loop_statement.condition;
+            printer.group("Condition", |printer| {
+                printer.visit_expression(&condition.node, condition.span)
+            });
+            printer.group("Body", |printer| {
+                ast::walk_statements(printer, &loop_statement.body)
+            });
+        });
+    }
+
+    fn visit_return(&mut self, return_statement: &ReturnStatement, span: Span) {
+        self.line("Return");
+        self.nested(|printer| ast::walk_return(printer, return_statement, span));
+    }
+
+    fn visit_binary(&mut self, binary: &BinaryExpression, span: Span) {
+        self.line(&format!("Binary {}", binary.operator));
+        self.nested(|printer| ast::walk_binary(printer, binary, span));
+    }
+
+    fn visit_literal(&mut self, literal: &LiteralExpression, _span: Span) {
+        match &literal.value {
+            LiteralValue::String(value) => self.line(&format!("Literal {:?}", value)),
+            value => self.line(&format!("Literal {}", value)),
+        }
+    }
+
+    fn visit_function_call(&mut self, call: &FunctionCall, span: Span) {
+        self.line(&format!("Call {}", call.callee));
+        self.nested(|printer| ast::walk_function_call(printer, call, span));
+    }
+
+    fn visit_variable_ref(&mut self, var_ref: &VariableRef, _span: Span) {
+        self.line(&format!("Variable {}", var_ref.name));
+    }
+}
@@ -8,12 +8,34 @@ pub struct Program {
     pub body: Vec<SpannedStatement>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Span {
+// A 1-based line and column in the source. Columns count characters, not bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, PartialOrd, Ord)]
+pub struct Position {
     pub line: usize,
     pub column: usize,
 }
 
+// A range of source text. `start` is the first character and `end` is just past the last one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub offset: usize, // Byte offset of the first character
+    pub len: usize,    // Length in bytes
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    // Returns a span from the start of this span to the end of `other`
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            offset: self.offset,
+            len: (other.offset + other.len).saturating_sub(self.offset),
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct WithSpan<T> {
     pub node: T,
@@ -26,15 +48,15 @@ pub trait Spannable: Sized {
     }
 }
 
-impl<T> WithSpan<T> {
-    pub fn as_ref(&self) -> &T {
+impl<T> AsRef<T> for WithSpan<T> {
+    fn as_ref(&self) -> &T {
         &self.node
     }
 }
 
 impl Display for Span {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}:{}", self.line, self.column)
+        write!(f, "{}:{}", self.start.line, self.start.column)
     }
 }
 
@@ -107,6 +129,7 @@ pub enum Statement {
     Loop(LoopStatement),
     Return(ReturnStatement),
     Expr(Expression), // Basically just for void function calls
+    Import(ImportStatement),
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -128,12 +151,14 @@ pub struct FunctionDecl {
     pub params: Vec<Parameter>,
     pub type_: Type,
     pub body: Vec<SpannedStatement>,
+    pub public: bool, // Declared with `pub`, so other files can import it
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Parameter {
     pub name: String,
     pub type_: Type,
+    pub span: Span, // From the name to the end of the type
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -195,3 +220,343 @@ pub struct LoopStatement {
 pub struct ReturnStatement {
     pub value: Option<SpannedExpression>,
 }
+
+// `import "path";` brings in every public function of a file, `import { a, b } from "path";` only
+// the named ones. The path is relative to the importing file
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImportStatement {
+    pub path: String,
+    pub names: Option<Vec<String>>,
+}
+
+// Read-only traversal of the syntax tree. Every method defaults to walking the node's children, so
+// a pass only overrides the nodes it cares about and calls the matching `walk_*` function to keep
+// descending
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement, span: Span) {
+        walk_statement(self, statement, span);
+    }
+
+    fn visit_function_decl(&mut self, func: &FunctionDecl, span: Span) {
+        walk_function_decl(self, func, span);
+    }
+
+    fn visit_parameter(&mut self, _param: &Parameter, _span: Span) {}
+
+    fn visit_var_decl(&mut self, var_decl: &VariableDecl, span: Span) {
+        walk_var_decl(self, var_decl, span);
+    }
+
+    fn visit_var_assignment(&mut self, var_assignment: &VariableAssignment, span: Span) {
+        walk_var_assignment(self, var_assignment, span);
+    }
+
+    fn visit_if(&mut self, if_statement: &IfStatement, span: Span) {
+        walk_if(self, if_statement, span);
+    }
+
+    fn visit_loop(&mut self, loop_statement: &LoopStatement, span: Span) {
+        walk_loop(self, loop_statement, span);
+    }
+
+    fn visit_import(&mut self, _import: &ImportStatement, _span: Span) {}
+
+    fn visit_return(&mut self, return_statement: &ReturnStatement, span: Span) {
+        walk_return(self, return_statement, span);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression, span: Span) {
+        walk_expression(self, expression, span);
+    }
+
+    fn visit_binary(&mut self, binary: &BinaryExpression, span: Span) {
+        walk_binary(self, binary, span);
+    }
+
+    fn visit_literal(&mut self, _literal: &LiteralExpression, _span: Span) {}
+
+    fn visit_function_call(&mut self, call: &FunctionCall, span: Span) {
+        walk_function_call(self, call, span);
+    }
+
+    fn visit_variable_ref(&mut self, _var_ref: &VariableRef, _span: Span) {}
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    walk_statements(visitor, &program.body);
+}
+
+pub fn walk_statements<V: Visitor + ?Sized>(visitor: &mut V, statements: &[SpannedStatement]) {
+    for statement in statements {
+        visitor.visit_statement(&statement.node, statement.span);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement, span: Span) {
+    match statement {
+        Statement::Function(func) => visitor.visit_function_decl(func, span),
+        Statement::VarDecl(var_decl) => visitor.visit_var_decl(var_decl, span),
+        Statement::VarAssignment(var_assignment) => {
+            visitor.visit_var_assignment(var_assignment, span)
+        }
+        Statement::If(if_statement) => visitor.visit_if(if_statement, span),
+        Statement::Loop(loop_statement) => visitor.visit_loop(loop_statement, span),
+        Statement::Return(return_statement) => visitor.visit_return(return_statement, span),
+        // An expression statement shares its span with the expression
+        Statement::Expr(expression) => visitor.visit_expression(expression, span),
+        Statement::Import(import) => visitor.visit_import(import, span),
+    }
+}
+
+pub fn walk_function_decl<V: Visitor + ?Sized>(visitor: &mut V, func: &FunctionDecl, _span: Span) {
+    for param in &func.params {
+        visitor.visit_parameter(param, param.span);
+    }
+    walk_statements(visitor, &func.body);
+}
+
+pub fn walk_var_decl<V: Visitor + ?Sized>(visitor: &mut V, var_decl: &VariableDecl, _span: Span) {
+    visitor.visit_expression(&var_decl.value.node, var_decl.value.span);
+}
+
+pub fn walk_var_assignment<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    var_assignment: &VariableAssignment,
+    _span: Span,
+) {
+    visitor.visit_expression(&var_assignment.value.node, var_assignment.value.span);
+}
+
+pub fn walk_if<V: Visitor + ?Sized>(visitor: &mut V, if_statement: &IfStatement, _span: Span) {
+    let condition = &if_statement.condition;
+    visitor.visit_expression(&condition.node, condition.span);
+    walk_statements(visitor, &if_statement.then_body);
+    if let Some(else_body) = &if_statement.else_body {
+        visitor.visit_statement(&else_body.node, else_body.span);
+    }
+}
+
+pub fn walk_loop<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    loop_statement: &LoopStatement,
+    _span: Span,
+) {
+    let condition = &loop_statement.condition;
+    visitor.visit_expression(&condition.node, condition.span);
+    walk_statements(visitor, &loop_statement.body);
+}
+
+pub fn walk_return<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    return_statement: &ReturnStatement,
+    _span: Span,
+) {
+    if let Some(value) = &return_statement.value {
+        visitor.visit_expression(&value.node, value.span);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression, span: Span) {
+    match expression {
+        Expression::Binary(binary) => visitor.visit_binary(binary, span),
+        Expression::Literal(literal) => visitor.visit_literal(literal, span),
+        Expression::FunctionCall(call) => visitor.visit_function_call(call, span),
+        Expression::VariableRef(var_ref) => visitor.visit_variable_ref(var_ref, span),
+    }
+}
+
+pub fn walk_binary<V: Visitor + ?Sized>(visitor: &mut V, binary: &BinaryExpression, _span: Span) {
+    visitor.visit_expression(&binary.left.node, binary.left.span);
+    visitor.visit_expression(&binary.right.node, binary.right.span);
+}
+
+pub fn walk_function_call<V: Visitor + ?Sized>(visitor: &mut V, call: &FunctionCall, _span: Span) {
+    for arg in &call.args {
+        visitor.visit_expression(&arg.node, arg.span);
+    }
+}
+
+// Traversal that can rewrite the tree in place, e.g. to fold constants or rename variables. A
+// method may replace its whole node through the `&mut` reference; spans are passed by value
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Statement, span: Span) {
+        walk_statement_mut(self, statement, span);
+    }
+
+    fn visit_function_decl_mut(&mut self, func: &mut FunctionDecl, span: Span) {
+        walk_function_decl_mut(self, func, span);
+    }
+
+    fn visit_parameter_mut(&mut self, _param: &mut Parameter, _span: Span) {}
+
+    fn visit_var_decl_mut(&mut self, var_decl: &mut VariableDecl, span: Span) {
+        walk_var_decl_mut(self, var_decl, span);
+    }
+
+    fn visit_var_assignment_mut(&mut self, var_assignment: &mut VariableAssignment, span: Span) {
+        walk_var_assignment_mut(self, var_assignment, span);
+    }
+
+    fn visit_if_mut(&mut self, if_statement: &mut IfStatement, span: Span) {
+        walk_if_mut(self, if_statement, span);
+    }
+
+    fn visit_loop_mut(&mut self, loop_statement: &mut LoopStatement, span: Span) {
+        walk_loop_mut(self, loop_statement, span);
+    }
+
+    fn visit_import_mut(&mut self, _import: &mut ImportStatement, _span: Span) {}
+
+    fn visit_return_mut(&mut self, return_statement: &mut ReturnStatement, span: Span) {
+        walk_return_mut(self, return_statement, span);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression, span: Span) {
+        walk_expression_mut(self, expression, span);
+    }
+
+    fn visit_binary_mut(&mut self, binary: &mut BinaryExpression, span: Span) {
+        walk_binary_mut(self, binary, span);
+    }
+
+    fn visit_literal_mut(&mut self, _literal: &mut LiteralExpression, _span: Span) {}
+
+    fn visit_function_call_mut(&mut self, call: &mut FunctionCall, span: Span) {
+        walk_function_call_mut(self, call, span);
+    }
+
+    fn visit_variable_ref_mut(&mut self, _var_ref: &mut VariableRef, _span: Span) {}
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    walk_statements_mut(visitor, &mut program.body);
+}
+
+pub fn walk_statements_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    statements: &mut [SpannedStatement],
+) {
+    for statement in statements {
+        visitor.visit_statement_mut(&mut statement.node, statement.span);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    statement: &mut Statement,
+    span: Span,
+) {
+    match statement {
+        Statement::Function(func) => visitor.visit_function_decl_mut(func, span),
+        Statement::VarDecl(var_decl) => visitor.visit_var_decl_mut(var_decl, span),
+        Statement::VarAssignment(var_assignment) => {
+            visitor.visit_var_assignment_mut(var_assignment, span)
+        }
+        Statement::If(if_statement) => visitor.visit_if_mut(if_statement, span),
+        Statement::Loop(loop_statement) => visitor.visit_loop_mut(loop_statement, span),
+        Statement::Return(return_statement) => visitor.visit_return_mut(return_statement, span),
+        Statement::Expr(expression) => visitor.visit_expression_mut(expression, span),
+        Statement::Import(import) => visitor.visit_import_mut(import, span),
+    }
+}
+
+pub fn walk_function_decl_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    func: &mut FunctionDecl,
+    _span: Span,
+) {
+    for param in &mut func.params {
+        let span = param.span;
+        visitor.visit_parameter_mut(param, span);
+    }
+    walk_statements_mut(visitor, &mut func.body);
+}
+
+pub fn walk_var_decl_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    var_decl: &mut VariableDecl,
+    _span: Span,
+) {
+    visitor.visit_expression_mut(&mut var_decl.value.node, var_decl.value.span);
+}
+
+pub fn walk_var_assignment_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    var_assignment: &mut VariableAssignment,
+    _span: Span,
+) {
+    visitor.visit_expression_mut(&mut var_assignment.value.node, var_assignment.value.span);
+}
+
+pub fn walk_if_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    if_statement: &mut IfStatement,
+    _span: Span,
+) {
+    let condition = &mut if_statement.condition;
+    visitor.visit_expression_mut(&mut condition.node, condition.span);
+    walk_statements_mut(visitor, &mut if_statement.then_body);
+    if let Some(else_body) = &mut if_statement.else_body {
+        visitor.visit_statement_mut(&mut else_body.node, else_body.span);
+    }
+}
+
+pub fn walk_loop_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    loop_statement: &mut LoopStatement,
+    _span: Span,
+) {
+    let condition = &mut loop_statement.condition;
+    visitor.visit_expression_mut(&mut condition.node, condition.span);
+    walk_statements_mut(visitor, &mut loop_statement.body);
+}
+
+pub fn walk_return_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    return_statement: &mut ReturnStatement,
+    _span: Span,
+) {
+    if let Some(value) = &mut return_statement.value {
+        visitor.visit_expression_mut(&mut value.node, value.span);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    expression: &mut Expression,
+    span: Span,
+) {
+    match expression {
+        Expression::Binary(binary) => visitor.visit_binary_mut(binary, span),
+        Expression::Literal(literal) => visitor.visit_literal_mut(literal, span),
+        Expression::FunctionCall(call) => visitor.visit_function_call_mut(call, span),
+        Expression::VariableRef(var_ref) => visitor.visit_variable_ref_mut(var_ref, span),
+    }
+}
+
+pub fn walk_binary_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    binary: &mut BinaryExpression,
+    _span: Span,
+) {
+    visitor.visit_expression_mut(&mut binary.left.node, binary.left.span);
+    visitor.visit_expression_mut(&mut binary.right.node, binary.right.span);
+}
+
+pub fn walk_function_call_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    call: &mut FunctionCall,
+    _span: Span,
+) {
+    for arg in &mut call.args {
+        visitor.visit_expression_mut(&mut arg.node, arg.span);
+    }
+}
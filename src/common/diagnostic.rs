@@ -0,0 +1,127 @@
+use std::fmt::Write;
+
+use super::{ast::Span, error::PerryError};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// A secondary location attached to an error, e.g. "declared here"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl PerryError {
+    // Renders the error with the offending source lines and a caret under each span.
+    // Secondary labels are underlined with dashes. ANSI colours are only used if `color` is set
+    pub fn render(&self, source: &str, color: bool) -> String {
+        let paint = |style: &'static str| if color { style } else { "" };
+        let reset = paint(RESET);
+        let lines: Vec<&str> = source.lines().collect();
+
+        // Primary span first, then secondary labels, ordered by line for display
+        let mut markers: Vec<(Span, char, &str, &str)> = vec![(self.span, '^', "", paint(RED))];
+        for label in &self.labels {
+            markers.push((label.span, '-', &label.message, paint(BLUE)));
+        }
+        markers.sort_by_key(|(span, ..)| span.start);
+
+        let gutter_width = markers
+            .iter()
+            .map(|(span, ..)| span.start.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let blank_gutter = " ".repeat(gutter_width);
+
+        let mut output = String::new();
+        let _ = writeln!(
+            output,
+            "{}{}[{}]{}: {}{}{}",
+            paint(RED),
+            self.kind,
+            self.code(),
+            reset,
+            paint(BOLD),
+            self.message,
+            reset
+        );
+        let _ = writeln!(
+            output,
+            "{}{}-->{} {}",
+            blank_gutter,
+            paint(BLUE),
+            reset,
+            self.location()
+        );
+        let _ = writeln!(output, "{} {}|{}", blank_gutter, paint(BLUE), reset);
+
+        let mut last_line = None;
+        for (span, marker, message, style) in markers {
+            // Spans past the end of the input (e.g. EOF) point just after the last line
+            let line_text = lines
+                .get(span.start.line.saturating_sub(1))
+                .copied()
+                .unwrap_or("");
+            if last_line != Some(span.start.line) {
+                let _ = writeln!(
+                    output,
+                    "{}{:>gutter_width$} |{} {}",
+                    paint(BLUE),
+                    span.start.line,
+                    reset,
+                    line_text
+                );
+                last_line = Some(span.start.line);
+            }
+            let (padding, width) = underline_range(line_text, &span);
+            let _ = write!(
+                output,
+                "{} {}|{} {}{}{}{}",
+                blank_gutter,
+                paint(BLUE),
+                reset,
+                padding,
+                style,
+                marker.to_string().repeat(width),
+                reset
+            );
+            if !message.is_empty() {
+                let _ = write!(output, " {}{}{}", style, message, reset);
+            }
+            output.push('\n');
+        }
+        for note in &self.notes {
+            let _ = writeln!(
+                output,
+                "{} {}={} note: {}",
+                blank_gutter,
+                paint(BLUE),
+                reset,
+                note
+            );
+        }
+        output
+    }
+}
+
+// Returns the whitespace leading up to a span on its line and the width of its underline.
+// Tabs are kept so the underline stays aligned with the source line. Spans covering several
+// lines are underlined to the end of their first line
+fn underline_range(line_text: &str, span: &Span) -> (String, usize) {
+    let start_column = span.start.column.max(1);
+    let padding = line_text
+        .chars()
+        .chain(std::iter::repeat(' '))
+        .take(start_column - 1)
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+    let end_column = if span.end.line == span.start.line {
+        span.end.column
+    } else {
+        line_text.chars().count() + 1
+    };
+    (padding, end_column.saturating_sub(start_column).max(1))
+}
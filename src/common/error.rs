@@ -0,0 +1,102 @@
+use std::fmt::Display;
+
+use super::{ast::Span, diagnostic::Label};
+
+// The stage of the pipeline an error came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Lexical,
+    Syntax,
+    Semantic,
+    Runtime,
+}
+
+impl ErrorKind {
+    // Stable identifier printed alongside the error so it can be looked up or matched on
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::Lexical => "E01",
+            ErrorKind::Syntax => "E02",
+            ErrorKind::Semantic => "E03",
+            ErrorKind::Runtime => "E04",
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Lexical => write!(f, "LexicalError"),
+            ErrorKind::Syntax => write!(f, "SyntaxError"),
+            ErrorKind::Semantic => write!(f, "SemanticError"),
+            ErrorKind::Runtime => write!(f, "RuntimeError"),
+        }
+    }
+}
+
+// A single error type covering every stage, so callers driving the whole pipeline only have to
+// handle one thing. Each stage's own error converts into it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerryError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub file: Option<String>, // The imported file the spans point into, None for the input itself
+}
+
+impl PerryError {
+    pub fn new(kind: ErrorKind, message: String, span: Span) -> Self {
+        Self {
+            kind,
+            message,
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            file: None,
+        }
+    }
+
+    pub fn with_labels(mut self, labels: Vec<Label>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn with_notes(mut self, notes: Vec<String>) -> Self {
+        self.notes = notes;
+        self
+    }
+
+    pub fn with_file(mut self, file: String) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    // Where the error is, e.g. `1:5` or `lib/math.pry:1:5`
+    pub fn location(&self) -> String {
+        match &self.file {
+            Some(file) => format!("{}:{}", file, self.span),
+            None => self.span.to_string(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+}
+
+impl Display for PerryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {} (at {})",
+            self.kind,
+            self.code(),
+            self.message,
+            self.location()
+        )
+    }
+}
+
+impl std::error::Error for PerryError {}
@@ -1,2 +1,5 @@
 pub mod ast;
+pub mod diagnostic;
+pub mod error;
+pub mod test;
 pub mod token;
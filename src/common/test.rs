@@ -0,0 +1,208 @@
+#![cfg(test)]
+
+use super::ast::*;
+use super::diagnostic::*;
+use super::error::*;
+use super::token::BinaryOperator;
+use crate::formatter::format_program;
+use crate::pipeline::parse_source;
+
+// Builds the span between two 1-based (line, column) positions of an ASCII source, with `end`
+// pointing just past the last character
+pub fn span(source: &str, start: (usize, usize), end: (usize, usize)) -> Span {
+    let offset_of = |(line, column): (usize, usize)| {
+        let line_start: usize = source
+            .split_inclusive('\n')
+            .take(line - 1)
+            .map(|line| line.len())
+            .sum();
+        line_start + column - 1
+    };
+    Span {
+        offset: offset_of(start),
+        len: offset_of(end) - offset_of(start),
+        start: Position {
+            line: start.0,
+            column: start.1,
+        },
+        end: Position {
+            line: end.0,
+            column: end.1,
+        },
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostic_caret_under_span() {
+        let source = "var x: int = 1;\nprint(\"Missing paren\";";
+        let diagnostic = PerryError::new(
+            ErrorKind::Syntax,
+            "expected RightParen, got Semicolon".to_string(),
+            span(source, (2, 22), (2, 23)),
+        );
+        let expected = "SyntaxError[E02]: expected RightParen, got Semicolon
+ --> 2:22
+  |
+2 | print(\"Missing paren\";
+  |                      ^
+";
+        assert_eq!(diagnostic.render(source, false), expected);
+    }
+
+    #[test]
+    fn diagnostic_secondary_label() {
+        let source = "var x: int = 1;\nvar x: int = 2;";
+        let diagnostic = PerryError::new(
+            ErrorKind::Semantic,
+            "duplicate declaration of x".to_string(),
+            span(source, (2, 1), (2, 16)),
+        )
+        .with_labels(vec![Label {
+            span: span(source, (1, 1), (1, 16)),
+            message: "previously declared here".to_string(),
+        }]);
+        let expected = "SemanticError[E03]: duplicate declaration of x
+ --> 2:1
+  |
+1 | var x: int = 1;
+  | --------------- previously declared here
+2 | var x: int = 2;
+  | ^^^^^^^^^^^^^^^
+";
+        assert_eq!(diagnostic.render(source, false), expected);
+    }
+
+    #[test]
+    fn diagnostic_notes() {
+        let source = "f(1);";
+        let diagnostic = PerryError::new(
+            ErrorKind::Semantic,
+            "function f expects 0 arguments, got 1".to_string(),
+            span(source, (1, 1), (1, 5)),
+        )
+        .with_notes(vec!["expected signature: f(): void".to_string()]);
+        let expected = "SemanticError[E03]: function f expects 0 arguments, got 1
+ --> 1:1
+  |
+1 | f(1);
+  | ^^^^
+  = note: expected signature: f(): void
+";
+        assert_eq!(diagnostic.render(source, false), expected);
+    }
+
+    #[test]
+    fn diagnostic_color() {
+        let diagnostic = PerryError::new(
+            ErrorKind::Runtime,
+            "use of undefined function f".to_string(),
+            span("f();", (1, 1), (1, 4)),
+        );
+        assert!(diagnostic.render("f();", true).contains("\x1b[1;31m"));
+        assert!(!diagnostic.render("f();", false).contains('\x1b'));
+    }
+
+    #[test]
+    fn diagnostic_multi_line_span() {
+        let source = "while (true) {\n}";
+        let diagnostic = PerryError::new(
+            ErrorKind::Semantic,
+            "example".to_string(),
+            span(source, (1, 1), (2, 2)),
+        );
+        let expected = "SemanticError[E03]: example
+ --> 1:1
+  |
+1 | while (true) {
+  | ^^^^^^^^^^^^^^
+";
+        assert_eq!(diagnostic.render(source, false), expected);
+    }
+
+    // Records every name used, in the order the walk reaches it
+    #[derive(Default)]
+    struct NameCollector {
+        names: Vec<String>,
+        parameter_spans: Vec<Span>,
+    }
+
+    impl Visitor for NameCollector {
+        fn visit_parameter(&mut self, param: &Parameter, span: Span) {
+            self.names.push(format!("param {}", param.name));
+            self.parameter_spans.push(span);
+        }
+
+        fn visit_function_call(&mut self, call: &FunctionCall, span: Span) {
+            self.names.push(format!("call {}", call.callee));
+            walk_function_call(self, call, span);
+        }
+
+        fn visit_variable_ref(&mut self, var_ref: &VariableRef, _span: Span) {
+            self.names.push(format!("ref {}", var_ref.name));
+        }
+    }
+
+    #[test]
+    fn visitor_walks_every_node() {
+        let source = "func f(a: int, b: int): int {\n    while (a < b) {\n        a = a + g(b);\n    }\n    if (a == 1) {\n        return c;\n    } else {\n        h(d);\n    }\n    return e;\n}\nvar x: int = f(y, 2);\n";
+        let program = parse_source(source).unwrap();
+        let mut collector = NameCollector::default();
+        collector.visit_program(&program);
+        let expected = [
+            "param a", "param b", "ref a", "ref b", "ref a", "call g", "ref b", "ref a", "ref c",
+            "call h", "ref d", "ref e", "call f", "ref y",
+        ];
+        assert_eq!(collector.names, expected);
+        // Each parameter is visited with its own span, not its function's
+        assert_eq!(
+            collector.parameter_spans,
+            vec![
+                span(source, (1, 8), (1, 14)),
+                span(source, (1, 16), (1, 22))
+            ]
+        );
+    }
+
+    // Replaces additions and multiplications of two number literals with their result
+    struct ConstantFolder;
+
+    impl VisitorMut for ConstantFolder {
+        fn visit_expression_mut(&mut self, expression: &mut Expression, span: Span) {
+            walk_expression_mut(self, expression, span);
+            let Expression::Binary(binary) = expression else {
+                return;
+            };
+            let (Expression::Literal(left), Expression::Literal(right)) =
+                (&binary.left.node, &binary.right.node)
+            else {
+                return;
+            };
+            let (LiteralValue::Number(left), LiteralValue::Number(right)) =
+                (&left.value, &right.value)
+            else {
+                return;
+            };
+            let value = match binary.operator {
+                BinaryOperator::Add => left + right,
+                BinaryOperator::Multiply => left * right,
+                _ => return,
+            };
+            *expression = Expression::Literal(LiteralExpression {
+                value: LiteralValue::Number(value),
+            });
+        }
+    }
+
+    #[test]
+    fn visitor_mut_rewrites_nodes() {
+        let source = "func f(n: int): int {\n    return n * (2 + 3);\n}\nvar x: int = 1 + 2 * 3;\nprintln(f(4 + 4) == x);\n";
+        let mut program = parse_source(source).unwrap();
+        ConstantFolder.visit_program_mut(&mut program);
+        let expected =
+            "func f(n: int): int {\n    return n * 5;\n}\nvar x: int = 7;\nprintln(f(8) == x);\n";
+        assert_eq!(format_program(&program, Vec::new()), expected);
+    }
+}
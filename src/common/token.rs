@@ -1,6 +1,9 @@
-use super::ast::{Spannable, WithSpan};
+use std::fmt::Display;
+
+use super::ast::{Span, Spannable, WithSpan};
 
 #[derive(Debug, PartialEq, Clone)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Token {
     Identifier(String),
     NumericLiteral(i32),
@@ -40,6 +43,52 @@ pub enum UnaryOperator {
 }
 
 pub type SpannedToken = WithSpan<Token>;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriviaKind {
+    Whitespace, // Spaces and tabs, never a line break
+    Newline,    // `\n` or `\r\n`
+    LineComment,
+    BlockComment,
+}
+
+impl TriviaKind {
+    pub fn is_comment(&self) -> bool {
+        matches!(self, TriviaKind::LineComment | TriviaKind::BlockComment)
+    }
+}
+
+// Source text between tokens that the parser doesn't need. Comments include their delimiters
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+// A token with its exact source text and the trivia around it. Trailing trivia is everything up
+// to and including the line break after the token, leading trivia is the rest before it
+#[derive(Debug, PartialEq, Clone)]
+pub struct LosslessToken {
+    pub leading: Vec<Trivia>,
+    pub token: SpannedToken,
+    pub text: String,
+    pub trailing: Vec<Trivia>,
+}
+
+impl Display for LosslessToken {
+    // Writes the token back out exactly as it appeared in the source
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for trivia in &self.leading {
+            write!(f, "{}", trivia.text)?;
+        }
+        write!(f, "{}", self.text)?;
+        for trivia in &self.trailing {
+            write!(f, "{}", trivia.text)?;
+        }
+        Ok(())
+    }
+}
 impl Spannable for Token {}
 
 impl Token {
@@ -68,3 +117,22 @@ impl BinaryOperator {
         }
     }
 }
+
+impl Display for BinaryOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::GreaterThanOrEqual => ">=",
+            BinaryOperator::LessThanOrEqual => "<=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
@@ -0,0 +1,133 @@
+pub mod test;
+
+use std::{
+    io::{BufRead, Write},
+    rc::Rc,
+};
+
+use crate::analyzer::Analyzer;
+use crate::common::{
+    ast::{LiteralValue, Parameter, Span, Type},
+    error::{ErrorKind, PerryError},
+};
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::pipeline::parse_source;
+
+// High-level API for embedding Perry. Keeps analyzer and interpreter state between calls, so
+// functions and variables declared by loaded source can be used by later loads and calls
+#[derive(Default)]
+pub struct Engine {
+    analyzer: Analyzer,
+    interpreter: Interpreter,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Creates an engine whose programs print to `output` and read from `input`
+    pub fn with_io(output: Box<dyn Write>, input: Box<dyn BufRead>) -> Self {
+        Self {
+            analyzer: Analyzer::new(),
+            interpreter: Interpreter::with_io(output, input),
+        }
+    }
+
+    // Parses, analyzes and executes source. Nothing is declared if analysis or execution fails
+    pub fn load(&mut self, source: &str) -> Result<(), Vec<PerryError>> {
+        let program = parse_source(source)?;
+        let mut analyzer = self.analyzer.clone();
+        analyzer.analyze(&program).map_err(|err| vec![err.into()])?;
+        self.interpreter
+            .execute_or_undo(&program)
+            .map_err(|err| vec![err.into()])?;
+        self.analyzer = analyzer;
+        Ok(())
+    }
+
+    // Registers a host function callable from Perry. Arguments are type checked against `params`
+    // before the function runs, and an `Err` is reported as a runtime error at the call site
+    pub fn register_function<F>(&mut self, name: &str, params: Vec<Type>, return_type: Type, f: F)
+    where
+        F: Fn(&[LiteralValue]) -> Result<LiteralValue, String> + 'static,
+    {
+        let params = params
+            .into_iter()
+            .enumerate()
+            .map(|(index, type_)| Parameter {
+                name: format!("arg{}", index),
+                type_,
+                span: Span::default(),
+            })
+            .collect();
+        self.analyzer
+            .register_built_in(name.to_string(), params, return_type);
+        self.interpreter.register_built_in(
+            name.to_string(),
+            Rc::new(move |_, args: Vec<LiteralValue>, span: Span| {
+                f(&args).map_err(|message| RuntimeError::new(message, span))
+            }),
+        );
+    }
+
+    // Sets a global variable visible to loaded source, declaring it if needed. Its type is fixed
+    // by the first value it is set to
+    pub fn set_global(&mut self, name: &str, value: LiteralValue) -> Result<(), Vec<PerryError>> {
+        self.analyzer
+            .declare_global(name.to_string(), Type::from(&value))
+            .map_err(|err| vec![err.into()])?;
+        self.interpreter.set_global(name, value);
+        Ok(())
+    }
+
+    // The code passed to `exit` by the last source loaded or function called, if it called it
+    pub fn exit_code(&self) -> Option<i32> {
+        self.interpreter.exit_code()
+    }
+
+    // Seeds the numbers `random_int` returns, so loaded source behaves the same every run
+    pub fn set_seed(&mut self, seed: u64) {
+        self.interpreter.set_seed(seed);
+    }
+
+    // Calls a function declared by loaded source and returns its result
+    pub fn call(
+        &mut self,
+        name: &str,
+        args: Vec<LiteralValue>,
+    ) -> Result<LiteralValue, Vec<PerryError>> {
+        let Some((params, _)) = self.analyzer.function_signature(name) else {
+            return Err(api_error(format!("use of undefined function {}", name)));
+        };
+        if params.len() != args.len() {
+            return Err(api_error(format!(
+                "function {} expects {} arguments, got {}",
+                name,
+                params.len(),
+                args.len()
+            )));
+        }
+        for (param, arg) in params.iter().zip(&args) {
+            let arg_type = Type::from(arg);
+            if param.type_ != arg_type && param.type_ != Type::Any {
+                return Err(api_error(format!(
+                    "type mismatch for {}: expected {}, got {}",
+                    param.name, param.type_, arg_type
+                )));
+            }
+        }
+        self.interpreter
+            .call_function(name, args)
+            .map_err(|err| vec![err.into()])
+    }
+}
+
+// An error caused by the embedder rather than by Perry source, so it has no meaningful span
+fn api_error(message: String) -> Vec<PerryError> {
+    vec![PerryError::new(
+        ErrorKind::Runtime,
+        message,
+        Span::default(),
+    )]
+}
@@ -0,0 +1,161 @@
+#![cfg(test)]
+
+use super::*;
+use crate::interpreter::OutputBuffer;
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_function_by_name() {
+        let mut engine = Engine::new();
+        engine
+            .load(
+                r#"func factorial(n: int): int {
+                    if (n <= 1) {
+                        return 1;
+                    }
+                    return n * factorial(n - 1);
+                }"#,
+            )
+            .unwrap();
+        let result = engine
+            .call("factorial", vec![LiteralValue::Number(5)])
+            .unwrap();
+        assert_eq!(result, LiteralValue::Number(120));
+    }
+
+    #[test]
+    fn call_with_wrong_arguments() {
+        let mut engine = Engine::new();
+        engine
+            .load(r#"func id(n: int): int { return n; }"#)
+            .unwrap();
+        assert!(engine.call("id", vec![]).is_err());
+        assert!(
+            engine
+                .call("id", vec![LiteralValue::String("1".to_string())])
+                .is_err()
+        );
+        assert!(engine.call("missing", vec![]).is_err());
+    }
+
+    #[test]
+    fn host_function() {
+        let mut engine = Engine::new();
+        engine.register_function("double", vec![Type::Int], Type::Int, |args| {
+            let LiteralValue::Number(n) = args[0] else {
+                return Err("expected a number".to_string());
+            };
+            Ok(LiteralValue::Number(n * 2))
+        });
+        engine
+            .load(r#"func quadruple(n: int): int { return double(double(n)); }"#)
+            .unwrap();
+        let result = engine.call("quadruple", vec![LiteralValue::Number(3)]);
+        assert_eq!(result.unwrap(), LiteralValue::Number(12));
+    }
+
+    #[test]
+    fn host_function_error() {
+        let mut engine = Engine::new();
+        engine.register_function("fail", vec![], Type::Void, |_| Err("failed".to_string()));
+        let errors = engine.load("fail();").unwrap_err();
+        assert_eq!(errors[0].kind, ErrorKind::Runtime);
+        assert_eq!(errors[0].message, "failed");
+        assert_eq!(errors[0].span.start.column, 1);
+    }
+
+    #[test]
+    fn host_function_type_checked() {
+        let mut engine = Engine::new();
+        engine.register_function("double", vec![Type::Int], Type::Int, |args| {
+            Ok(args[0].clone())
+        });
+        let errors = engine.load(r#"double("two");"#).unwrap_err();
+        assert_eq!(errors[0].kind, ErrorKind::Semantic);
+    }
+
+    #[test]
+    fn globals() {
+        let mut engine = Engine::new();
+        engine.set_global("limit", LiteralValue::Number(3)).unwrap();
+        engine
+            .load(r#"func get_limit(): int { return limit; }"#)
+            .unwrap();
+        let result = engine.call("get_limit", vec![]).unwrap();
+        assert_eq!(result, LiteralValue::Number(3));
+
+        engine.set_global("limit", LiteralValue::Number(7)).unwrap();
+        let result = engine.call("get_limit", vec![]).unwrap();
+        assert_eq!(result, LiteralValue::Number(7));
+
+        assert!(
+            engine
+                .set_global("limit", LiteralValue::Bool(true))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn failed_load_declares_nothing() {
+        let mut engine = Engine::new();
+        let errors = engine
+            .load(r#"func f(): int { return 1; } var x: string = 1;"#)
+            .unwrap_err();
+        assert_eq!(errors[0].kind, ErrorKind::Semantic);
+        engine.load(r#"func f(): int { return 2; }"#).unwrap();
+        assert_eq!(engine.call("f", vec![]).unwrap(), LiteralValue::Number(2));
+    }
+
+    #[test]
+    fn failed_execution_declares_nothing() {
+        let mut engine = Engine::new();
+        let errors = engine
+            .load("var y: int = 1 / 0; func f(): int { return 1; }")
+            .unwrap_err();
+        assert_eq!(errors[0].kind, ErrorKind::Runtime);
+        assert!(engine.call("f", vec![]).is_err());
+        engine
+            .load("var y: int = 2; func f(): int { return y; }")
+            .unwrap();
+        assert_eq!(engine.call("f", vec![]).unwrap(), LiteralValue::Number(2));
+    }
+
+    #[test]
+    // Default engines share stdin, which used to stay locked by the first one
+    fn two_default_engines_on_one_thread() {
+        let mut first = Engine::new();
+        let mut second = Engine::new();
+        first.load("var x: int = 1;").unwrap();
+        second.load("var x: int = 2;").unwrap();
+    }
+
+    #[test]
+    fn captured_output() {
+        let output = OutputBuffer::new();
+        let mut engine = Engine::with_io(Box::new(output.clone()), Box::new(&b""[..]));
+        engine
+            .load(r#"func greet(name: string): void { print("Hi "); println(name); }"#)
+            .unwrap();
+        engine
+            .call("greet", vec![LiteralValue::String("Ada".to_string())])
+            .unwrap();
+        assert_eq!(output.contents(), "Hi Ada\n");
+    }
+
+    #[test]
+    fn seeded_engines_agree() {
+        let draws = || {
+            let mut engine = Engine::new();
+            engine.set_seed(42);
+            engine
+                .load("func draw(): int { return random_int(1, 1000); }")
+                .unwrap();
+            (0..5)
+                .map(|_| engine.call("draw", vec![]).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draws(), draws());
+    }
+}
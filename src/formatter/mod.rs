@@ -0,0 +1,251 @@
+pub mod test;
+
+use std::collections::VecDeque;
+
+use crate::common::{
+    ast::{
+        Expression, IfStatement, LiteralValue, Program, Spannable, SpannedExpression,
+        SpannedStatement, Statement,
+    },
+    error::PerryError,
+    token::Trivia,
+};
+use crate::parser::Parser;
+use crate::tokenizer::Tokenizer;
+
+const INDENT: &str = "    ";
+
+// Formats source code in the canonical style. Comments are kept, source that doesn't parse is
+// rejected with its errors
+pub fn format_source(source: &str) -> Result<String, Vec<PerryError>> {
+    let tokens = Tokenizer::new(source.to_string())
+        .tokenize_lossless()
+        .map_err(|err| vec![err.into()])?;
+    let comments = tokens
+        .iter()
+        .flat_map(|token| token.leading.iter().chain(&token.trailing))
+        .filter(|trivia| trivia.kind.is_comment())
+        .cloned()
+        .collect();
+    let tokens = tokens.into_iter().map(|token| token.token).collect();
+    let program = Parser::new(tokens)
+        .parse()
+        .map_err(|errors| errors.into_iter().map(PerryError::from).collect::<Vec<_>>())?;
+    Ok(format_program(&program, comments))
+}
+
+// Prints a program as source code. Comments are placed between the statements they were found
+// between; a comment on the same line after a statement stays at the end of that line
+pub fn format_program(program: &Program, comments: Vec<Trivia>) -> String {
+    let mut formatter = Formatter {
+        out: String::new(),
+        depth: 0,
+        comments: comments.into(),
+        last_line: None,
+    };
+    formatter.statements(&program.body);
+    formatter.comments_before(usize::MAX);
+    formatter.out
+}
+
+struct Formatter {
+    out: String,
+    depth: usize,
+    comments: VecDeque<Trivia>, // Comments not printed yet, in source order
+    last_line: Option<usize>,   // Source line the last printed item ended on, None at a block start
+}
+
+impl Formatter {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    // Keeps at most one blank line where the source had any
+    fn separate(&mut self, start_line: usize) {
+        if self
+            .last_line
+            .is_some_and(|last_line| start_line > last_line + 1)
+        {
+            self.out.push('\n');
+        }
+    }
+
+    // Prints the comments that start before `offset` on their own lines
+    fn comments_before(&mut self, offset: usize) {
+        while let Some(comment) = self
+            .comments
+            .pop_front_if(|comment| comment.span.offset < offset)
+        {
+            self.separate(comment.span.start.line);
+            self.line(&comment.text);
+            self.last_line = Some(comment.span.end.line);
+        }
+    }
+
+    // Appends a comment that follows the statement on its last line
+    fn trailing_comment(&mut self, statement: &SpannedStatement) {
+        let end = statement.span.offset + statement.span.len;
+        let line = statement.span.end.line;
+        if let Some(comment) = self
+            .comments
+            .pop_front_if(|comment| comment.span.offset >= end && comment.span.start.line == line)
+        {
+            self.out.pop(); // The line break
+            self.out.push(' ');
+            self.out.push_str(&comment.text);
+            self.out.push('\n');
+        }
+    }
+
+    fn statements(&mut self, statements: &[SpannedStatement]) {
+        for statement in statements {
+            self.comments_before(statement.span.offset);
+            self.separate(statement.span.start.line);
+            self.statement(statement);
+            self.trailing_comment(statement);
+            self.last_line = Some(statement.span.end.line);
+        }
+    }
+
+    // Prints the statements of a block one level deeper, along with the comments before `end`
+    fn block(&mut self, statements: &[SpannedStatement], end: usize) {
+        self.depth += 1;
+        self.last_line = None;
+        self.statements(statements);
+        self.comments_before(end);
+        self.depth -= 1;
+    }
+
+    fn statement(&mut self, statement: &SpannedStatement) {
+        // Offset of the closing brace of a statement ending in a block
+        let end = statement.span.offset + statement.span.len - 1;
+        match &statement.node {
+            Statement::Function(func) => {
+                let params = func
+                    .params
+                    .iter()
+                    .map(|param| format!("{}: {}", param.name, param.type_))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let visibility = if func.public { "pub " } else { "" };
+                self.line(&format!(
+                    "{}func {}({}): {} {{",
+                    visibility, func.name, params, func.type_
+                ));
+                self.block(&func.body, end);
+                self.line("}");
+            }
+            Statement::VarDecl(var_decl) => self.line(&format!(
+                "var {}: {} = {};",
+                var_decl.name,
+                var_decl.type_,
+                format_expression(&var_decl.value)
+            )),
+            Statement::VarAssignment(var_assignment) => self.line(&format!(
+                "{} = {};",
+                var_assignment.name,
+                format_expression(&var_assignment.value)
+            )),
+            Statement::If(if_statement) => {
+                self.line(&format!(
+                    "if ({}) {{",
+                    format_expression(&if_statement.condition)
+                ));
+                self.if_chain(if_statement, end);
+            }
+            Statement::Loop(loop_statement) => {
+                self.line(&format!(
+                    "while ({}) {{",
+                    format_expression(&loop_statement.condition)
+                ));
+                self.block(&loop_statement.body, end);
+                self.line("}");
+            }
+            Statement::Return(return_statement) => match &return_statement.value {
+                Some(value) => self.line(&format!("return {};", format_expression(value))),
+                None => self.line("return;"),
+            },
+            Statement::Expr(expr) => {
+                let expr = expr.clone().spanned(statement.span);
+                self.line(&format!("{};", format_expression(&expr)));
+            }
+            Statement::Import(import) => match &import.names {
+                Some(names) => self.line(&format!(
+                    "import {{ {} }} from \"{}\";",
+                    names.join(", "),
+                    import.path
+                )),
+                None => self.line(&format!("import \"{}\";", import.path)),
+            },
+        }
+    }
+
+    // Prints the body of an if whose header was printed, followed by its else branches
+    fn if_chain(&mut self, if_statement: &IfStatement, end: usize) {
+        let Some(else_body) = &if_statement.else_body else {
+            self.block(&if_statement.then_body, end);
+            self.line("}");
+            return;
+        };
+        self.block(&if_statement.then_body, else_body.span.offset);
+        let Statement::If(else_if) = &else_body.node else {
+            unreachable!("else branches are parsed as if statements");
+        };
+        // A plain else is parsed as an if whose condition spans the `else` keyword
+        if else_if.condition.span.offset == else_body.span.offset {
+            self.line("} else {");
+            self.block(&else_if.then_body, end);
+            self.line("}");
+        } else {
+            self.line(&format!(
+                "}} else if ({}) {{",
+                format_expression(&else_if.condition)
+            ));
+            self.if_chain(else_if, end);
+        }
+    }
+}
+
+fn format_expression(expression: &SpannedExpression) -> String {
+    match &expression.node {
+        Expression::Binary(binary) => {
+            let precedence = binary.operator.get_precedence();
+            format!(
+                "{} {} {}",
+                operand(&binary.left, precedence, false),
+                binary.operator,
+                operand(&binary.right, precedence, true)
+            )
+        }
+        Expression::Literal(literal) => match &literal.value {
+            LiteralValue::String(value) => format!("\"{}\"", value),
+            value => value.to_string(),
+        },
+        Expression::FunctionCall(call) => {
+            let args = call.args.iter().map(format_expression).collect::<Vec<_>>();
+            format!("{}({})", call.callee, args.join(", "))
+        }
+        Expression::VariableRef(var_ref) => var_ref.name.clone(),
+    }
+}
+
+// Prints an operand of a binary operator, parenthesized if it would otherwise bind differently.
+// Operators are left-associative, so a right operand of equal precedence needs parentheses too
+fn operand(operand: &SpannedExpression, parent_precedence: u8, is_right: bool) -> String {
+    let printed = format_expression(operand);
+    match &operand.node {
+        Expression::Binary(binary) => {
+            let precedence = binary.operator.get_precedence();
+            if precedence < parent_precedence || (is_right && precedence == parent_precedence) {
+                format!("({})", printed)
+            } else {
+                printed
+            }
+        }
+        _ => printed,
+    }
+}
@@ -0,0 +1,101 @@
+#![cfg(test)]
+
+use super::*;
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_layout() {
+        let input = r#"func   add(a:int,b :int):int{return a+b;}
+var x:int=add(1,2)*3;
+if(x>3){println("big");}else if (x == 3) {println("three");} else{println("small");}
+while(x>0){x=x-1;}"#;
+        let expected = r#"func add(a: int, b: int): int {
+    return a + b;
+}
+var x: int = add(1, 2) * 3;
+if (x > 3) {
+    println("big");
+} else if (x == 3) {
+    println("three");
+} else {
+    println("small");
+}
+while (x > 0) {
+    x = x - 1;
+}
+"#;
+        assert_eq!(format_source(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn imports_and_pub() {
+        let input = r#"import   "lib/math.pry" ;import{square,cube}from "math.pry";
+pub   func f():void{}"#;
+        let expected = r#"import "lib/math.pry";
+import { square, cube } from "math.pry";
+pub func f(): void {
+}
+"#;
+        assert_eq!(format_source(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn parentheses_kept_where_needed() {
+        let input = r#"var x: int = ((1 + 2)) * (3 - (4 - 5)) + (6 * 7);"#;
+        let expected = "var x: int = (1 + 2) * (3 - (4 - 5)) + 6 * 7;\n";
+        assert_eq!(format_source(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn comments_and_blank_lines_kept() {
+        let input = r#"// Header
+/* block
+   comment */
+func f(): void {
+  // inside
+  println(1); // trailing
+
+
+  println(2);
+  // before brace
+}
+var x: int = 1;
+// end"#;
+        let expected = r#"// Header
+/* block
+   comment */
+func f(): void {
+    // inside
+    println(1); // trailing
+
+    println(2);
+    // before brace
+}
+var x: int = 1;
+// end
+"#;
+        assert_eq!(format_source(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn idempotent() {
+        for entry in std::fs::read_dir("working_examples").unwrap() {
+            let path = entry.unwrap().path();
+            // Skip the golden output files next to the examples
+            if path.extension().is_none_or(|extension| extension != "pry") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            let once = format_source(&source).unwrap();
+            let twice = format_source(&once).unwrap();
+            assert_eq!(once, twice, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn broken_source_rejected() {
+        assert!(format_source("var x: int = ;").is_err());
+    }
+}
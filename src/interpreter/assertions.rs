@@ -0,0 +1,60 @@
+use std::rc::Rc;
+
+use crate::common::ast::LiteralValue;
+
+use super::{BuiltInFn, Context, RuntimeError};
+
+// Assertions for tests. A failure is a runtime error at the call, naming the values involved
+pub fn built_ins() -> Vec<(&'static str, BuiltInFn)> {
+    vec![
+        (
+            "assert",
+            Rc::new(
+                |_: &mut Context, args: Vec<LiteralValue>, span| match args[0] {
+                    LiteralValue::Bool(true) => Ok(LiteralValue::Void),
+                    _ => Err(RuntimeError::new("assertion failed".to_string(), span)),
+                },
+            ),
+        ),
+        (
+            "assert_eq",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                if args[0] == args[1] {
+                    return Ok(LiteralValue::Void);
+                }
+                Err(RuntimeError::new(
+                    format!(
+                        "assert_eq failed: {} != {}",
+                        quoted(&args[0]),
+                        quoted(&args[1])
+                    ),
+                    span,
+                ))
+            }),
+        ),
+        (
+            "assert_ne",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                if args[0] != args[1] {
+                    return Ok(LiteralValue::Void);
+                }
+                Err(RuntimeError::new(
+                    format!(
+                        "assert_ne failed: {} == {}",
+                        quoted(&args[0]),
+                        quoted(&args[1])
+                    ),
+                    span,
+                ))
+            }),
+        ),
+    ]
+}
+
+// Shows a value the way it is written in source, so "1" and 1 can be told apart
+fn quoted(value: &LiteralValue) -> String {
+    match value {
+        LiteralValue::String(value) => format!("{:?}", value),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    cell::RefCell,
+    io::{self, BufRead, Read, Write},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use super::random::Random;
+
+// State the interpreter shares with built-in functions. Output and input are injectable so
+// programs can be run against captured output and canned input
+pub struct Context {
+    pub output: Box<dyn Write>,
+    pub input: Box<dyn BufRead>,
+    pub args: Vec<String>,      // Command-line arguments given to the program
+    pub exit_code: Option<i32>, // Set when the program called `exit`
+    // The directory the file built-ins may access. None, the default, turns them off
+    pub file_root: Option<PathBuf>,
+    pub random: Random, // Numbers for `random_int`, seeded from the clock unless set
+}
+
+impl Context {
+    pub fn new(output: Box<dyn Write>, input: Box<dyn BufRead>) -> Self {
+        Self {
+            output,
+            input,
+            args: Vec::new(),
+            exit_code: None,
+            file_root: None,
+            random: Random::default(),
+        }
+    }
+}
+
+impl Default for Context {
+    // Uses the process' stdout and stdin. Stdin is locked only while it is read, so several
+    // interpreters can exist on one thread
+    fn default() -> Self {
+        Self::new(Box::new(io::stdout()), Box::new(StdinReader::default()))
+    }
+}
+
+// Reads the process' stdin through the buffer the standard library keeps for it, locking it for
+// each call. Nothing is buffered here beyond a call, so text one interpreter didn't read is left
+// for the next
+#[derive(Default)]
+struct StdinReader {
+    peeked: Vec<u8>, // What stdin had buffered at the last `fill_buf`
+}
+
+impl Read for StdinReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::stdin().lock().read(buf)
+    }
+}
+
+impl BufRead for StdinReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let mut stdin = io::stdin().lock();
+        self.peeked.clear();
+        self.peeked.extend_from_slice(stdin.fill_buf()?);
+        Ok(&self.peeked)
+    }
+
+    fn consume(&mut self, amount: usize) {
+        io::stdin().lock().consume(amount);
+        self.peeked.drain(..amount.min(self.peeked.len()));
+    }
+}
+
+// An in-memory output sink that can be read back after it was handed to an interpreter
+#[derive(Clone, Default)]
+pub struct OutputBuffer {
+    buffer: Rc<RefCell<Vec<u8>>>,
+}
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns everything written so far
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.borrow()).into_owned()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use std::{num::IntErrorKind, rc::Rc};
+
+use crate::common::ast::LiteralValue;
+
+use super::{BuiltInFn, Context, RuntimeError, string_arg};
+
+// Built-ins converting between ints, strings and bools
+pub fn built_ins() -> Vec<(&'static str, BuiltInFn)> {
+    vec![
+        (
+            "to_string",
+            // Matches what `print` writes
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, _| {
+                Ok(LiteralValue::String(args[0].to_string()))
+            }),
+        ),
+        (
+            "parse_int",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let text = string_arg(&args, 0, span)?;
+                text.parse().map(LiteralValue::Number).map_err(|err| {
+                    let message = match err.kind() {
+                        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                            format!("{:?} is out of range for an int", text)
+                        }
+                        _ => format!("cannot parse {:?} as an int", text),
+                    };
+                    RuntimeError::new(message, span)
+                })
+            }),
+        ),
+        (
+            "to_bool",
+            // Ints are true unless they are 0, strings must spell out a bool
+            Rc::new(
+                |_: &mut Context, args: Vec<LiteralValue>, span| match &args[0] {
+                    LiteralValue::Bool(value) => Ok(LiteralValue::Bool(*value)),
+                    LiteralValue::Number(value) => Ok(LiteralValue::Bool(*value != 0)),
+                    LiteralValue::String(text) if text == "true" => Ok(LiteralValue::Bool(true)),
+                    LiteralValue::String(text) if text == "false" => Ok(LiteralValue::Bool(false)),
+                    value => Err(RuntimeError::new(
+                        format!("cannot convert {:?} to a bool", value.to_string()),
+                        span,
+                    )),
+                },
+            ),
+        ),
+    ]
+}
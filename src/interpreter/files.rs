@@ -0,0 +1,108 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::common::ast::{LiteralValue, Span};
+
+use super::{BuiltInFn, Context, RuntimeError, string_arg};
+
+// File built-ins. They only work once the embedder sets `Context::file_root`, and only on paths
+// under it. Relative paths are resolved against the root
+pub fn built_ins() -> Vec<(&'static str, BuiltInFn)> {
+    vec![
+        (
+            "read_file",
+            Rc::new(|context: &mut Context, args: Vec<LiteralValue>, span| {
+                let path = string_arg(&args, 0, span)?;
+                let resolved = resolve(context, path, span)?;
+                fs::read_to_string(resolved)
+                    .map(LiteralValue::String)
+                    .map_err(|err| {
+                        RuntimeError::new(format!("cannot read {}: {}", path, err), span)
+                    })
+            }),
+        ),
+        (
+            "write_file",
+            Rc::new(|context: &mut Context, args: Vec<LiteralValue>, span| {
+                let path = string_arg(&args, 0, span)?;
+                let resolved = resolve(context, path, span)?;
+                fs::write(resolved, string_arg(&args, 1, span)?)
+                    .map(|_| LiteralValue::Void)
+                    .map_err(|err| {
+                        RuntimeError::new(format!("cannot write {}: {}", path, err), span)
+                    })
+            }),
+        ),
+        (
+            "file_exists",
+            Rc::new(|context: &mut Context, args: Vec<LiteralValue>, span| {
+                let resolved = resolve(context, string_arg(&args, 0, span)?, span)?;
+                Ok(LiteralValue::Bool(resolved.is_file()))
+            }),
+        ),
+    ]
+}
+
+// Resolves `path` against the file root, failing if file access is off or the path leads outside
+// the root. Symbolic links are followed before checking, so they can't be used to escape it, and
+// links to files that don't exist are refused since writing through them would create the file
+fn resolve(context: &Context, path: &str, span: Span) -> Result<PathBuf, RuntimeError> {
+    let Some(root) = &context.file_root else {
+        return Err(RuntimeError::new(
+            "file access is disabled".to_string(),
+            span,
+        ));
+    };
+    let root = root.canonicalize().map_err(|err| {
+        RuntimeError::new(format!("cannot access {}: {}", root.display(), err), span)
+    })?;
+    let resolved = canonicalize_existing(&normalize(&root.join(path)));
+    // Only the part that doesn't exist yet is left uncanonicalized, so a link there is broken
+    if resolved.ancestors().any(Path::is_symlink) {
+        return Err(RuntimeError::new(
+            format!("{} goes through a broken symbolic link", path),
+            span,
+        ));
+    }
+    if !resolved.starts_with(&root) {
+        return Err(RuntimeError::new(
+            format!("{} is outside the directory files can be accessed in", path),
+            span,
+        ));
+    }
+    Ok(resolved)
+}
+
+// Removes `.` and `..` components without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+// Canonicalizes the longest part of an absolute path that exists and appends the rest, so paths
+// of files that are about to be created resolve too
+fn canonicalize_existing(path: &Path) -> PathBuf {
+    for ancestor in path.ancestors() {
+        if let Ok(canonical) = ancestor.canonicalize() {
+            let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            return if rest.as_os_str().is_empty() {
+                canonical
+            } else {
+                canonical.join(rest)
+            };
+        }
+    }
+    path.to_path_buf()
+}
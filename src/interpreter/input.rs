@@ -0,0 +1,74 @@
+use std::{
+    io::{BufRead, Read, Write},
+    rc::Rc,
+};
+
+use crate::common::ast::{LiteralValue, Span};
+
+use super::{BuiltInFn, Context, RuntimeError, string_arg, write_error};
+
+// Built-ins reading the interpreter's input. Once the input is exhausted they return empty
+// strings, and `eof` tells that apart from an empty line
+pub fn built_ins() -> Vec<(&'static str, BuiltInFn)> {
+    vec![
+        (
+            "read_line",
+            Rc::new(|context: &mut Context, _: Vec<LiteralValue>, span| read_line(context, span)),
+        ),
+        (
+            "input",
+            // Shows a prompt first, like Python's `input`
+            Rc::new(|context: &mut Context, args: Vec<LiteralValue>, span| {
+                write!(context.output, "{}", string_arg(&args, 0, span)?)
+                    .map_err(|err| write_error(err, span))?;
+                read_line(context, span)
+            }),
+        ),
+        (
+            "read_all",
+            Rc::new(|context: &mut Context, _: Vec<LiteralValue>, span| {
+                let mut text = String::new();
+                context
+                    .input
+                    .read_to_string(&mut text)
+                    .map_err(|err| read_error(err, span))?;
+                Ok(LiteralValue::String(text))
+            }),
+        ),
+        (
+            "eof",
+            Rc::new(|context: &mut Context, _: Vec<LiteralValue>, span| {
+                let buffer = context
+                    .input
+                    .fill_buf()
+                    .map_err(|err| read_error(err, span))?;
+                Ok(LiteralValue::Bool(buffer.is_empty()))
+            }),
+        ),
+    ]
+}
+
+// Reads the next line without its line ending. Output is flushed first, so a prompt printed
+// without a newline shows up before the program waits
+fn read_line(context: &mut Context, span: Span) -> Result<LiteralValue, RuntimeError> {
+    context
+        .output
+        .flush()
+        .map_err(|err| write_error(err, span))?;
+    let mut line = String::new();
+    context
+        .input
+        .read_line(&mut line)
+        .map_err(|err| read_error(err, span))?;
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(LiteralValue::String(line))
+}
+
+fn read_error(err: std::io::Error, span: Span) -> RuntimeError {
+    RuntimeError::new(format!("failed to read input: {}", err), span)
+}
@@ -0,0 +1,98 @@
+use std::rc::Rc;
+
+use crate::common::ast::{LiteralValue, Span};
+
+use super::{BuiltInFn, Context, RuntimeError, int_arg};
+
+// Integer math built-ins. Results that don't fit in an int are runtime errors rather than
+// wrapping around
+pub fn built_ins() -> Vec<(&'static str, BuiltInFn)> {
+    vec![
+        (
+            "abs",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let n = int_arg(&args, 0, span)?;
+                checked(n.checked_abs(), format!("abs({})", n), span)
+            }),
+        ),
+        (
+            "min",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let (a, b) = (int_arg(&args, 0, span)?, int_arg(&args, 1, span)?);
+                Ok(LiteralValue::Number(a.min(b)))
+            }),
+        ),
+        (
+            "max",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let (a, b) = (int_arg(&args, 0, span)?, int_arg(&args, 1, span)?);
+                Ok(LiteralValue::Number(a.max(b)))
+            }),
+        ),
+        (
+            "pow",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let (base, exponent) = (int_arg(&args, 0, span)?, int_arg(&args, 1, span)?);
+                let Ok(power) = u32::try_from(exponent) else {
+                    return Err(RuntimeError::new(
+                        format!("exponent {} is negative", exponent),
+                        span,
+                    ));
+                };
+                checked(
+                    base.checked_pow(power),
+                    format!("pow({}, {})", base, exponent),
+                    span,
+                )
+            }),
+        ),
+        (
+            "isqrt",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let n = int_arg(&args, 0, span)?;
+                n.checked_isqrt().map(LiteralValue::Number).ok_or_else(|| {
+                    RuntimeError::new(format!("isqrt of negative number {}", n), span)
+                })
+            }),
+        ),
+        (
+            "clamp",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let n = int_arg(&args, 0, span)?;
+                let (low, high) = (int_arg(&args, 1, span)?, int_arg(&args, 2, span)?);
+                if low > high {
+                    return Err(RuntimeError::new(
+                        format!("clamp bounds {}..{} are reversed", low, high),
+                        span,
+                    ));
+                }
+                Ok(LiteralValue::Number(n.clamp(low, high)))
+            }),
+        ),
+        (
+            "gcd",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let (a, b) = (int_arg(&args, 0, span)?, int_arg(&args, 1, span)?);
+                let (mut x, mut y) = (a.unsigned_abs(), b.unsigned_abs());
+                while y != 0 {
+                    (x, y) = (y, x % y);
+                }
+                // Only gcd(-2147483648, 0) and its relatives reach 2^31
+                checked(i32::try_from(x).ok(), format!("gcd({}, {})", a, b), span)
+            }),
+        ),
+        (
+            "sign",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                Ok(LiteralValue::Number(int_arg(&args, 0, span)?.signum()))
+            }),
+        ),
+    ]
+}
+
+// Wraps the result of a checked operation, describing the call if it overflowed
+fn checked(result: Option<i32>, call: String, span: Span) -> Result<LiteralValue, RuntimeError> {
+    result
+        .map(LiteralValue::Number)
+        .ok_or_else(|| RuntimeError::new(format!("{} overflows an int", call), span))
+}
@@ -1,15 +1,32 @@
+pub mod assertions;
+pub mod context;
+pub mod conversions;
+pub mod files;
+pub mod input;
+pub mod math;
+pub mod process;
+pub mod random;
+pub mod strings;
 pub mod test;
 
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{BufRead, Write},
+    rc::Rc,
+};
 
 use crate::common::{
     ast::{
         self, Expression, LiteralExpression, LiteralValue, Span, Spannable, SpannedStatement,
         Statement, Type,
     },
+    error::{ErrorKind, PerryError},
     token::BinaryOperator,
 };
 
+pub use context::{Context, OutputBuffer};
+
 #[derive(Clone, Debug)]
 struct ScopeStack {
     tables: Vec<Vec<SpannedStatement>>,
@@ -25,10 +42,25 @@ impl ScopeStack {
         self.tables[0].push(statement);
     }
 
+    // Add a statement to the outermost scope, regardless of the current scope
+    fn add_global(&mut self, statement: SpannedStatement) {
+        if let Some(globals) = self.tables.last_mut() {
+            globals.push(statement);
+        }
+    }
+
     fn enter_scope(&mut self) {
         self.tables.insert(0, Vec::new());
     }
 
+    // Drops every scope above the outermost one, e.g. after an error unwound mid-scope
+    fn reset_to_global(&mut self) {
+        let depth = self.tables.len();
+        if depth > 1 {
+            self.tables.drain(..depth - 1);
+        }
+    }
+
     fn exit_scope(&mut self) {
         self.tables.remove(0);
     }
@@ -56,37 +88,98 @@ impl ScopeStack {
     }
 }
 
-type BuiltInFn = fn(Vec<LiteralValue>) -> Result<LiteralValue, RuntimeError>;
+// A built-in function. Receives the interpreter's context, the evaluated arguments and the span
+// of the call
+pub type BuiltInFn =
+    Rc<dyn Fn(&mut Context, Vec<LiteralValue>, Span) -> Result<LiteralValue, RuntimeError>>;
 
-#[derive(Clone)]
-pub struct Interpreter<'a> {
+pub struct Interpreter {
     scope_stack: ScopeStack,
     built_ins: HashMap<String, BuiltInFn>,
-    program_ast: &'a ast::Program,
+    context: Context,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<'a> Interpreter<'a> {
-    pub fn new(program_ast: &'a ast::Program) -> Self {
-        Self {
+impl Interpreter {
+    // Creates an interpreter that prints to stdout and reads from stdin
+    pub fn new() -> Self {
+        Self::with_context(Context::default())
+    }
+
+    // Creates an interpreter that prints to `output` and reads from `input`
+    pub fn with_io(output: Box<dyn Write>, input: Box<dyn BufRead>) -> Self {
+        Self::with_context(Context::new(output, input))
+    }
+
+    fn with_context(context: Context) -> Self {
+        let mut interpreter = Self {
             scope_stack: ScopeStack::new(),
             built_ins: HashMap::new(),
-            program_ast,
-        }
+            context,
+        };
+        interpreter.scope_stack.enter_scope(); // Global scope
+        interpreter.initialize_built_ins();
+        interpreter
     }
 
     fn initialize_built_ins(&mut self) {
-        self.built_ins
-            .insert("print".to_string(), |args: Vec<LiteralValue>| {
-                let arg_value = args[0].clone();
-                print!("{}", arg_value);
-                Ok(LiteralValue::Void)
-            });
-        self.built_ins
-            .insert("println".to_string(), |args: Vec<LiteralValue>| {
-                let arg_value = args[0].clone();
-                println!("{}", arg_value);
-                Ok(LiteralValue::Void)
-            });
+        for (name, function) in standard_built_ins() {
+            self.register_built_in(name.to_string(), function);
+        }
+    }
+
+    // Registers a built-in function, replacing any existing one with the same name
+    pub fn register_built_in(&mut self, name: String, function: BuiltInFn) {
+        self.built_ins.insert(name, function);
+    }
+
+    // Sets a global variable, declaring it if it doesn't exist yet
+    pub fn set_global(&mut self, name: &str, value: LiteralValue) {
+        let literal = Expression::Literal(LiteralExpression {
+            value: value.clone(),
+        });
+        if let Some(symbol) = self.scope_stack.lookup_mut(name)
+            && let Statement::VarDecl(var_decl) = &mut symbol.node
+        {
+            var_decl.value.node = literal;
+            return;
+        }
+        self.scope_stack.add_global(
+            Statement::VarDecl(ast::VariableDecl {
+                name: name.to_string(),
+                type_: Type::from(&value),
+                value: literal.spanned(Span::default()),
+            })
+            .spanned(Span::default()),
+        );
+    }
+
+    // Calls a user-defined function by name with already evaluated arguments
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        args: Vec<LiteralValue>,
+    ) -> Result<LiteralValue, RuntimeError> {
+        let Some(function) = self.scope_stack.lookup(name).cloned() else {
+            return Err(RuntimeError::new(
+                format!("use of undefined function {}", name),
+                Span::default(),
+            ));
+        };
+        self.context.exit_code = None;
+        self.scope_stack.enter_scope();
+        let result = self.execute_function(&function, &args);
+        let _ = self.context.output.flush();
+        match result {
+            Ok(_) => self.scope_stack.exit_scope(),
+            Err(_) => self.scope_stack.reset_to_global(),
+        }
+        self.exited(result)
     }
 
     fn evaluate_expression(
@@ -110,8 +203,7 @@ impl<'a> Interpreter<'a> {
                     self.scope_stack.exit_scope();
                     Ok(result)
                 } else if let Some(built_in_fn) = self.built_ins.get(&function_call.callee) {
-                    built_in_fn(evaluated_args)?;
-                    Ok(LiteralValue::Void)
+                    built_in_fn(&mut self.context, evaluated_args, expression.span)
                 } else {
                     Err(RuntimeError::new(
                         format!("use of undefined function {}", function_call.callee),
@@ -126,74 +218,24 @@ impl<'a> Interpreter<'a> {
                         expression.span,
                     ));
                 };
-                let Statement::VarDecl(declared_expression) = symbol.clone().node else {
-                    panic!(
-                        "attempted to evaluate non-statement {:?}",
-                        symbol.clone().node
-                    );
+                let Statement::VarDecl(declared_expression) = &symbol.node else {
+                    panic!("attempted to evaluate non-statement {:?}", symbol.node);
                 };
-                let evaluated_expression = self.evaluate_expression(&declared_expression.value)?;
-                Ok(evaluated_expression)
+                // Declarations and assignments store the evaluated value as a literal
+                let Expression::Literal(literal) = &declared_expression.value.node else {
+                    panic!("variable {} holds an unevaluated value", variable_ref.name);
+                };
+                Ok(literal.value.clone())
             }
             ast::Expression::Binary(binary_expression) => {
                 let left_value = self.evaluate_expression(&binary_expression.left)?;
                 let right_value = self.evaluate_expression(&binary_expression.right)?;
-                match binary_expression.operator {
-                    BinaryOperator::Add
-                    | BinaryOperator::Subtract
-                    | BinaryOperator::Multiply
-                    | BinaryOperator::Divide
-                    | BinaryOperator::Modulo
-                    | BinaryOperator::GreaterThan
-                    | BinaryOperator::LessThan
-                    | BinaryOperator::GreaterThanOrEqual
-                    | BinaryOperator::LessThanOrEqual => {
-                        let LiteralValue::Number(left_number) = left_value else {
-                            return Err(RuntimeError::new(
-                                format!("not a number: {}", left_value),
-                                binary_expression.left.span,
-                            ));
-                        };
-                        let LiteralValue::Number(right_number) = right_value else {
-                            return Err(RuntimeError::new(
-                                format!("not a number: {}", right_value),
-                                binary_expression.right.span,
-                            ));
-                        };
-                        match binary_expression.operator {
-                            BinaryOperator::Add => {
-                                Ok(LiteralValue::Number(left_number + right_number))
-                            }
-                            BinaryOperator::Subtract => {
-                                Ok(LiteralValue::Number(left_number - right_number))
-                            }
-                            BinaryOperator::Multiply => {
-                                Ok(LiteralValue::Number(left_number * right_number))
-                            }
-                            BinaryOperator::Divide => {
-                                Ok(LiteralValue::Number(left_number / right_number))
-                            }
-                            BinaryOperator::Modulo => {
-                                Ok(LiteralValue::Number(left_number % right_number))
-                            }
-                            BinaryOperator::GreaterThan => {
-                                Ok(LiteralValue::Bool(left_number > right_number))
-                            }
-                            BinaryOperator::LessThan => {
-                                Ok(LiteralValue::Bool(left_number < right_number))
-                            }
-                            BinaryOperator::GreaterThanOrEqual => {
-                                Ok(LiteralValue::Bool(left_number >= right_number))
-                            }
-                            BinaryOperator::LessThanOrEqual => {
-                                Ok(LiteralValue::Bool(left_number <= right_number))
-                            }
-                            _ => unreachable!(),
-                        }
-                    }
-                    BinaryOperator::Equal => Ok(LiteralValue::Bool(left_value == right_value)),
-                    BinaryOperator::NotEqual => Ok(LiteralValue::Bool(left_value != right_value)),
-                }
+                binary_operation(
+                    binary_expression.operator,
+                    left_value,
+                    right_value,
+                    expression.span,
+                )
             }
         }
     }
@@ -201,7 +243,7 @@ impl<'a> Interpreter<'a> {
     fn execute_function(
         &mut self,
         function: &ast::SpannedStatement,
-        arg_vals: &Vec<LiteralValue>,
+        arg_vals: &[LiteralValue],
     ) -> Result<LiteralValue, RuntimeError> {
         let Statement::Function(func) = function.node.clone() else {
             return Err(RuntimeError::new(
@@ -227,20 +269,20 @@ impl<'a> Interpreter<'a> {
             .map(|opt| opt.unwrap_or(LiteralValue::Void))
     }
 
+    // Executes the loop body until the condition is false. Each iteration gets a fresh scope
     fn execute_loop(
         &mut self,
         loop_statement: &ast::LoopStatement,
     ) -> Result<Option<LiteralValue>, RuntimeError> {
-        self.scope_stack.enter_scope();
-        let condition_value = self.evaluate_expression(&loop_statement.condition)?;
-        if condition_value == LiteralValue::Bool(false) {
+        while self.evaluate_expression(&loop_statement.condition)? == LiteralValue::Bool(true) {
+            self.scope_stack.enter_scope();
+            let returned = self.execute_statements(&loop_statement.body)?;
             self.scope_stack.exit_scope();
-            return Ok(None);
-        }
-        if let Some(returned) = self.execute_statements(&loop_statement.body)? {
-            return Ok(Some(returned));
+            if returned.is_some() {
+                return Ok(returned);
+            }
         }
-        Ok(self.execute_loop(loop_statement)?)
+        Ok(None)
     }
 
     fn execute_if(
@@ -252,7 +294,7 @@ impl<'a> Interpreter<'a> {
         let return_value = if condition_value == LiteralValue::Bool(true) {
             self.execute_statements(&if_statement.then_body)?
         } else if let Some(else_body) = &if_statement.else_body {
-            self.execute_statements(&vec![*else_body.clone()])?
+            self.execute_statements(std::slice::from_ref(else_body))?
         } else {
             None
         };
@@ -263,21 +305,31 @@ impl<'a> Interpreter<'a> {
     // Executes a vec of statement and returns the value if any of the statements is a return
     fn execute_statements(
         &mut self,
-        body: &Vec<SpannedStatement>,
+        body: &[SpannedStatement],
     ) -> Result<Option<LiteralValue>, RuntimeError> {
         for statement in body.iter() {
             let statement_return: Option<LiteralValue> = match statement.node.clone() {
-                ast::Statement::Function(_) | ast::Statement::VarDecl(_) => {
+                ast::Statement::Function(_) => {
                     self.scope_stack.add_statement(statement.clone());
                     None
                 }
-                ast::Statement::Return(return_statement) => return_statement
-                    .value
-                    .as_ref()
-                    .map(|v| self.evaluate_expression(v))
-                    .transpose()?,
+                ast::Statement::VarDecl(mut var_decl) => {
+                    // The value is evaluated once, here, not every time the variable is read
+                    let value = self.evaluate_expression(&var_decl.value)?;
+                    var_decl.value.node = Expression::Literal(LiteralExpression { value });
+                    self.scope_stack
+                        .add_statement(Statement::VarDecl(var_decl).spanned(statement.span));
+                    None
+                }
+                // A bare `return;` still leaves the function, with no value
+                ast::Statement::Return(return_statement) => Some(match &return_statement.value {
+                    Some(value) => self.evaluate_expression(value)?,
+                    None => LiteralValue::Void,
+                }),
                 ast::Statement::If(if_statement) => self.execute_if(&if_statement)?,
                 ast::Statement::Loop(loop_statement) => self.execute_loop(&loop_statement)?,
+                // Imports are resolved by linking files together before they run
+                ast::Statement::Import(_) => None,
                 ast::Statement::Expr(expr) => {
                     // NOTE: Might want to filter by function calls here.
                     self.evaluate_expression(&expr.spanned(statement.span))?;
@@ -319,12 +371,186 @@ impl<'a> Interpreter<'a> {
         Ok(None)
     }
 
-    pub fn execute(&mut self) -> Result<(), RuntimeError> {
-        self.scope_stack.enter_scope();
-        self.initialize_built_ins();
-        self.execute_statements(&self.program_ast.body)?;
+    // Evaluates a standalone expression in the global scope and returns its value
+    pub fn evaluate(
+        &mut self,
+        expression: &ast::SpannedExpression,
+    ) -> Result<LiteralValue, RuntimeError> {
+        self.context.exit_code = None;
+        let result = self.evaluate_expression(expression);
+        let _ = self.context.output.flush();
+        if result.is_err() {
+            self.scope_stack.reset_to_global();
+        }
+        self.exited(result)
+    }
+
+    // Gives access to the output and input used by built-ins
+    pub fn context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    // Forgets every declared function and variable. Built-ins and I/O are kept
+    pub fn reset(&mut self) {
+        self.scope_stack = ScopeStack::new();
+        self.scope_stack.enter_scope(); // Global scope
+    }
+
+    // Executes a program in the global scope. Declarations are kept, so several programs can be
+    // executed one after another against the same interpreter
+    pub fn execute(&mut self, program_ast: &ast::Program) -> Result<(), RuntimeError> {
+        self.context.exit_code = None;
+        let result = self.execute_statements(&program_ast.body);
+        // Output written without a trailing newline may still be buffered
+        let _ = self.context.output.flush();
+        if let Err(err) = result {
+            self.scope_stack.reset_to_global();
+            self.exited(Err(err))?;
+        }
         Ok(())
     }
+
+    // Executes a program, undoing its declarations and its changes to globals if it fails. Callers
+    // that analyze programs one at a time can then drop the failed program's analysis too
+    pub fn execute_or_undo(&mut self, program_ast: &ast::Program) -> Result<(), RuntimeError> {
+        let saved = self.scope_stack.clone();
+        let result = self.execute(program_ast);
+        if result.is_err() {
+            self.scope_stack = saved;
+        }
+        result
+    }
+
+    // The code passed to `exit` by the last program, expression or function run, if it called it
+    pub fn exit_code(&self) -> Option<i32> {
+        self.context.exit_code
+    }
+
+    // Makes `random_int` return the same numbers every run, as the `seed` built-in does
+    pub fn set_seed(&mut self, seed: u64) {
+        self.context.random.seed(seed);
+    }
+
+    // A call to `exit` unwinds like an error but ends the program without failing it
+    fn exited(
+        &self,
+        result: Result<LiteralValue, RuntimeError>,
+    ) -> Result<LiteralValue, RuntimeError> {
+        match result {
+            Err(_) if self.context.exit_code.is_some() => Ok(LiteralValue::Void),
+            result => result,
+        }
+    }
+}
+
+// The built-ins every program can call, shared by the interpreter and the VM
+pub fn standard_built_ins() -> Vec<(&'static str, BuiltInFn)> {
+    let mut built_ins: Vec<(&'static str, BuiltInFn)> = vec![
+        (
+            "print",
+            Rc::new(|context: &mut Context, args: Vec<LiteralValue>, span| {
+                write!(context.output, "{}", args[0]).map_err(|err| write_error(err, span))?;
+                Ok(LiteralValue::Void)
+            }),
+        ),
+        (
+            "println",
+            Rc::new(|context: &mut Context, args: Vec<LiteralValue>, span| {
+                writeln!(context.output, "{}", args[0]).map_err(|err| write_error(err, span))?;
+                Ok(LiteralValue::Void)
+            }),
+        ),
+    ];
+    built_ins.extend(strings::built_ins());
+    built_ins.extend(conversions::built_ins());
+    built_ins.extend(math::built_ins());
+    built_ins.extend(input::built_ins());
+    built_ins.extend(process::built_ins());
+    built_ins.extend(files::built_ins());
+    built_ins.extend(assertions::built_ins());
+    built_ins.extend(random::built_ins());
+    built_ins
+}
+
+// Returns argument `index` of a built-in call as a string. The analyzer checks argument types, so
+// this only fails for programs it never saw
+fn string_arg(args: &[LiteralValue], index: usize, span: Span) -> Result<&str, RuntimeError> {
+    match args.get(index) {
+        Some(LiteralValue::String(value)) => Ok(value),
+        _ => Err(argument_error("a string", index, span)),
+    }
+}
+
+// Returns argument `index` of a built-in call as an int
+fn int_arg(args: &[LiteralValue], index: usize, span: Span) -> Result<i32, RuntimeError> {
+    match args.get(index) {
+        Some(LiteralValue::Number(value)) => Ok(*value),
+        _ => Err(argument_error("an int", index, span)),
+    }
+}
+
+fn argument_error(expected: &str, index: usize, span: Span) -> RuntimeError {
+    RuntimeError::new(
+        format!("expected {} as argument {}", expected, index + 1),
+        span,
+    )
+}
+
+// Converts a count or index to an int, failing if it doesn't fit
+fn int_value(value: usize, span: Span) -> Result<LiteralValue, RuntimeError> {
+    i32::try_from(value)
+        .map(LiteralValue::Number)
+        .map_err(|_| RuntimeError::new(format!("{} does not fit in an int", value), span))
+}
+
+// Applies a binary operator to two evaluated operands. Errors point at the whole expression
+pub fn binary_operation(
+    operator: BinaryOperator,
+    left: LiteralValue,
+    right: LiteralValue,
+    span: Span,
+) -> Result<LiteralValue, RuntimeError> {
+    match operator {
+        BinaryOperator::Equal => return Ok(LiteralValue::Bool(left == right)),
+        BinaryOperator::NotEqual => return Ok(LiteralValue::Bool(left != right)),
+        _ => {}
+    }
+    let (LiteralValue::Number(left), LiteralValue::Number(right)) = (&left, &right) else {
+        let operand = if matches!(left, LiteralValue::Number(_)) {
+            right
+        } else {
+            left
+        };
+        return Err(RuntimeError::new(
+            format!("not a number: {}", operand),
+            span,
+        ));
+    };
+    let (left, right) = (*left, *right);
+    if right == 0 && matches!(operator, BinaryOperator::Divide | BinaryOperator::Modulo) {
+        return Err(RuntimeError::new("division by zero".to_string(), span));
+    }
+    let result = match operator {
+        BinaryOperator::Add => left.checked_add(right),
+        BinaryOperator::Subtract => left.checked_sub(right),
+        BinaryOperator::Multiply => left.checked_mul(right),
+        BinaryOperator::Divide => left.checked_div(right),
+        BinaryOperator::Modulo => left.checked_rem(right),
+        BinaryOperator::GreaterThan => return Ok(LiteralValue::Bool(left > right)),
+        BinaryOperator::LessThan => return Ok(LiteralValue::Bool(left < right)),
+        BinaryOperator::GreaterThanOrEqual => return Ok(LiteralValue::Bool(left >= right)),
+        BinaryOperator::LessThanOrEqual => return Ok(LiteralValue::Bool(left <= right)),
+        BinaryOperator::Equal | BinaryOperator::NotEqual => unreachable!(),
+    };
+    // Results that don't fit an int are errors rather than panics or wrapped values
+    result
+        .map(LiteralValue::Number)
+        .ok_or_else(|| RuntimeError::new("integer overflow".to_string(), span))
+}
+
+// Reports a failure to write program output as a runtime error at the call
+fn write_error(err: std::io::Error, span: Span) -> RuntimeError {
+    RuntimeError::new(format!("failed to write output: {}", err), span)
 }
 
 #[derive(Debug, PartialEq)]
@@ -344,3 +570,9 @@ impl Display for RuntimeError {
         write!(f, "RuntimeError: {} (at {})", self.message, self.span)
     }
 }
+
+impl From<RuntimeError> for PerryError {
+    fn from(err: RuntimeError) -> Self {
+        PerryError::new(ErrorKind::Runtime, err.message, err.span)
+    }
+}
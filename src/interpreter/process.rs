@@ -0,0 +1,55 @@
+use std::rc::Rc;
+
+use crate::common::ast::LiteralValue;
+
+use super::{BuiltInFn, Context, RuntimeError, int_arg, int_value};
+
+// Built-ins for the program's command-line arguments and exit status
+pub fn built_ins() -> Vec<(&'static str, BuiltInFn)> {
+    vec![
+        (
+            "arg",
+            Rc::new(|context: &mut Context, args: Vec<LiteralValue>, span| {
+                let index = int_arg(&args, 0, span)?;
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|index| context.args.get(index))
+                    .map(|arg| LiteralValue::String(arg.clone()))
+                    .ok_or_else(|| {
+                        RuntimeError::new(
+                            format!(
+                                "argument {} out of bounds for {} arguments",
+                                index,
+                                context.args.len()
+                            ),
+                            span,
+                        )
+                    })
+            }),
+        ),
+        (
+            "arg_count",
+            Rc::new(|context: &mut Context, _: Vec<LiteralValue>, span| {
+                int_value(context.args.len(), span)
+            }),
+        ),
+        (
+            "exit",
+            // Unwinds like an error. `execute` sees the exit code and ends the program normally
+            Rc::new(|context: &mut Context, args: Vec<LiteralValue>, span| {
+                let code = int_arg(&args, 0, span)?;
+                if !(0..=255).contains(&code) {
+                    return Err(RuntimeError::new(
+                        format!("exit code {} is not between 0 and 255", code),
+                        span,
+                    ));
+                }
+                context.exit_code = Some(code);
+                Err(RuntimeError::new(
+                    format!("program exited with code {}", code),
+                    span,
+                ))
+            }),
+        ),
+    ]
+}
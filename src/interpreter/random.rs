@@ -0,0 +1,90 @@
+use std::{
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::common::ast::LiteralValue;
+
+use super::{BuiltInFn, Context, RuntimeError, int_arg};
+
+// A small pseudo-random number generator (SplitMix64). Not suitable for cryptography, but fast,
+// and the same seed always gives the same numbers on every platform
+#[derive(Debug, Clone, PartialEq)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // Seeded from the clock, so unseeded programs differ between runs
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    // Restarts the sequence of numbers
+    pub fn seed(&mut self, seed: u64) {
+        self.state = seed;
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // A number between `low` and `high`, both included. Draws that would favor some numbers are
+    // thrown away
+    pub fn int_between(&mut self, low: i32, high: i32) -> i32 {
+        let range = (i64::from(high) - i64::from(low)) as u64 + 1;
+        let limit = u64::MAX - u64::MAX % range;
+        loop {
+            let n = self.next_u64();
+            if n < limit {
+                return (i64::from(low) + (n % range) as i64) as i32;
+            }
+        }
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::from_time()
+    }
+}
+
+// Built-ins drawing pseudo-random numbers. Programs calling `seed` get the same numbers every run
+pub fn built_ins() -> Vec<(&'static str, BuiltInFn)> {
+    vec![
+        (
+            "random_int",
+            Rc::new(|context: &mut Context, args: Vec<LiteralValue>, span| {
+                let (low, high) = (int_arg(&args, 0, span)?, int_arg(&args, 1, span)?);
+                if low > high {
+                    return Err(RuntimeError::new(
+                        format!("random_int bounds {}..{} are reversed", low, high),
+                        span,
+                    ));
+                }
+                Ok(LiteralValue::Number(context.random.int_between(low, high)))
+            }),
+        ),
+        (
+            "seed",
+            Rc::new(|context: &mut Context, args: Vec<LiteralValue>, span| {
+                // Negative seeds are as good as any other
+                context
+                    .random
+                    .seed(i64::from(int_arg(&args, 0, span)?) as u64);
+                Ok(LiteralValue::Void)
+            }),
+        ),
+    ]
+}
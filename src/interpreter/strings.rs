@@ -0,0 +1,178 @@
+use std::rc::Rc;
+
+use crate::common::ast::{LiteralValue, Span};
+
+use super::{BuiltInFn, Context, RuntimeError, int_arg, int_value, string_arg};
+
+// String built-ins. Indices and lengths count characters, not bytes, so they never split one
+pub fn built_ins() -> Vec<(&'static str, BuiltInFn)> {
+    vec![
+        (
+            "len",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                int_value(string_arg(&args, 0, span)?.chars().count(), span)
+            }),
+        ),
+        (
+            "substring",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let text = string_arg(&args, 0, span)?;
+                let (start, end) = (int_arg(&args, 1, span)?, int_arg(&args, 2, span)?);
+                let length = text.chars().count();
+                if start < 0 || end < start || end as usize > length {
+                    return Err(RuntimeError::new(
+                        format!(
+                            "substring {}..{} out of bounds for string of length {}",
+                            start, end, length
+                        ),
+                        span,
+                    ));
+                }
+                Ok(LiteralValue::String(
+                    text.chars()
+                        .skip(start as usize)
+                        .take((end - start) as usize)
+                        .collect(),
+                ))
+            }),
+        ),
+        (
+            "index_of",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let text = string_arg(&args, 0, span)?;
+                match text.find(string_arg(&args, 1, span)?) {
+                    Some(offset) => int_value(text[..offset].chars().count(), span),
+                    None => Ok(LiteralValue::Number(-1)),
+                }
+            }),
+        ),
+        (
+            "contains",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let text = string_arg(&args, 0, span)?;
+                Ok(LiteralValue::Bool(
+                    text.contains(string_arg(&args, 1, span)?),
+                ))
+            }),
+        ),
+        (
+            "starts_with",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let text = string_arg(&args, 0, span)?;
+                Ok(LiteralValue::Bool(
+                    text.starts_with(string_arg(&args, 1, span)?),
+                ))
+            }),
+        ),
+        (
+            "ends_with",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let text = string_arg(&args, 0, span)?;
+                Ok(LiteralValue::Bool(
+                    text.ends_with(string_arg(&args, 1, span)?),
+                ))
+            }),
+        ),
+        (
+            "to_upper",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                Ok(LiteralValue::String(
+                    string_arg(&args, 0, span)?.to_uppercase(),
+                ))
+            }),
+        ),
+        (
+            "to_lower",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                Ok(LiteralValue::String(
+                    string_arg(&args, 0, span)?.to_lowercase(),
+                ))
+            }),
+        ),
+        (
+            "trim",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                Ok(LiteralValue::String(
+                    string_arg(&args, 0, span)?.trim().to_string(),
+                ))
+            }),
+        ),
+        (
+            "split_count",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                int_value(split(&args, span)?.count(), span)
+            }),
+        ),
+        (
+            "split_part",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let index = int_arg(&args, 2, span)?;
+                let parts: Vec<&str> = split(&args, span)?.collect();
+                let part = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| parts.get(index))
+                    .ok_or_else(|| {
+                        RuntimeError::new(
+                            format!("part {} out of bounds for {} parts", index, parts.len()),
+                            span,
+                        )
+                    })?;
+                Ok(LiteralValue::String(part.to_string()))
+            }),
+        ),
+        (
+            "repeat",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let text = string_arg(&args, 0, span)?;
+                let count = int_arg(&args, 1, span)?;
+                if count < 0 {
+                    return Err(RuntimeError::new(
+                        format!("repeat count {} is negative", count),
+                        span,
+                    ));
+                }
+                // The length of the result has to fit in an int
+                if text.len().saturating_mul(count as usize) > i32::MAX as usize {
+                    return Err(RuntimeError::new(
+                        "repeated string would be too long".to_string(),
+                        span,
+                    ));
+                }
+                Ok(LiteralValue::String(text.repeat(count as usize)))
+            }),
+        ),
+        (
+            "char_at",
+            Rc::new(|_: &mut Context, args: Vec<LiteralValue>, span| {
+                let text = string_arg(&args, 0, span)?;
+                let index = int_arg(&args, 1, span)?;
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|index| text.chars().nth(index))
+                    .map(|ch| LiteralValue::String(ch.to_string()))
+                    .ok_or_else(|| {
+                        RuntimeError::new(
+                            format!(
+                                "index {} out of bounds for string of length {}",
+                                index,
+                                text.chars().count()
+                            ),
+                            span,
+                        )
+                    })
+            }),
+        ),
+    ]
+}
+
+// Splits the first argument on the second, which may not be empty
+fn split(args: &[LiteralValue], span: Span) -> Result<std::str::Split<'_, &str>, RuntimeError> {
+    let separator = string_arg(args, 1, span)?;
+    if separator.is_empty() {
+        return Err(RuntimeError::new(
+            "split separator is empty".to_string(),
+            span,
+        ));
+    }
+    Ok(string_arg(args, 0, span)?.split(separator))
+}
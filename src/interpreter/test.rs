@@ -1,11 +1,122 @@
 #![cfg(test)]
 
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
 use crate::analyzer::Analyzer;
-use crate::interpreter::Interpreter;
+use crate::interpreter::{Interpreter, OutputBuffer, RuntimeError, random::Random};
 use crate::parser::Parser;
 use crate::tokenizer::Tokenizer;
+use crate::vm::Vm;
+
+// Runs the program on both the interpreter and the VM and returns everything it printed. The
+// backends must agree
+pub fn run_with_output(input: &str) -> String {
+    run_with_input(input, "")
+}
+
+// Like `run_with_output`, with `stdin` as the program's input
+pub fn run_with_input(input: &str, stdin: &str) -> String {
+    let mut tokenizer = Tokenizer::new(input.to_string());
+    let tokens = tokenizer.tokenize().unwrap();
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse().unwrap();
+    let mut analyzer = Analyzer::new();
+    analyzer.analyze(&ast).unwrap();
+    let output = OutputBuffer::new();
+    let stdin = stdin.as_bytes().to_vec();
+    let mut interpreter = Interpreter::with_io(
+        Box::new(output.clone()),
+        Box::new(Cursor::new(stdin.clone())),
+    );
+    interpreter.execute(&ast).unwrap();
+    let vm_output = OutputBuffer::new();
+    let mut vm = Vm::with_io(Box::new(vm_output.clone()), Box::new(Cursor::new(stdin)));
+    vm.execute(&ast).unwrap();
+    assert_eq!(vm_output.contents(), output.contents(), "VM output differs");
+    output.contents()
+}
+
+// Runs the program on both backends with command-line arguments. Returns what it printed and the
+// code it exited with, if it called `exit`
+pub fn run_with_args(input: &str, args: &[&str]) -> (String, Option<i32>) {
+    let ast = Parser::new(Tokenizer::new(input.to_string()).tokenize().unwrap())
+        .parse()
+        .unwrap();
+    Analyzer::new().analyze(&ast).unwrap();
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let output = OutputBuffer::new();
+    let mut interpreter = Interpreter::with_io(Box::new(output.clone()), Box::new(&b""[..]));
+    interpreter.context_mut().args = args.clone();
+    interpreter.execute(&ast).unwrap();
+    let vm_output = OutputBuffer::new();
+    let mut vm = Vm::with_io(Box::new(vm_output.clone()), Box::new(&b""[..]));
+    vm.context_mut().args = args;
+    vm.execute(&ast).unwrap();
+    assert_eq!(vm_output.contents(), output.contents(), "VM output differs");
+    assert_eq!(
+        vm.exit_code(),
+        interpreter.exit_code(),
+        "VM exit code differs"
+    );
+    (output.contents(), interpreter.exit_code())
+}
+
+// Creates an empty directory for a test to put files in
+pub fn test_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("perry-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+// Runs the program on both backends with file access. Each backend gets its own root under
+// `directory`, prepared by `setup`. Returns what the program printed or the runtime error the
+// backends agree on
+pub fn run_with_files(
+    input: &str,
+    directory: &Path,
+    setup: impl Fn(&Path),
+) -> Result<String, RuntimeError> {
+    let ast = Parser::new(Tokenizer::new(input.to_string()).tokenize().unwrap())
+        .parse()
+        .unwrap();
+    Analyzer::new().analyze(&ast).unwrap();
+    let root = |backend: &str| {
+        let root = directory.join(backend);
+        fs::create_dir_all(&root).unwrap();
+        setup(&root);
+        Some(root)
+    };
+    let output = OutputBuffer::new();
+    let mut interpreter = Interpreter::with_io(Box::new(output.clone()), Box::new(&b""[..]));
+    interpreter.context_mut().file_root = root("interpreter");
+    let result = interpreter.execute(&ast);
+    let vm_output = OutputBuffer::new();
+    let mut vm = Vm::with_io(Box::new(vm_output.clone()), Box::new(&b""[..]));
+    vm.context_mut().file_root = root("vm");
+    assert_eq!(vm.execute(&ast), result, "VM result differs");
+    assert_eq!(vm_output.contents(), output.contents(), "VM output differs");
+    result.map(|_| output.contents())
+}
+
+// Runs a program that fails at runtime on both backends and returns the error they agree on
+pub fn run_with_error(input: &str) -> RuntimeError {
+    let ast = Parser::new(Tokenizer::new(input.to_string()).tokenize().unwrap())
+        .parse()
+        .unwrap();
+    Analyzer::new().analyze(&ast).unwrap();
+    let mut interpreter = Interpreter::with_io(Box::new(OutputBuffer::new()), Box::new(&b""[..]));
+    let err = interpreter.execute(&ast).unwrap_err();
+    let mut vm = Vm::with_io(Box::new(OutputBuffer::new()), Box::new(&b""[..]));
+    assert_eq!(vm.execute(&ast).unwrap_err(), err, "VM error differs");
+    err
+}
 
-mod test {
+mod tests {
     use super::*;
 
     #[test]
@@ -23,26 +134,587 @@ mod test {
         else {println("Odd");}if (is_prime(i)) {println("Prime number:");
         println(i);} var fact: int = factorial(i);println("Factorial:");
         println(fact);i = i + 1;}}complex(5);"#;
-        let mut tokenizer = Tokenizer::new(input.to_string());
-        let tokens = tokenizer.tokenize().unwrap();
-        let mut parser = Parser::new(tokens);
-        let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        analyzer.analyze().unwrap();
-        let mut interpreter = Interpreter::new(&ast);
-        interpreter.execute().unwrap();
+        let expected = "Odd\nFactorial:\n1\nEven\nPrime number:\n2\nFactorial:\n2\n\
+            Odd\nPrime number:\n3\nFactorial:\n6\nEven\nFactorial:\n24\n\
+            Odd\nPrime number:\n5\nFactorial:\n120\n";
+        assert_eq!(run_with_output(input), expected);
     }
 
     #[test]
     fn builtin_print() {
-        let input = r#"println("Hello, World!");"#;
-        let mut tokenizer = Tokenizer::new(input.to_string());
-        let tokens = tokenizer.tokenize().unwrap();
-        let mut parser = Parser::new(tokens);
-        let ast = parser.parse().unwrap();
-        let mut analyzer = Analyzer::new(&ast);
-        analyzer.analyze().unwrap();
-        let mut interpreter = Interpreter::new(&ast);
-        interpreter.execute().unwrap();
+        let input = r#"println("Hello, World!"); print(1); print(true);"#;
+        assert_eq!(run_with_output(input), "Hello, World!\n1true");
+    }
+
+    #[test]
+    // Loops used to recurse once per iteration
+    fn long_loop() {
+        let input = r#"var i: int = 0; while (i < 100000) { var t: int = i; i = i + 1; }
+        println(i);"#;
+        assert_eq!(run_with_output(input), "100000\n");
+    }
+
+    #[test]
+    // Variables hold the value they were given, not the expression that computed it
+    fn variables_evaluated_once() {
+        let input = r#"func next(): int { println("called"); return 1; }
+        var i: int = 1; var t: int = i; i = 5; println(t);
+        var n: int = next(); println(n + n);"#;
+        assert_eq!(run_with_output(input), "1\ncalled\n2\n");
+    }
+
+    #[test]
+    fn bare_return_leaves_function() {
+        let input = r#"func f(n: int): void { if (n > 1) { return; } println(n); }
+        f(1); f(2); f(0);"#;
+        assert_eq!(run_with_output(input), "1\n0\n");
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let source = "var zero: int = 0;\nprintln(1 / zero);";
+        let ast = Parser::new(Tokenizer::new(source.to_string()).tokenize().unwrap())
+            .parse()
+            .unwrap();
+        let output = OutputBuffer::new();
+        let mut interpreter = Interpreter::with_io(Box::new(output.clone()), Box::new(&b""[..]));
+        let err = interpreter.execute(&ast).unwrap_err();
+        assert_eq!(err.message, "division by zero");
+        assert_eq!((err.span.start.line, err.span.start.column), (2, 9));
+        let mut vm = Vm::with_io(Box::new(output), Box::new(&b""[..]));
+        assert_eq!(vm.execute(&ast).unwrap_err(), err);
+    }
+
+    #[test]
+    fn string_built_ins() {
+        let cases = vec![
+            (r#"len("héllo")"#, "5"),
+            (r#"len("")"#, "0"),
+            (r#"substring("héllo", 1, 3)"#, "él"),
+            (r#"substring("abc", 3, 3)"#, ""),
+            (r#"index_of("héllo", "l")"#, "2"),
+            (r#"index_of("hello", "z")"#, "-1"),
+            (r#"index_of("hello", "")"#, "0"),
+            (r#"contains("hello", "ell")"#, "true"),
+            (r#"contains("hello", "L")"#, "false"),
+            (r#"starts_with("hello", "he")"#, "true"),
+            (r#"ends_with("hello", "he")"#, "false"),
+            (r#"to_upper("Straße")"#, "STRASSE"),
+            (r#"to_lower("HeLLo")"#, "hello"),
+            (r#"trim("  a b  ")"#, "a b"),
+            (r#"split_count("a,b,,c", ",")"#, "4"),
+            (r#"split_count("", ",")"#, "1"),
+            (r#"split_part("a,b,,c", ",", 1)"#, "b"),
+            (r#"split_part("a,b,,c", ",", 2)"#, ""),
+            (r#"split_part("key = value", " = ", 1)"#, "value"),
+            (r#"repeat("ab", 3)"#, "ababab"),
+            (r#"repeat("ab", 0)"#, ""),
+            (r#"char_at("héllo", 1)"#, "é"),
+        ];
+        for (call, expected) in cases {
+            let input = format!("println({});", call);
+            assert_eq!(
+                run_with_output(&input),
+                format!("{}\n", expected),
+                "{}",
+                call
+            );
+        }
+    }
+
+    #[test]
+    fn string_built_in_errors() {
+        let cases = vec![
+            (
+                r#"substring("hello", 2, 9)"#,
+                "substring 2..9 out of bounds for string of length 5",
+            ),
+            (
+                r#"substring("hello", 3, 1)"#,
+                "substring 3..1 out of bounds for string of length 5",
+            ),
+            (
+                r#"substring("hello", 0 - 1, 2)"#,
+                "substring -1..2 out of bounds for string of length 5",
+            ),
+            (
+                r#"char_at("héllo", 5)"#,
+                "index 5 out of bounds for string of length 5",
+            ),
+            (
+                r#"char_at("hello", 0 - 1)"#,
+                "index -1 out of bounds for string of length 5",
+            ),
+            (
+                r#"split_part("a,b", ",", 2)"#,
+                "part 2 out of bounds for 2 parts",
+            ),
+            (r#"split_count("a,b", "")"#, "split separator is empty"),
+            (r#"repeat("ab", 0 - 1)"#, "repeat count -1 is negative"),
+            (
+                r#"repeat("ab", 2000000000)"#,
+                "repeated string would be too long",
+            ),
+        ];
+        for (call, message) in cases {
+            let input = format!("var x: int = 1;\nprintln({});", call);
+            let err = run_with_error(&input);
+            assert_eq!(err.message, message, "{}", call);
+            // Errors point at the call
+            assert_eq!(err.span.start.line, 2, "{}", call);
+            assert_eq!(err.span.start.column, 9, "{}", call);
+            assert_eq!(err.span.len, call.len(), "{}", call);
+        }
+    }
+
+    #[test]
+    fn conversion_built_ins() {
+        let cases = vec![
+            (r#"len(to_string(42))"#, "2"),
+            (r#"to_string(0 - 7)"#, "-7"),
+            (r#"to_string(true)"#, "true"),
+            (r#"to_string("text")"#, "text"),
+            (r#"parse_int("42") + 1"#, "43"),
+            (r#"parse_int("-2147483648")"#, "-2147483648"),
+            (r#"parse_int("+7")"#, "7"),
+            (r#"parse_int(to_string(123))"#, "123"),
+            (r#"to_bool("true")"#, "true"),
+            (r#"to_bool("false")"#, "false"),
+            (r#"to_bool(0)"#, "false"),
+            (r#"to_bool(2)"#, "true"),
+            (r#"to_bool(1 > 2)"#, "false"),
+        ];
+        for (call, expected) in cases {
+            let input = format!("println({});", call);
+            assert_eq!(
+                run_with_output(&input),
+                format!("{}\n", expected),
+                "{}",
+                call
+            );
+        }
+    }
+
+    #[test]
+    fn conversion_built_in_errors() {
+        let cases = vec![
+            (r#"parse_int("4 2")"#, r#"cannot parse "4 2" as an int"#),
+            (r#"parse_int("")"#, r#"cannot parse "" as an int"#),
+            (r#"parse_int(" 1")"#, r#"cannot parse " 1" as an int"#),
+            (
+                r#"parse_int("2147483648")"#,
+                r#""2147483648" is out of range for an int"#,
+            ),
+            (r#"to_bool("yes")"#, r#"cannot convert "yes" to a bool"#),
+        ];
+        for (call, message) in cases {
+            let err = run_with_error(&format!("println({});", call));
+            assert_eq!(err.message, message, "{}", call);
+            assert_eq!(err.span.len, call.len(), "{}", call);
+        }
+    }
+
+    #[test]
+    fn math_built_ins() {
+        let cases = vec![
+            ("abs(0 - 5)", "5"),
+            ("abs(5)", "5"),
+            ("min(3, 0 - 2)", "-2"),
+            ("max(3, 0 - 2)", "3"),
+            ("pow(2, 10)", "1024"),
+            ("pow(0 - 3, 3)", "-27"),
+            ("pow(7, 0)", "1"),
+            ("pow(2, 30)", "1073741824"),
+            ("isqrt(0)", "0"),
+            ("isqrt(15)", "3"),
+            ("isqrt(16)", "4"),
+            ("isqrt(2147483647)", "46340"),
+            ("clamp(15, 0, 10)", "10"),
+            ("clamp(0 - 5, 0, 10)", "0"),
+            ("clamp(5, 0, 10)", "5"),
+            ("gcd(12, 18)", "6"),
+            ("gcd(0 - 12, 18)", "6"),
+            ("gcd(0, 7)", "7"),
+            ("gcd(0, 0)", "0"),
+            ("sign(0 - 9)", "-1"),
+            ("sign(0)", "0"),
+            ("sign(9)", "1"),
+        ];
+        for (call, expected) in cases {
+            let input = format!("println({});", call);
+            assert_eq!(
+                run_with_output(&input),
+                format!("{}\n", expected),
+                "{}",
+                call
+            );
+        }
+    }
+
+    #[test]
+    fn math_built_in_errors() {
+        let min = "var min_int: int = 0 - 2147483647 - 1;\n";
+        let cases = vec![
+            ("pow(2, 31)", "pow(2, 31) overflows an int"),
+            ("pow(10, 0 - 1)", "exponent -1 is negative"),
+            ("abs(min_int)", "abs(-2147483648) overflows an int"),
+            ("gcd(min_int, 0)", "gcd(-2147483648, 0) overflows an int"),
+            ("isqrt(0 - 4)", "isqrt of negative number -4"),
+            ("clamp(1, 5, 2)", "clamp bounds 5..2 are reversed"),
+        ];
+        for (call, message) in cases {
+            let err = run_with_error(&format!("{}println({});", min, call));
+            assert_eq!(err.message, message, "{}", call);
+            assert_eq!(err.span.start.line, 2, "{}", call);
+            assert_eq!(err.span.len, call.len(), "{}", call);
+        }
+    }
+
+    #[test]
+    // Programs may declare the names of built-ins, replacing them where the declaration is visible
+    fn redefined_built_ins() {
+        let input = r#"println(abs(0 - 2));
+func abs(n: int): int {
+    return n + 100;
+}
+func maximum(a: int, b: int): int {
+    var max: int = a;
+    if (b > max) {
+        max = b;
+    }
+    return max;
+}
+println(abs(0 - 2));
+println(maximum(3, 7));
+println(max(3, 7));
+func max(a: int, b: int): int {
+    return a;
+}
+println(max(3, 7));"#;
+        assert_eq!(run_with_output(input), "2\n98\n7\n7\n3\n");
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let cases = [
+            "2147483647 + 1",
+            "0 - 2147483647 - 2",
+            "2147483647 * 2",
+            "(0 - 2147483647 - 1) / (0 - 1)",
+            "(0 - 2147483647 - 1) % (0 - 1)",
+        ];
+        for expression in cases {
+            let err = run_with_error(&format!("println({});", expression));
+            assert_eq!(err.message, "integer overflow", "{}", expression);
+            // The error points at the operation that overflowed
+            assert_eq!(err.span.start.column, 9, "{}", expression);
+            assert_eq!(err.span.len, expression.len(), "{}", expression);
+        }
+        assert_eq!(
+            run_with_output("println(2147483646 + 1);\nprintln(0 - 2147483647 - 1);"),
+            "2147483647\n-2147483648\n"
+        );
+    }
+
+    #[test]
+    // A filter reading its input line by line
+    fn read_lines_until_eof() {
+        let input = r#"var count: int = 0;
+while (eof() == false) {
+    var line: string = read_line();
+    count = count + 1;
+    println(to_upper(line));
+}
+println(count);"#;
+        assert_eq!(run_with_input(input, "one\r\n\nthree"), "ONE\n\nTHREE\n3\n");
+        assert_eq!(run_with_input(input, ""), "0\n");
+    }
+
+    #[test]
+    fn read_input() {
+        let input = r#"var name: string = input("name? ");
+println(name);
+println(read_all());
+println(read_line() == "");
+println(eof());"#;
+        assert_eq!(
+            run_with_input(input, "Perry\nrest\nof it\n"),
+            "name? Perry\nrest\nof it\n\ntrue\ntrue\n"
+        );
+    }
+
+    #[test]
+    fn program_args() {
+        let input = r#"var i: int = 0;
+while (i < arg_count()) {
+    println(arg(i));
+    i = i + 1;
+}"#;
+        assert_eq!(
+            run_with_args(input, &["a", "--b", ""]),
+            ("a\n--b\n\n".to_string(), None)
+        );
+        assert_eq!(run_with_args(input, &[]), (String::new(), None));
+        let err = run_with_error("println(arg(0));");
+        assert_eq!(err.message, "argument 0 out of bounds for 0 arguments");
+    }
+
+    #[test]
+    // `exit` unwinds out of nested calls and loops, and ends the program without an error
+    fn exit_unwinds() {
+        let input = r#"func check(n: int): void {
+    while (true) {
+        if (n > 2) {
+            print("too big");
+            exit(3);
+        }
+        return;
+    }
+}
+check(1);
+println("checked");
+check(5);
+println("unreachable");"#;
+        assert_eq!(
+            run_with_args(input, &[]),
+            ("checked\ntoo big".to_string(), Some(3))
+        );
+        assert_eq!(run_with_args("exit(0);", &[]), (String::new(), Some(0)));
+        for code in ["256", "0 - 1"] {
+            let err = run_with_error(&format!("exit({});", code));
+            assert!(
+                err.message.ends_with("is not between 0 and 255"),
+                "{}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    // The exit code is cleared for each program run on the same backend
+    fn exit_code_per_program() {
+        let exits = Parser::new(Tokenizer::new("exit(4);".to_string()).tokenize().unwrap())
+            .parse()
+            .unwrap();
+        let ends = Parser::new(
+            Tokenizer::new("println(1);".to_string())
+                .tokenize()
+                .unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let mut interpreter =
+            Interpreter::with_io(Box::new(OutputBuffer::new()), Box::new(&b""[..]));
+        let mut vm = Vm::with_io(Box::new(OutputBuffer::new()), Box::new(&b""[..]));
+        interpreter.execute(&exits).unwrap();
+        vm.execute(&exits).unwrap();
+        assert_eq!(
+            (interpreter.exit_code(), vm.exit_code()),
+            (Some(4), Some(4))
+        );
+        interpreter.execute(&ends).unwrap();
+        vm.execute(&ends).unwrap();
+        assert_eq!((interpreter.exit_code(), vm.exit_code()), (None, None));
+    }
+
+    #[test]
+    fn files_disabled_by_default() {
+        for call in [
+            r#"read_file("a.txt")"#,
+            r#"file_exists("a.txt")"#,
+            r#"write_file("a.txt", "")"#,
+        ] {
+            let err = run_with_error(&format!("println({});", call));
+            assert_eq!(err.message, "file access is disabled", "{}", call);
+            assert_eq!(err.span.len, call.len(), "{}", call);
+        }
+    }
+
+    #[test]
+    fn read_and_write_files() {
+        let directory = test_directory("files");
+        let input = r#"println(file_exists("out.txt"));
+write_file("out.txt", "hello");
+println(file_exists("out.txt"));
+println(read_file("./sub/../out.txt"));
+write_file("sub/nested.txt", "nested");
+println(read_file("sub/nested.txt"));
+println(file_exists("sub"));"#;
+        let output = run_with_files(input, &directory, |root| {
+            fs::create_dir(root.join("sub")).unwrap();
+        });
+        assert_eq!(output.unwrap(), "false\ntrue\nhello\nnested\nfalse\n");
+        let written = directory.join("interpreter").join("out.txt");
+        assert_eq!(fs::read_to_string(written).unwrap(), "hello");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn file_errors() {
+        let directory = test_directory("file-errors");
+        let outside = directory.join("outside.txt");
+        let cases = vec![
+            (
+                r#"read_file("../outside.txt")"#.to_string(),
+                "../outside.txt is outside the directory files can be accessed in".to_string(),
+            ),
+            (
+                format!(r#"file_exists("{}")"#, outside.display()),
+                format!(
+                    "{} is outside the directory files can be accessed in",
+                    outside.display()
+                ),
+            ),
+            (
+                r#"read_file("missing.txt")"#.to_string(),
+                "cannot read missing.txt: ".to_string(),
+            ),
+            (
+                r#"write_file("missing/a.txt", "")"#.to_string(),
+                "cannot write missing/a.txt: ".to_string(),
+            ),
+            (
+                r#"read_file(".")"#.to_string(),
+                "cannot read .: ".to_string(),
+            ),
+        ];
+        for (call, message) in cases {
+            let err =
+                run_with_files(&format!("println({});", call), &directory, |_| {}).unwrap_err();
+            assert!(
+                err.message.starts_with(&message),
+                "{}: {}",
+                call,
+                err.message
+            );
+            assert_eq!(err.span.len, call.len(), "{}", call);
+        }
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    // Links inside the root can't be used to reach files outside it
+    fn symlinks_cannot_escape_root() {
+        let directory = test_directory("file-links");
+        let outside = test_directory("file-links-outside");
+        fs::write(outside.join("secret.txt"), "secret").unwrap();
+        let link = |root: &Path| std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        let err = run_with_files(
+            r#"println(read_file("link/secret.txt"));"#,
+            &directory,
+            link,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.message,
+            "link/secret.txt is outside the directory files can be accessed in"
+        );
+        fs::remove_dir_all(&directory).unwrap();
+        let err =
+            run_with_files(r#"write_file("link/new.txt", "x");"#, &directory, link).unwrap_err();
+        assert!(
+            err.message
+                .ends_with("is outside the directory files can be accessed in")
+        );
+        assert!(!outside.join("new.txt").exists());
+        fs::remove_dir_all(directory).unwrap();
+        fs::remove_dir_all(outside).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    // A link to a file that doesn't exist yet can't be written through, wherever it points
+    fn dangling_symlinks_refused() {
+        let directory = test_directory("file-dangling-links");
+        let outside = test_directory("file-dangling-links-outside");
+        let link = |root: &Path| {
+            std::os::unix::fs::symlink(outside.join("new.txt"), root.join("link")).unwrap();
+            std::os::unix::fs::symlink(root.join("missing.txt"), root.join("inner")).unwrap();
+        };
+        let cases = [
+            (r#"write_file("link", "x");"#, "link"),
+            (r#"println(read_file("link"));"#, "link"),
+            (r#"println(file_exists("./link"));"#, "./link"),
+            (r#"write_file("inner", "x");"#, "inner"),
+            (r#"write_file("link/new.txt", "x");"#, "link/new.txt"),
+        ];
+        for (input, path) in cases {
+            let err = run_with_files(input, &directory, link).unwrap_err();
+            assert_eq!(
+                err.message,
+                format!("{} goes through a broken symbolic link", path),
+                "{}",
+                input
+            );
+            fs::remove_dir_all(&directory).unwrap();
+        }
+        assert!(!outside.join("new.txt").exists());
+        fs::remove_dir_all(outside).unwrap();
+    }
+
+    #[test]
+    fn assertions() {
+        let output = run_with_output(
+            r#"assert(1 < 2);
+assert_eq("a", "a");
+assert_ne(1, 2);
+println("passed");"#,
+        );
+        assert_eq!(output, "passed\n");
+        let cases = vec![
+            ("assert(1 > 2)", "assertion failed"),
+            ("assert_eq(1 + 1, 3)", "assert_eq failed: 2 != 3"),
+            (r#"assert_eq("1", 1)"#, r#"assert_eq failed: "1" != 1"#),
+            (r#"assert_ne("a", "a")"#, r#"assert_ne failed: "a" == "a""#),
+            ("assert_ne(true, true)", "assert_ne failed: true == true"),
+        ];
+        for (call, message) in cases {
+            let err = run_with_error(&format!("println(1);\n{};", call));
+            assert_eq!(err.message, message, "{}", call);
+            let start = (err.span.start.line, err.span.start.column);
+            assert_eq!(start, (2, 1), "{}", call);
+        }
+    }
+
+    #[test]
+    fn seeded_random_numbers() {
+        let output = run_with_output(
+            r#"seed(7);
+var first: int = random_int(1, 100);
+var second: int = random_int(1, 100);
+seed(7);
+assert_eq(random_int(1, 100), first);
+assert_eq(random_int(1, 100), second);
+var i: int = 0;
+while (i < 200) {
+    var n: int = random_int(0 - 3, 3);
+    assert_eq(clamp(n, 0 - 3, 3), n);
+    i = i + 1;
+}
+assert_eq(random_int(5, 5), 5);
+println(random_int(0 - 2147483647 - 1, 2147483647) != 0);
+seed(0 - 1);
+println(random_int(1, 1000000));"#,
+        );
+        assert_eq!(output.lines().count(), 2);
+        let err = run_with_error("println(random_int(5, 2));");
+        assert_eq!(err.message, "random_int bounds 5..2 are reversed");
+    }
+
+    #[test]
+    // The numbers for a seed are part of the language, so seeded programs behave the same
+    // everywhere
+    fn random_sequence_is_stable() {
+        let mut random = Random::new(0);
+        assert_eq!(random.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(random.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        let mut interpreter =
+            Interpreter::with_io(Box::new(OutputBuffer::new()), Box::new(&b""[..]));
+        let mut vm = Vm::with_io(Box::new(OutputBuffer::new()), Box::new(&b""[..]));
+        interpreter.set_seed(3);
+        vm.set_seed(3);
+        assert_eq!(interpreter.context_mut().random, Random::new(3));
+        assert_eq!(vm.context_mut().random, Random::new(3));
     }
 }
@@ -0,0 +1,475 @@
+use crate::common::{
+    ast::{
+        BinaryExpression, Expression, FunctionCall, FunctionDecl, IfStatement, ImportStatement,
+        LiteralExpression, LiteralValue, LoopStatement, Parameter, Position, Program,
+        ReturnStatement, Span, Spannable, SpannedExpression, SpannedStatement, Statement, Type,
+        VariableAssignment, VariableDecl, VariableRef,
+    },
+    token::{BinaryOperator, SpannedToken, Token, UnaryOperator},
+};
+
+use super::Json;
+
+// The JSON schema of the syntax tree and tokens. Every node is an object with a `kind` naming its
+// variant and a `span`, followed by its fields. Changing a name here breaks external tools, so
+// keep it in step with the golden files in src/json/golden
+
+const BINARY_OPERATORS: [BinaryOperator; 11] = [
+    BinaryOperator::Add,
+    BinaryOperator::Subtract,
+    BinaryOperator::Multiply,
+    BinaryOperator::Divide,
+    BinaryOperator::Modulo,
+    BinaryOperator::NotEqual,
+    BinaryOperator::Equal,
+    BinaryOperator::GreaterThan,
+    BinaryOperator::LessThan,
+    BinaryOperator::GreaterThanOrEqual,
+    BinaryOperator::LessThanOrEqual,
+];
+
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+// Decoding errors name the path to the offending value, e.g. `$.body[1].value: missing field kind`
+pub trait FromJson: Sized {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String>;
+
+    fn from_json(json: &Json) -> Result<Self, String> {
+        Self::from_json_at(json, "$")
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> Json {
+        Json::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        let values = json
+            .as_array()
+            .ok_or_else(|| format!("{}: expected an array", path))?;
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| T::from_json_at(value, &format!("{}[{}]", path, index)))
+            .collect()
+    }
+}
+
+impl ToJson for Program {
+    fn to_json(&self) -> Json {
+        Json::object(vec![("body", self.body.to_json())])
+    }
+}
+
+impl FromJson for Program {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        Ok(Program {
+            body: field(json, path, "body")?,
+        })
+    }
+}
+
+impl ToJson for Position {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("line", self.line.into()),
+            ("column", self.column.into()),
+        ])
+    }
+}
+
+impl FromJson for Position {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        Ok(Position {
+            line: field(json, path, "line")?,
+            column: field(json, path, "column")?,
+        })
+    }
+}
+
+impl ToJson for Span {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("offset", self.offset.into()),
+            ("len", self.len.into()),
+            ("start", self.start.to_json()),
+            ("end", self.end.to_json()),
+        ])
+    }
+}
+
+impl FromJson for Span {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        Ok(Span {
+            offset: field(json, path, "offset")?,
+            len: field(json, path, "len")?,
+            start: field(json, path, "start")?,
+            end: field(json, path, "end")?,
+        })
+    }
+}
+
+impl ToJson for Type {
+    fn to_json(&self) -> Json {
+        self.to_string().into()
+    }
+}
+
+impl FromJson for Type {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        match String::from_json_at(json, path)?.as_str() {
+            "any" => Ok(Type::Any),
+            name => name
+                .parse()
+                .map_err(|_| format!("{}: unknown type {}", path, name)),
+        }
+    }
+}
+
+impl ToJson for Parameter {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("span", self.span.to_json()),
+            ("name", self.name.as_str().into()),
+            ("type", self.type_.to_json()),
+        ])
+    }
+}
+
+impl FromJson for Parameter {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        Ok(Parameter {
+            name: field(json, path, "name")?,
+            type_: field(json, path, "type")?,
+            span: field(json, path, "span")?,
+        })
+    }
+}
+
+impl ToJson for SpannedStatement {
+    fn to_json(&self) -> Json {
+        let (kind, fields) = match &self.node {
+            Statement::Function(func) => (
+                "Function",
+                vec![
+                    ("name", func.name.as_str().into()),
+                    ("params", func.params.to_json()),
+                    ("returnType", func.type_.to_json()),
+                    ("body", func.body.to_json()),
+                    ("public", func.public.into()),
+                ],
+            ),
+            Statement::VarDecl(var_decl) => (
+                "VarDecl",
+                vec![
+                    ("name", var_decl.name.as_str().into()),
+                    ("type", var_decl.type_.to_json()),
+                    ("value", var_decl.value.to_json()),
+                ],
+            ),
+            Statement::VarAssignment(var_assignment) => (
+                "VarAssignment",
+                vec![
+                    ("name", var_assignment.name.as_str().into()),
+                    ("value", var_assignment.value.to_json()),
+                ],
+            ),
+            Statement::If(if_statement) => (
+                "If",
+                vec![
+                    ("condition", if_statement.condition.to_json()),
+                    ("then", if_statement.then_body.to_json()),
+                    (
+                        "else",
+                        if_statement
+                            .else_body
+                            .as_ref()
+                            .map_or(Json::Null, |else_body| else_body.to_json()),
+                    ),
+                ],
+            ),
+            Statement::Loop(loop_statement) => (
+                "While",
+                vec![
+                    ("condition", loop_statement.condition.to_json()),
+                    ("body", loop_statement.body.to_json()),
+                ],
+            ),
+            Statement::Return(return_statement) => (
+                "Return",
+                vec![(
+                    "value",
+                    return_statement
+                        .value
+                        .as_ref()
+                        .map_or(Json::Null, ToJson::to_json),
+                )],
+            ),
+            // The expression of an expression statement has the statement's span
+            Statement::Expr(expression) => (
+                "Expr",
+                vec![(
+                    "expression",
+                    expression.clone().spanned(self.span).to_json(),
+                )],
+            ),
+            Statement::Import(import) => (
+                "Import",
+                vec![
+                    ("path", import.path.as_str().into()),
+                    (
+                        "names",
+                        import.names.as_ref().map_or(Json::Null, |names| {
+                            Json::Array(names.iter().map(|name| name.as_str().into()).collect())
+                        }),
+                    ),
+                ],
+            ),
+        };
+        node(kind, self.span, fields)
+    }
+}
+
+impl FromJson for SpannedStatement {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        let kind: String = field(json, path, "kind")?;
+        let span: Span = field(json, path, "span")?;
+        let statement = match kind.as_str() {
+            "Function" => Statement::Function(FunctionDecl {
+                name: field(json, path, "name")?,
+                params: field(json, path, "params")?,
+                type_: field(json, path, "returnType")?,
+                body: field(json, path, "body")?,
+                // Trees written before `pub` existed have no such field
+                public: optional_field(json, path, "public")?.unwrap_or(false),
+            }),
+            "VarDecl" => Statement::VarDecl(VariableDecl {
+                name: field(json, path, "name")?,
+                type_: field(json, path, "type")?,
+                value: field(json, path, "value")?,
+            }),
+            "VarAssignment" => Statement::VarAssignment(VariableAssignment {
+                name: field(json, path, "name")?,
+                value: field(json, path, "value")?,
+            }),
+            "If" => Statement::If(IfStatement {
+                condition: field(json, path, "condition")?,
+                then_body: field(json, path, "then")?,
+                else_body: optional_field::<SpannedStatement>(json, path, "else")?.map(Box::new),
+            }),
+            "While" => Statement::Loop(LoopStatement {
+                condition: field(json, path, "condition")?,
+                body: field(json, path, "body")?,
+            }),
+            "Return" => Statement::Return(ReturnStatement {
+                value: optional_field(json, path, "value")?,
+            }),
+            "Expr" => Statement::Expr(field::<SpannedExpression>(json, path, "expression")?.node),
+            "Import" => Statement::Import(ImportStatement {
+                path: field(json, path, "path")?,
+                names: optional_field(json, path, "names")?,
+            }),
+            kind => return Err(format!("{}: unknown statement kind {}", path, kind)),
+        };
+        Ok(statement.spanned(span))
+    }
+}
+
+impl ToJson for SpannedExpression {
+    fn to_json(&self) -> Json {
+        let (kind, fields) = match &self.node {
+            Expression::Binary(binary) => (
+                "Binary",
+                vec![
+                    ("operator", binary.operator.to_string().into()),
+                    ("left", binary.left.to_json()),
+                    ("right", binary.right.to_json()),
+                ],
+            ),
+            Expression::Literal(literal) => {
+                let value = match &literal.value {
+                    LiteralValue::String(value) => value.as_str().into(),
+                    LiteralValue::Number(value) => (*value).into(),
+                    LiteralValue::Bool(value) => (*value).into(),
+                    LiteralValue::Void => Json::Null,
+                };
+                (
+                    "Literal",
+                    vec![
+                        ("type", Type::from(&literal.value).to_json()),
+                        ("value", value),
+                    ],
+                )
+            }
+            Expression::FunctionCall(call) => (
+                "Call",
+                vec![
+                    ("callee", call.callee.as_str().into()),
+                    ("args", call.args.to_json()),
+                ],
+            ),
+            Expression::VariableRef(var_ref) => {
+                ("Variable", vec![("name", var_ref.name.as_str().into())])
+            }
+        };
+        node(kind, self.span, fields)
+    }
+}
+
+impl FromJson for SpannedExpression {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        let kind: String = field(json, path, "kind")?;
+        let span: Span = field(json, path, "span")?;
+        let expression = match kind.as_str() {
+            "Binary" => Expression::Binary(Box::new(BinaryExpression {
+                operator: binary_operator(&field::<String>(json, path, "operator")?)
+                    .ok_or_else(|| format!("{}.operator: unknown operator", path))?,
+                left: field(json, path, "left")?,
+                right: field(json, path, "right")?,
+            })),
+            "Literal" => {
+                let value = match field::<Type>(json, path, "type")? {
+                    Type::String => LiteralValue::String(field(json, path, "value")?),
+                    Type::Int => LiteralValue::Number(field(json, path, "value")?),
+                    Type::Bool => LiteralValue::Bool(field(json, path, "value")?),
+                    Type::Void => LiteralValue::Void,
+                    Type::Any => return Err(format!("{}.type: literals cannot be any", path)),
+                };
+                Expression::Literal(LiteralExpression { value })
+            }
+            "Call" => Expression::FunctionCall(Box::new(FunctionCall {
+                callee: field(json, path, "callee")?,
+                args: field(json, path, "args")?,
+            })),
+            "Variable" => Expression::VariableRef(Box::new(VariableRef {
+                name: field(json, path, "name")?,
+            })),
+            kind => return Err(format!("{}: unknown expression kind {}", path, kind)),
+        };
+        Ok(expression.spanned(span))
+    }
+}
+
+impl ToJson for SpannedToken {
+    fn to_json(&self) -> Json {
+        let (kind, value) = match &self.node {
+            Token::Identifier(name) => ("Identifier", Some(name.as_str().into())),
+            Token::NumericLiteral(value) => ("NumericLiteral", Some((*value).into())),
+            Token::StringLiteral(value) => ("StringLiteral", Some(value.as_str().into())),
+            Token::BooleanLiteral(value) => ("BooleanLiteral", Some((*value).into())),
+            Token::BinaryOperator(operator) => {
+                ("BinaryOperator", Some(operator.to_string().into()))
+            }
+            Token::UnaryOperator(UnaryOperator::Not) => ("UnaryOperator", Some("!".into())),
+            Token::Comma => ("Comma", None),
+            Token::Assign => ("Assign", None),
+            Token::LeftParen => ("LeftParen", None),
+            Token::RightParen => ("RightParen", None),
+            Token::LeftBrace => ("LeftBrace", None),
+            Token::RightBrace => ("RightBrace", None),
+            Token::Semicolon => ("Semicolon", None),
+            Token::Colon => ("Colon", None),
+            Token::EOF => ("EOF", None),
+        };
+        node(
+            kind,
+            self.span,
+            value.map(|value| ("value", value)).into_iter().collect(),
+        )
+    }
+}
+
+impl FromJson for SpannedToken {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        let kind: String = field(json, path, "kind")?;
+        let span: Span = field(json, path, "span")?;
+        let token = match kind.as_str() {
+            "Identifier" => Token::Identifier(field(json, path, "value")?),
+            "NumericLiteral" => Token::NumericLiteral(field(json, path, "value")?),
+            "StringLiteral" => Token::StringLiteral(field(json, path, "value")?),
+            "BooleanLiteral" => Token::BooleanLiteral(field(json, path, "value")?),
+            "BinaryOperator" => Token::BinaryOperator(
+                binary_operator(&field::<String>(json, path, "value")?)
+                    .ok_or_else(|| format!("{}.value: unknown operator", path))?,
+            ),
+            "UnaryOperator" => match field::<String>(json, path, "value")?.as_str() {
+                "!" => Token::UnaryOperator(UnaryOperator::Not),
+                _ => return Err(format!("{}.value: unknown operator", path)),
+            },
+            "Comma" => Token::Comma,
+            "Assign" => Token::Assign,
+            "LeftParen" => Token::LeftParen,
+            "RightParen" => Token::RightParen,
+            "LeftBrace" => Token::LeftBrace,
+            "RightBrace" => Token::RightBrace,
+            "Semicolon" => Token::Semicolon,
+            "Colon" => Token::Colon,
+            "EOF" => Token::EOF,
+            kind => return Err(format!("{}: unknown token kind {}", path, kind)),
+        };
+        Ok(token.spanned(span))
+    }
+}
+
+impl FromJson for String {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        json.as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("{}: expected a string", path))
+    }
+}
+
+impl FromJson for bool {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        json.as_bool()
+            .ok_or_else(|| format!("{}: expected a boolean", path))
+    }
+}
+
+impl FromJson for i32 {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        json.as_i64()
+            .and_then(|value| i32::try_from(value).ok())
+            .ok_or_else(|| format!("{}: expected a 32-bit integer", path))
+    }
+}
+
+impl FromJson for usize {
+    fn from_json_at(json: &Json, path: &str) -> Result<Self, String> {
+        json.as_i64()
+            .and_then(|value| usize::try_from(value).ok())
+            .ok_or_else(|| format!("{}: expected a non-negative integer", path))
+    }
+}
+
+fn node(kind: &str, span: Span, fields: Vec<(&str, Json)>) -> Json {
+    let mut entries = vec![("kind", kind.into()), ("span", span.to_json())];
+    entries.extend(fields);
+    Json::object(entries)
+}
+
+fn field<T: FromJson>(json: &Json, path: &str, key: &str) -> Result<T, String> {
+    let value = json
+        .get(key)
+        .ok_or_else(|| format!("{}: missing field {}", path, key))?;
+    T::from_json_at(value, &format!("{}.{}", path, key))
+}
+
+// Reads a field that may be null or left out
+fn optional_field<T: FromJson>(json: &Json, path: &str, key: &str) -> Result<Option<T>, String> {
+    match json.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(value) => T::from_json_at(value, &format!("{}.{}", path, key)).map(Some),
+    }
+}
+
+fn binary_operator(symbol: &str) -> Option<BinaryOperator> {
+    BINARY_OPERATORS
+        .into_iter()
+        .find(|operator| operator.to_string() == symbol)
+}
@@ -0,0 +1,155 @@
+#![cfg(test)]
+
+use super::ast::{FromJson, ToJson};
+use super::*;
+use crate::common::token::SpannedToken;
+use crate::interpreter::{Interpreter, OutputBuffer};
+use crate::pipeline::{
+    ast_to_json, check_ast, load_ast, parse_source, tokenize_source, tokens_to_json,
+};
+
+// Golden files pin the JSON schema. Regenerate them with `perry ast --json` and
+// `perry tokens --json` only when the schema is meant to change
+const PROGRAM: &str = include_str!("golden/program.pry");
+const PROGRAM_JSON: &str = include_str!("golden/program.json");
+const TOKENS: &str = include_str!("golden/tokens.pry");
+const TOKENS_JSON: &str = include_str!("golden/tokens.json");
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_values() {
+        let text = r#" {"a": [1, -2.5, 3e2], "b": {"c": null, "d": true}, "e": "x\"y\\z\né😀"} "#;
+        let expected = Json::object(vec![
+            (
+                "a",
+                Json::Array(vec![
+                    Json::Number(1.0),
+                    Json::Number(-2.5),
+                    Json::Number(300.0),
+                ]),
+            ),
+            (
+                "b",
+                Json::object(vec![("c", Json::Null), ("d", Json::Bool(true))]),
+            ),
+            ("e", Json::from("x\"y\\z\né😀")),
+        ]);
+        assert_eq!(Json::parse(text).unwrap(), expected);
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let value = Json::object(vec![
+            ("list", Json::from(vec![Json::from(1), Json::from("two")])),
+            ("empty", Json::Array(Vec::new())),
+            ("text", Json::from("tab\tquote\" control\u{1}")),
+            ("half", Json::Number(0.5)),
+        ]);
+        let compact = value.to_string();
+        assert_eq!(
+            compact,
+            r#"{"list":[1,"two"],"empty":[],"text":"tab\tquote\" control\u0001","half":0.5}"#
+        );
+        assert_eq!(Json::parse(&compact).unwrap(), value);
+        assert_eq!(Json::parse(&value.to_pretty()).unwrap(), value);
+    }
+
+    #[test]
+    fn pretty_layout() {
+        let value = Json::object(vec![
+            ("a", Json::from(vec![Json::from(1), Json::Null])),
+            ("b", Json::Object(Vec::new())),
+        ]);
+        assert_eq!(
+            value.to_pretty(),
+            "{\n  \"a\": [\n    1,\n    null\n  ],\n  \"b\": {}\n}"
+        );
+    }
+
+    #[test]
+    fn invalid_documents() {
+        for text in [
+            "",
+            "{",
+            "[1,]",
+            r#"{"a" 1}"#,
+            r#""abc"#,
+            "tru",
+            "1 2",
+            r#""\ud800""#,
+            r#""\u+123""#,
+            r#""\u 123""#,
+            "1e999",
+            "-1e999",
+        ] {
+            assert!(Json::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn ast_golden() {
+        let program = parse_source(PROGRAM).unwrap();
+        assert_eq!(ast_to_json(&program), PROGRAM_JSON.trim_end());
+    }
+
+    #[test]
+    fn tokens_golden() {
+        let tokens = tokenize_source(TOKENS).unwrap();
+        assert_eq!(tokens_to_json(&tokens), TOKENS_JSON.trim_end());
+    }
+
+    #[test]
+    fn ast_round_trip() {
+        let loaded = load_ast(PROGRAM_JSON).unwrap();
+        assert_eq!(loaded.body, parse_source(PROGRAM).unwrap().body);
+        assert_eq!(ast_to_json(&loaded), PROGRAM_JSON.trim_end());
+    }
+
+    #[test]
+    fn tokens_round_trip() {
+        let json = Json::parse(TOKENS_JSON).unwrap();
+        let tokens = Vec::<SpannedToken>::from_json(&json).unwrap();
+        assert_eq!(tokens, tokenize_source(TOKENS).unwrap());
+        assert_eq!(tokens.to_json(), json);
+    }
+
+    #[test]
+    fn loaded_ast_runs() {
+        let program = check_ast(PROGRAM_JSON).unwrap();
+        let output = OutputBuffer::new();
+        let mut interpreter = Interpreter::with_io(Box::new(output.clone()), Box::new(&b""[..]));
+        interpreter.execute(&program).unwrap();
+        assert_eq!(output.contents(), "even\nodd\neven\n");
+    }
+
+    #[test]
+    fn invalid_ast() {
+        let span = r#"{"offset": 0, "len": 1, "start": {"line": 1, "column": 1}, "end": {"line": 1, "column": 2}}"#;
+        let cases = [
+            ("[]".to_string(), "$: missing field body"),
+            (
+                format!(r#"{{"body": [{{"kind": "Goto", "span": {}}}]}}"#, span),
+                "$.body[0]: unknown statement kind Goto",
+            ),
+            (
+                format!(
+                    r#"{{"body": [{{"kind": "VarAssignment", "span": {0}, "name": "x", "value": {{"kind": "Literal", "span": {0}, "type": "int", "value": 1.5}}}}]}}"#,
+                    span
+                ),
+                "$.body[0].value.value: expected a 32-bit integer",
+            ),
+        ];
+        for (text, expected) in cases {
+            let errors = load_ast(&text).unwrap_err();
+            assert_eq!(
+                errors[0].message,
+                format!("invalid syntax tree: {}", expected)
+            );
+        }
+        let errors = load_ast("{\n  \"body\": [,]\n}").unwrap_err();
+        assert_eq!(errors[0].span.start.line, 2);
+        assert!(errors[0].message.starts_with("invalid JSON"));
+    }
+}
@@ -0,0 +1,27 @@
+pub mod analyzer;
+pub mod binary;
+pub mod common;
+pub mod engine;
+pub mod formatter;
+pub mod interpreter;
+pub mod json;
+pub mod linker;
+pub mod lsp;
+pub mod parser;
+pub mod pipeline;
+pub mod repl;
+pub mod testing;
+pub mod tokenizer;
+pub mod vm;
+
+pub use analyzer::Analyzer;
+pub use common::ast;
+pub use common::error::{ErrorKind, PerryError};
+pub use engine::Engine;
+pub use formatter::format_source;
+pub use interpreter::Interpreter;
+pub use parser::Parser;
+pub use pipeline::run_source;
+pub use repl::Repl;
+pub use tokenizer::Tokenizer;
+pub use vm::Vm;
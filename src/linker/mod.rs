@@ -0,0 +1,401 @@
+pub mod test;
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::analyzer::{Analyzer, Symbol, SymbolKind};
+use crate::common::{
+    ast::{
+        FunctionCall, FunctionDecl, IfStatement, ImportStatement, LoopStatement, Program, Span,
+        SpannedStatement, Statement, VariableAssignment, VariableDecl, VariableRef, VisitorMut,
+        walk_function_call_mut, walk_statements_mut, walk_var_assignment_mut, walk_var_decl_mut,
+    },
+    error::{ErrorKind, PerryError},
+};
+use crate::parser::Parser;
+use crate::pipeline::{INLINE_PATH, tokenize_source};
+
+// Reads the source of an imported file
+pub type ReadFile = Box<dyn Fn(&Path) -> io::Result<String>>;
+
+// A file that went into a linked program. Spans in imported files are shifted past the end of
+// every file loaded before them, so an offset alone tells which file a span points into
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    pub path: String,
+    pub base: usize, // Added to every span offset in the file
+    pub len: usize,
+}
+
+// A program and every file imported into it, flattened into one body that runs the imported
+// files first. The top-level names of imported files are prefixed with their path, e.g.
+// `lib/math.pry::square`, so files never see each other's names unless they import them
+#[derive(Debug, Clone)]
+pub struct Linked {
+    pub program: Program,
+    pub files: Vec<SourceFile>,
+}
+
+impl Linked {
+    // Points an error from running the linked program at the file its span falls in
+    pub fn locate(&self, mut err: PerryError) -> PerryError {
+        let offset = err.span.offset;
+        if let Some(file) = self
+            .files
+            .iter()
+            .skip(1)
+            .find(|file| file.base <= offset && offset <= file.base + file.len)
+        {
+            err = unshift(err, file.base).with_file(file.path.clone());
+        }
+        err
+    }
+}
+
+// A loaded file, ready to be imported
+struct Module {
+    exports: Vec<(String, Symbol)>, // Public functions, by the name they are declared with
+    renames: HashMap<String, String>, // Top-level names to their names in the linked program
+    body: Vec<SpannedStatement>,
+}
+
+// Loads a file and everything it imports. Each file is parsed and analyzed on its own, against the
+// signatures of the functions it imports, before anything runs
+pub struct Linker {
+    read_file: ReadFile,
+    modules: HashMap<PathBuf, Module>,
+    order: Vec<PathBuf>,   // Loaded files, each after the files it imports
+    loading: Vec<PathBuf>, // The chain of imports being loaded, for finding cycles
+    files: Vec<SourceFile>,
+    next_base: usize,
+}
+
+impl Default for Linker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Linker {
+    // Creates a linker that reads imported files from disk
+    pub fn new() -> Self {
+        Self::with_reader(Box::new(|path: &Path| fs::read_to_string(path)))
+    }
+
+    // Creates a linker that reads imported files with `read_file`
+    pub fn with_reader(read_file: ReadFile) -> Self {
+        Self {
+            read_file,
+            modules: HashMap::new(),
+            order: Vec::new(),
+            loading: Vec::new(),
+            files: Vec::new(),
+            next_base: 0,
+        }
+    }
+
+    // Links the file at `path` whose contents are `source`. Imports are resolved relative to the
+    // directory of `path`. Errors in imported files name the file and have spans within it
+    pub fn link(mut self, path: &Path, source: &str) -> Result<Linked, Vec<PerryError>> {
+        let path = normalize(path);
+        self.load(&path, source, true)?;
+        let mut body = Vec::new();
+        for path in &self.order {
+            if let Some(module) = self.modules.remove(path) {
+                body.extend(module.body);
+            }
+        }
+        Ok(Linked {
+            program: Program { body },
+            files: self.files,
+        })
+    }
+
+    fn load(&mut self, path: &Path, source: &str, root: bool) -> Result<(), Vec<PerryError>> {
+        let display = path.display().to_string();
+        let base = self.next_base;
+        self.files.push(SourceFile {
+            path: display.clone(),
+            base,
+            len: source.len(),
+        });
+        self.next_base += source.len() + 1;
+        // Errors in the file being linked are reported as they always were, except for imports
+        // that fail to resolve, which name it unless it isn't a file at all
+        let file = (!root).then(|| display.clone());
+        let importer = file
+            .clone()
+            .or_else(|| (path != Path::new(INLINE_PATH)).then(|| display.clone()));
+        let locate_in = |file: &Option<String>, errors: Vec<PerryError>| -> Vec<PerryError> {
+            errors
+                .into_iter()
+                .map(|err| match file {
+                    // Errors from files it imports are already located
+                    Some(file) if err.file.is_none() => unshift(err, base).with_file(file.clone()),
+                    _ => err,
+                })
+                .collect()
+        };
+        let locate = |errors| locate_in(&file, errors);
+
+        // Lexical errors point into the file as it is, so they are shifted like everything else
+        // before being located
+        let mut tokens = tokenize_source(source)
+            .map_err(|errors| locate(errors.into_iter().map(|err| shift(err, base)).collect()))?;
+        for token in &mut tokens {
+            token.span.offset += base;
+        }
+        let mut program = Parser::new(tokens)
+            .parse()
+            .map_err(|errors| locate(errors.into_iter().map(PerryError::from).collect()))?;
+
+        self.loading.push(path.to_path_buf());
+        let imports = self.load_imports(path, &program);
+        self.loading.pop();
+        let mut analyzer = Analyzer::new();
+        let mut renames = HashMap::new();
+        for (import, target) in imports.map_err(|errors| locate_in(&importer, errors))? {
+            let module = &self.modules[&target];
+            analyzer.add_module(import.path.clone(), module.exports.clone());
+            for (name, _) in &module.exports {
+                if import
+                    .names
+                    .as_ref()
+                    .is_none_or(|names| names.contains(name))
+                {
+                    renames.insert(name.clone(), module.renames[name].clone());
+                }
+            }
+        }
+        analyzer
+            .analyze(&program)
+            .map_err(|err| locate(vec![err.into()]))?;
+
+        let mut exports = Vec::new();
+        for statement in &program.body {
+            let (name, function) = match &statement.node {
+                Statement::Function(func) => (&func.name, Some(func)),
+                Statement::VarDecl(var_decl) => (&var_decl.name, None),
+                _ => continue,
+            };
+            if !root {
+                renames.insert(name.clone(), format!("{}::{}", display, name));
+            }
+            if let Some(func) = function.filter(|func| func.public) {
+                exports.push((
+                    name.clone(),
+                    Symbol {
+                        kind: SymbolKind::Function,
+                        type_: func.type_.clone(),
+                        params: func.params.clone(),
+                        span: Some(statement.span),
+                    },
+                ));
+            }
+        }
+        program
+            .body
+            .retain(|statement| !matches!(statement.node, Statement::Import(_)));
+        Renamer {
+            renames: &renames,
+            scopes: Vec::new(),
+        }
+        .visit_program_mut(&mut program);
+
+        self.modules.insert(
+            path.to_path_buf(),
+            Module {
+                exports,
+                renames,
+                body: program.body,
+            },
+        );
+        self.order.push(path.to_path_buf());
+        Ok(())
+    }
+
+    // Loads every file imported at the top level of a program. Returns each import with the file it
+    // resolved to
+    fn load_imports<'a>(
+        &mut self,
+        path: &Path,
+        program: &'a Program,
+    ) -> Result<Vec<(&'a ImportStatement, PathBuf)>, Vec<PerryError>> {
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let mut imports = Vec::new();
+        for statement in &program.body {
+            let Statement::Import(import) = &statement.node else {
+                continue;
+            };
+            let target = self.resolve(directory, &import.path, statement.span)?;
+            imports.push((import, target));
+        }
+        Ok(imports)
+    }
+
+    // Finds the file an import refers to and loads it unless it was loaded before
+    fn resolve(
+        &mut self,
+        directory: &Path,
+        import_path: &str,
+        span: Span,
+    ) -> Result<PathBuf, Vec<PerryError>> {
+        let target = normalize(&directory.join(import_path));
+        if let Some(start) = self.loading.iter().position(|loading| *loading == target) {
+            let cycle: Vec<String> = self.loading[start..]
+                .iter()
+                .chain([&target])
+                .map(|path| path.display().to_string())
+                .collect();
+            return Err(vec![import_error(
+                format!("import cycle: {}", cycle.join(" -> ")),
+                span,
+            )]);
+        }
+        if !self.modules.contains_key(&target) {
+            let source = (self.read_file)(&target).map_err(|err| {
+                vec![import_error(
+                    format!("cannot read {}: {}", target.display(), err),
+                    span,
+                )]
+            })?;
+            self.load(&target, &source, false)?;
+        }
+        Ok(target)
+    }
+
+    // Loads the file imported as `import_path` by the file at `importer` and returns its public
+    // functions. Lets tooling analyze a file that imports others without linking it
+    pub fn exports(
+        &mut self,
+        importer: &Path,
+        import_path: &str,
+        span: Span,
+    ) -> Result<Vec<(String, Symbol)>, Vec<PerryError>> {
+        let importer = normalize(importer);
+        let directory = importer.parent().unwrap_or(Path::new("")).to_path_buf();
+        self.loading.push(importer);
+        let target = self.resolve(&directory, import_path, span);
+        self.loading.pop();
+        Ok(self.modules[&target?].exports.clone())
+    }
+}
+
+// Resolves `.` and `..` without touching the file system, so the same file is recognized however
+// its imports spell the path
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn import_error(message: String, span: Span) -> PerryError {
+    PerryError::new(ErrorKind::Semantic, message, span)
+}
+
+// Moves an error's spans by `base` bytes, into or out of a file's place in the linked program
+fn shift(mut err: PerryError, base: usize) -> PerryError {
+    err.span.offset += base;
+    for label in &mut err.labels {
+        label.span.offset += base;
+    }
+    err
+}
+
+fn unshift(mut err: PerryError, base: usize) -> PerryError {
+    err.span.offset = err.span.offset.saturating_sub(base);
+    for label in &mut err.labels {
+        label.span.offset = label.span.offset.saturating_sub(base);
+    }
+    err
+}
+
+// Renames the top-level names of a file, and the functions it imports, to their names in the
+// linked program. Names declared in blocks hide top-level names and are left alone
+struct Renamer<'a> {
+    renames: &'a HashMap<String, String>,
+    scopes: Vec<Vec<String>>, // Names declared by enclosing blocks, innermost last
+}
+
+impl Renamer<'_> {
+    fn declare(&mut self, name: &mut String) {
+        match self.scopes.last_mut() {
+            Some(scope) => scope.push(name.clone()),
+            None => self.reference(name),
+        }
+    }
+
+    fn reference(&self, name: &mut String) {
+        if self.scopes.iter().any(|scope| scope.contains(name)) {
+            return;
+        }
+        if let Some(renamed) = self.renames.get(name.as_str()) {
+            *name = renamed.clone();
+        }
+    }
+
+    fn block(&mut self, declared: Vec<String>, statements: &mut [SpannedStatement]) {
+        self.scopes.push(declared);
+        walk_statements_mut(self, statements);
+        self.scopes.pop();
+    }
+}
+
+impl VisitorMut for Renamer<'_> {
+    fn visit_function_decl_mut(&mut self, func: &mut FunctionDecl, _span: Span) {
+        self.declare(&mut func.name);
+        let params = func.params.iter().map(|param| param.name.clone()).collect();
+        self.block(params, &mut func.body);
+    }
+
+    // Like the analyzer, the name is declared before its value is looked at
+    fn visit_var_decl_mut(&mut self, var_decl: &mut VariableDecl, span: Span) {
+        self.declare(&mut var_decl.name);
+        walk_var_decl_mut(self, var_decl, span);
+    }
+
+    fn visit_var_assignment_mut(&mut self, var_assignment: &mut VariableAssignment, span: Span) {
+        self.reference(&mut var_assignment.name);
+        walk_var_assignment_mut(self, var_assignment, span);
+    }
+
+    fn visit_if_mut(&mut self, if_statement: &mut IfStatement, _span: Span) {
+        let condition = &mut if_statement.condition;
+        self.visit_expression_mut(&mut condition.node, condition.span);
+        self.block(Vec::new(), &mut if_statement.then_body);
+        if let Some(else_body) = &mut if_statement.else_body {
+            self.block(Vec::new(), std::slice::from_mut(else_body.as_mut()));
+        }
+    }
+
+    fn visit_loop_mut(&mut self, loop_statement: &mut LoopStatement, _span: Span) {
+        let condition = &mut loop_statement.condition;
+        self.visit_expression_mut(&mut condition.node, condition.span);
+        self.block(Vec::new(), &mut loop_statement.body);
+    }
+
+    fn visit_function_call_mut(&mut self, call: &mut FunctionCall, span: Span) {
+        self.reference(&mut call.callee);
+        walk_function_call_mut(self, call, span);
+    }
+
+    fn visit_variable_ref_mut(&mut self, var_ref: &mut VariableRef, _span: Span) {
+        self.reference(&mut var_ref.name);
+    }
+}
@@ -0,0 +1,264 @@
+#![cfg(test)]
+
+use super::*;
+use crate::common::ast::Expression;
+use crate::interpreter::{Interpreter, OutputBuffer};
+use crate::vm::Vm;
+
+// Links the first file against the others, all held in memory
+pub fn link_files(files: &[(&str, &str)]) -> Result<Linked, Vec<PerryError>> {
+    let sources: HashMap<PathBuf, String> = files
+        .iter()
+        .map(|(path, source)| (PathBuf::from(path), source.to_string()))
+        .collect();
+    let linker = Linker::with_reader(Box::new(move |path: &Path| {
+        sources
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }));
+    let (path, source) = files[0];
+    linker.link(Path::new(path), source)
+}
+
+// Links and runs in-memory files on both backends, returning what they printed or the located
+// runtime error
+pub fn run_files(files: &[(&str, &str)]) -> Result<String, Vec<PerryError>> {
+    let linked = link_files(files).unwrap();
+    let output = OutputBuffer::new();
+    let mut interpreter = Interpreter::with_io(Box::new(output.clone()), Box::new(&b""[..]));
+    let result = interpreter.execute(&linked.program);
+    let vm_output = OutputBuffer::new();
+    let mut vm = Vm::with_io(Box::new(vm_output.clone()), Box::new(&b""[..]));
+    let vm_result = vm.execute(&linked.program);
+    assert_eq!(output.contents(), vm_output.contents());
+    assert_eq!(result, vm_result);
+    result
+        .map(|_| output.contents())
+        .map_err(|err| vec![linked.locate(err.into())])
+}
+
+const MATH: &str = r#"var calls: int = 0;
+println("math loaded");
+
+func count(): void {
+    calls = calls + 1;
+}
+
+pub func square(n: int): int {
+    count();
+    return n * n;
+}
+
+pub func calls_made(): int {
+    return calls;
+}
+
+pub func divide(a: int, b: int): int {
+    return a / b;
+}
+"#;
+
+const GREET: &str = r#"import { square } from "math.pry";
+
+pub func greet(n: int): void {
+    println(square(n));
+}
+"#;
+
+mod tests {
+    use super::*;
+
+    #[test]
+    // Each file runs once, before the files importing it, and keeps its own top-level names
+    fn link_modules() {
+        let main = r#"import "lib/math.pry";
+import { greet } from "lib/greet.pry";
+
+var calls: int = 100;
+greet(3);
+println(square(4));
+println(calls_made());
+println(calls);"#;
+        let output = run_files(&[
+            ("app/main.pry", main),
+            ("app/lib/math.pry", MATH),
+            ("app/lib/greet.pry", GREET),
+        ])
+        .unwrap();
+        assert_eq!(output, "math loaded\n9\n16\n2\n100\n");
+    }
+
+    #[test]
+    fn linked_names() {
+        let linked = link_files(&[
+            (
+                "main.pry",
+                r#"import { square } from "../lib/./math.pry"; square(2);"#,
+            ),
+            ("../lib/math.pry", MATH),
+        ])
+        .unwrap();
+        let files: Vec<&str> = linked.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(files, vec!["main.pry", "../lib/math.pry"]);
+        let Statement::Expr(Expression::FunctionCall(call)) =
+            &linked.program.body.last().unwrap().node
+        else {
+            panic!("expected a call");
+        };
+        assert_eq!(call.callee, "../lib/math.pry::square");
+    }
+
+    #[test]
+    // A local declared before a top-level name of the same spelling is not renamed with it
+    fn locals_hide_top_level_names() {
+        let lib = r#"pub func f(): int {
+    var total: int = 1;
+    return total;
+}
+var total: int = 5;
+pub func g(): int {
+    return total;
+}"#;
+        let main = r#"import "lib.pry"; println(f()); println(g());"#;
+        assert_eq!(
+            run_files(&[("main.pry", main), ("lib.pry", lib)]).unwrap(),
+            "1\n5\n"
+        );
+    }
+
+    #[test]
+    fn import_errors() {
+        let cases = vec![
+            (
+                r#"import { count } from "math.pry";"#,
+                "math.pry has no public function count",
+            ),
+            (
+                r#"import "math.pry"; count();"#,
+                "use of undefined function count",
+            ),
+            (r#"square(1);"#, "use of undefined function square"),
+            (
+                r#"import "math.pry"; func square(n: int): int { return n; }"#,
+                "duplicate declaration of square",
+            ),
+            (
+                r#"func f(): void { import "math.pry"; }"#,
+                "imports must be at the top level of a file",
+            ),
+            (
+                r#"func f(): void { pub func g(): void {} }"#,
+                "nested function g cannot be pub",
+            ),
+        ];
+        for (main, expected) in cases {
+            let errors = link_files(&[("main.pry", main), ("math.pry", MATH)]).unwrap_err();
+            assert_eq!(errors[0].message, expected, "{}", main);
+            assert_eq!(errors[0].file, None, "{}", main);
+        }
+    }
+
+    #[test]
+    // Imports that fail to resolve name the importing file, like errors in imported files do
+    fn missing_imports() {
+        let main = "var x: int = 1;\n  import \"missing.pry\";";
+        for (files, expected) in [
+            (vec![("main.pry", main)], "main.pry:2:3"),
+            (
+                vec![("a.pry", r#"import "main.pry";"#), ("main.pry", main)],
+                "main.pry:2:3",
+            ),
+        ] {
+            let errors = link_files(&files).unwrap_err();
+            assert_eq!(
+                errors[0].message,
+                "cannot read missing.pry: entity not found"
+            );
+            assert_eq!(errors[0].location(), expected);
+            assert!(
+                errors[0]
+                    .render(main, false)
+                    .contains(&format!(" --> {}\n", expected))
+            );
+        }
+        // Source that isn't from a file has no name to give
+        let errors = link_files(&[(INLINE_PATH, main)]).unwrap_err();
+        assert_eq!(errors[0].location(), "2:3");
+    }
+
+    #[test]
+    // Like a file reached through two others, a file imported twice by one file is loaded once
+    fn repeated_imports() {
+        let quiet = "println(\"quiet loaded\");\nfunc hidden(): void {}\n";
+        let cases = [
+            r#"import "math.pry"; import "math.pry";"#,
+            r#"import { square } from "math.pry"; import "./math.pry";"#,
+            r#"import "math.pry"; import { square } from "math.pry";"#,
+            r#"import "quiet.pry"; import "quiet.pry"; import "math.pry";"#,
+        ];
+        for main in cases {
+            let main = format!("{} println(square(3));", main);
+            let output = run_files(&[
+                ("main.pry", &main),
+                ("math.pry", MATH),
+                ("quiet.pry", quiet),
+            ])
+            .unwrap();
+            let loaded = if main.contains("quiet") {
+                "quiet loaded\nmath loaded\n"
+            } else {
+                "math loaded\n"
+            };
+            assert_eq!(output, format!("{}9\n", loaded), "{}", main);
+        }
+    }
+
+    #[test]
+    fn import_cycle() {
+        let errors = link_files(&[
+            ("a.pry", r#"import "b.pry";"#),
+            ("b.pry", r#"import "c.pry";"#),
+            ("c.pry", "\n  import \"b.pry\";"),
+        ])
+        .unwrap_err();
+        assert_eq!(errors[0].message, "import cycle: b.pry -> c.pry -> b.pry");
+        assert_eq!(errors[0].file.as_deref(), Some("c.pry"));
+        assert_eq!(errors[0].span.offset, 3);
+        assert_eq!(errors[0].location(), "c.pry:2:3");
+    }
+
+    #[test]
+    // Errors in imported files name the file and point into it, at every stage
+    fn errors_in_imported_files() {
+        let cases = vec![
+            ("\nvar x: int = 1 ? 2;", "c.pry:2:16", 16),
+            ("\nvar x: int = ;", "c.pry:2:14", 14),
+            ("\nvar x: string = 1;", "c.pry:2:17", 17),
+        ];
+        for (lib, expected, offset) in cases {
+            let errors = link_files(&[
+                ("main.pry", r#"import "b.pry";"#),
+                ("b.pry", r#"import "c.pry";"#),
+                ("c.pry", lib),
+            ])
+            .unwrap_err();
+            assert_eq!(errors[0].location(), expected, "{}", lib);
+            assert_eq!(errors[0].span.offset, offset, "{}", lib);
+        }
+    }
+
+    #[test]
+    fn runtime_errors_in_imported_files() {
+        let main = r#"import "math.pry";
+println(divide(4, 2));
+println(divide(1, 0));"#;
+        let err = &run_files(&[("main.pry", main), ("math.pry", MATH)]).unwrap_err()[0];
+        assert_eq!(err.message, "division by zero");
+        assert_eq!(err.location(), "math.pry:18:12");
+        assert_eq!(
+            &MATH[err.span.offset..err.span.offset + err.span.len],
+            "a / b"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use std::path::Path;
+
+use crate::analyzer::{Analyzer, Symbol, SymbolKind};
+use crate::common::{
+    ast::{Program, Span, SpannedStatement, Statement, Type},
+    error::PerryError,
+    token::{SpannedToken, Token},
+};
+use crate::json::Json;
+use crate::linker::Linker;
+use crate::parser::Parser;
+use crate::tokenizer::Tokenizer;
+
+const KEYWORDS: [&str; 10] = [
+    "var", "func", "if", "else", "while", "return", "void", "pub", "import", "from",
+];
+
+// An open document run through every analysis stage. Later stages work on whatever the earlier
+// ones could recover, so features keep working while the user is typing
+pub struct Document {
+    pub source: String,
+    pub tokens: Vec<SpannedToken>,
+    pub program: Program,
+    pub analyzer: Analyzer,
+    pub diagnostics: Vec<PerryError>,
+}
+
+impl Document {
+    pub fn analyze(source: &str) -> Self {
+        Self::analyze_file(None, source)
+    }
+
+    // Analyzes the source of the file at `path`. Files it imports are loaded from disk so their
+    // public functions can be checked against; without a path imports are unresolved
+    pub fn analyze_file(path: Option<&Path>, source: &str) -> Self {
+        let mut diagnostics: Vec<PerryError> = Vec::new();
+        let tokens = Tokenizer::new(source.to_string())
+            .tokenize()
+            .unwrap_or_else(|err| {
+                diagnostics.push(err.into());
+                Vec::new()
+            });
+        let program = if tokens.is_empty() {
+            Program { body: Vec::new() }
+        } else {
+            let (program, errors) = Parser::new(tokens.clone()).parse_partial();
+            diagnostics.extend(errors.into_iter().map(PerryError::from));
+            program
+        };
+        let mut analyzer = Analyzer::new();
+        if let Some(path) = path {
+            let mut linker = Linker::new();
+            for statement in &program.body {
+                let Statement::Import(import) = &statement.node else {
+                    continue;
+                };
+                match linker.exports(path, &import.path, statement.span) {
+                    Ok(exports) => analyzer.add_module(import.path.clone(), exports),
+                    // Errors inside the imported file are reported at the import
+                    Err(errors) => {
+                        diagnostics.extend(errors.into_iter().map(|err| match err.file {
+                            Some(file) => PerryError::new(
+                                err.kind,
+                                format!("in {}:{}: {}", file, err.span, err.message),
+                                statement.span,
+                            ),
+                            None => err,
+                        }))
+                    }
+                }
+            }
+        }
+        // Semantic errors in a partial program are mostly follow-ups of the syntax errors
+        if let Err(err) = analyzer.analyze(&program)
+            && diagnostics.is_empty()
+        {
+            diagnostics.push(err.into());
+        }
+        Self {
+            source: source.to_string(),
+            tokens,
+            program,
+            analyzer,
+            diagnostics,
+        }
+    }
+
+    // Converts an LSP position to a byte offset. LSP counts lines from 0 and characters in UTF-16
+    // code units
+    pub fn offset_at(&self, line: usize, character: usize) -> usize {
+        let line_start = self
+            .source
+            .split_inclusive('\n')
+            .take(line)
+            .map(str::len)
+            .sum::<usize>();
+        let mut units = 0;
+        for (index, ch) in self.source[line_start..].char_indices() {
+            if units >= character || ch == '\n' {
+                return line_start + index;
+            }
+            units += ch.len_utf16();
+        }
+        self.source.len()
+    }
+
+    // Converts a byte offset to an LSP position
+    pub fn position(&self, offset: usize) -> Json {
+        let offset = offset.min(self.source.len());
+        let before = &self.source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        Json::object(vec![
+            ("line", before.matches('\n').count().into()),
+            (
+                "character",
+                before[line_start..].encode_utf16().count().into(),
+            ),
+        ])
+    }
+
+    pub fn range(&self, span: Span) -> Json {
+        Json::object(vec![
+            ("start", self.position(span.offset)),
+            ("end", self.position(span.offset + span.len)),
+        ])
+    }
+
+    // Returns the name and span of the identifier under or right before the cursor
+    pub fn identifier_at(&self, offset: usize) -> Option<(&str, Span)> {
+        self.tokens.iter().find_map(|token| match &token.node {
+            Token::Identifier(name)
+                if token.span.offset <= offset
+                    && offset <= token.span.offset + token.span.len
+                    && !KEYWORDS.contains(&name.as_str()) =>
+            {
+                Some((name.as_str(), token.span))
+            }
+            _ => None,
+        })
+    }
+
+    // Resolves the identifier at the cursor, whether it is a use or a declaration
+    pub fn symbol_at(&self, offset: usize) -> Option<(String, Span, Symbol)> {
+        let (name, span) = self.identifier_at(offset)?;
+        let index = self.analyzer.index();
+        if let Some(reference) = index
+            .references
+            .iter()
+            .find(|reference| reference.span.offset == span.offset)
+        {
+            return Some((name.to_string(), span, reference.symbol.clone()));
+        }
+        index
+            .declarations
+            .iter()
+            .find(|declaration| {
+                declaration.name == name
+                    && self.declaration_name_span(name, &declaration.symbol) == Some(span)
+            })
+            .map(|declaration| (name.to_string(), span, declaration.symbol.clone()))
+    }
+
+    // Finds the name of a declaration inside its declaring statement, or inside the parameter
+    // for parameters, whose span starts at their name
+    pub fn declaration_name_span(&self, name: &str, symbol: &Symbol) -> Option<Span> {
+        let span = symbol.span?;
+        self.tokens
+            .iter()
+            .skip_while(|token| token.span.offset < span.offset)
+            .take_while(|token| token.span.offset < span.offset + span.len)
+            .find(|token| token.node == Token::Identifier(name.to_string()))
+            .map(|token| token.span)
+    }
+
+    // Returns every name that can be used at the cursor, sorted by name
+    pub fn visible_symbols(&self, offset: usize) -> Vec<(String, Symbol)> {
+        let mut symbols: Vec<(String, Symbol)> = self
+            .analyzer
+            .globals()
+            .into_iter()
+            .filter(|(_, symbol)| symbol.span.is_none())
+            .map(|(name, symbol)| (name.to_string(), symbol.clone()))
+            .collect();
+        collect_visible(&self.program.body, offset, &mut symbols);
+        // Declarations are collected from the outside in, so the last one of each name is the one
+        // in scope. Reversing first makes the stable sort and dedup keep it
+        symbols.reverse();
+        symbols.sort_by(|(a, _), (b, _)| a.cmp(b));
+        symbols.dedup_by(|(a, _), (b, _)| a == b);
+        symbols
+    }
+}
+
+// Collects the declarations made before `offset` in the blocks that contain it
+fn collect_visible(
+    statements: &[SpannedStatement],
+    offset: usize,
+    out: &mut Vec<(String, Symbol)>,
+) {
+    for statement in statements {
+        if statement.span.offset >= offset {
+            break;
+        }
+        let inside = offset <= statement.span.offset + statement.span.len;
+        match &statement.node {
+            Statement::VarDecl(var_decl) => out.push((
+                var_decl.name.clone(),
+                variable(var_decl.type_.clone(), statement.span),
+            )),
+            Statement::Function(func) => {
+                out.push((
+                    func.name.clone(),
+                    Symbol {
+                        kind: SymbolKind::Function,
+                        type_: func.type_.clone(),
+                        params: func.params.clone(),
+                        span: Some(statement.span),
+                    },
+                ));
+                if inside {
+                    for param in &func.params {
+                        out.push((
+                            param.name.clone(),
+                            variable(param.type_.clone(), param.span),
+                        ));
+                    }
+                    collect_visible(&func.body, offset, out);
+                }
+            }
+            Statement::If(if_statement) if inside => {
+                collect_visible(&if_statement.then_body, offset, out);
+                if let Some(else_body) = &if_statement.else_body {
+                    collect_visible(std::slice::from_ref(else_body), offset, out);
+                }
+            }
+            Statement::Loop(loop_statement) if inside => {
+                collect_visible(&loop_statement.body, offset, out);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn variable(type_: Type, span: Span) -> Symbol {
+    Symbol {
+        kind: SymbolKind::Variable,
+        type_,
+        params: Vec::new(),
+        span: Some(span),
+    }
+}
+
+// Describes a symbol the way it is declared, e.g. `func f(n: int): int` or `x: int`
+pub fn describe(name: &str, symbol: &Symbol) -> String {
+    match symbol.kind {
+        SymbolKind::Function => {
+            let params: Vec<String> = symbol
+                .params
+                .iter()
+                .map(|param| format!("{}: {}", param.name, param.type_))
+                .collect();
+            format!("func {}({}): {}", name, params.join(", "), symbol.type_)
+        }
+        SymbolKind::Variable => format!("{}: {}", name, symbol.type_),
+    }
+}
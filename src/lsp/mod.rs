@@ -0,0 +1,431 @@
+pub mod document;
+pub mod test;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use crate::analyzer::SymbolKind;
+use crate::common::{
+    ast::{self, Expression, FunctionDecl, Span, VariableDecl, Visitor},
+    error::PerryError,
+    token::Token,
+};
+use crate::json::Json;
+use document::{Document, describe};
+
+// JSON-RPC and LSP constants used by the server
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const SYNC_FULL: i32 = 1;
+const SEVERITY_ERROR: i32 = 1;
+const SYMBOL_FUNCTION: i32 = 12;
+const SYMBOL_VARIABLE: i32 = 13;
+const COMPLETION_FUNCTION: i32 = 3;
+const COMPLETION_VARIABLE: i32 = 6;
+
+// Serves the Language Server Protocol over `input` and `output` until the client sends `exit`
+// or closes the input
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::default();
+    while let Some(body) = read_message(&mut input)? {
+        let replies = match Json::parse(&body) {
+            Ok(message) => server.handle(&message),
+            Err(err) => vec![error_response(Json::Null, PARSE_ERROR, &err.to_string())],
+        };
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exited {
+            break;
+        }
+    }
+    Ok(())
+}
+
+// Reads one message body framed by a `Content-Length` header. Returns None at the end of input
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let Some(length) = content_length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, String>, // Source text by URI
+    exited: bool,
+}
+
+impl Server {
+    // Handles a request or notification and returns the messages to send back
+    fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let Some(method) = message.get("method").and_then(Json::as_str) else {
+            return Vec::new(); // A response to a request we never send
+        };
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+        let Some(id) = message.get("id").cloned() else {
+            return self.handle_notification(method, &params);
+        };
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => Some(Json::Null),
+            "textDocument/hover" => self.with_position(&params, hover),
+            "textDocument/definition" => self.with_position(&params, definition),
+            "textDocument/completion" => self.with_position(&params, completion),
+            "textDocument/documentSymbol" => self
+                .document(&params)
+                .map(|(_, document)| Json::Array(document_symbols(&document))),
+            _ => {
+                return vec![error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("unsupported method {}", method),
+                )];
+            }
+        };
+        match result {
+            Some(result) => vec![Json::object(vec![
+                ("jsonrpc", "2.0".into()),
+                ("id", id),
+                ("result", result),
+            ])],
+            None => vec![error_response(
+                id,
+                INVALID_PARAMS,
+                "unknown document or position",
+            )],
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .unwrap_or_default()
+            .to_string();
+        match method {
+            "exit" => {
+                self.exited = true;
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let text = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("text"))
+                    .and_then(Json::as_str)
+                    .unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![self.publish_diagnostics(&uri)]
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                match text {
+                    Some(text) => {
+                        self.documents.insert(uri.clone(), text.to_string());
+                        vec![self.publish_diagnostics(&uri)]
+                    }
+                    None => Vec::new(),
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![notification(
+                    "textDocument/publishDiagnostics",
+                    Json::object(vec![
+                        ("uri", uri.into()),
+                        ("diagnostics", Json::Array(Vec::new())),
+                    ]),
+                )]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn publish_diagnostics(&self, uri: &str) -> Json {
+        let document = Document::analyze_file(file_path(uri).as_deref(), &self.documents[uri]);
+        let diagnostics = document
+            .diagnostics
+            .iter()
+            .map(|err| diagnostic(&document, uri, err))
+            .collect();
+        notification(
+            "textDocument/publishDiagnostics",
+            Json::object(vec![
+                ("uri", uri.into()),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        )
+    }
+
+    // Analyzes the document named in the params
+    fn document(&self, params: &Json) -> Option<(String, Document)> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        let source = self.documents.get(uri)?;
+        let document = Document::analyze_file(file_path(uri).as_deref(), source);
+        Some((uri.to_string(), document))
+    }
+
+    // Runs a request handler on the document and cursor offset named in the params
+    fn with_position(
+        &self,
+        params: &Json,
+        handler: fn(&str, &Document, usize) -> Json,
+    ) -> Option<Json> {
+        let (uri, document) = self.document(params)?;
+        let position = params.get("position")?;
+        let line = position.get("line")?.as_i64()?;
+        let character = position.get("character")?.as_i64()?;
+        let offset = document.offset_at(line as usize, character as usize);
+        Some(handler(&uri, &document, offset))
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object(vec![
+        (
+            "capabilities",
+            Json::object(vec![
+                ("textDocumentSync", SYNC_FULL.into()),
+                ("hoverProvider", true.into()),
+                ("definitionProvider", true.into()),
+                ("documentSymbolProvider", true.into()),
+                ("completionProvider", Json::object(Vec::new())),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object(vec![
+                ("name", "perry".into()),
+                ("version", env!("CARGO_PKG_VERSION").into()),
+            ]),
+        ),
+    ])
+}
+
+// Shows the type of the variable or the signature of the function under the cursor
+fn hover(_: &str, document: &Document, offset: usize) -> Json {
+    let Some((name, span, symbol)) = document.symbol_at(offset) else {
+        return Json::Null;
+    };
+    Json::object(vec![
+        (
+            "contents",
+            Json::object(vec![
+                ("kind", "markdown".into()),
+                (
+                    "value",
+                    format!("```perry\n{}\n```", describe(&name, &symbol)).into(),
+                ),
+            ]),
+        ),
+        ("range", document.range(span)),
+    ])
+}
+
+// Jumps to the name in the declaration of the symbol under the cursor. Built-ins have none
+fn definition(uri: &str, document: &Document, offset: usize) -> Json {
+    document
+        .symbol_at(offset)
+        .and_then(|(name, _, symbol)| document.declaration_name_span(&name, &symbol))
+        .map_or(Json::Null, |span| location(uri, document.range(span)))
+}
+
+// Offers every name that is in scope at the cursor
+fn completion(_: &str, document: &Document, offset: usize) -> Json {
+    let items = document
+        .visible_symbols(offset)
+        .into_iter()
+        .map(|(name, symbol)| {
+            let kind = match symbol.kind {
+                SymbolKind::Function => COMPLETION_FUNCTION,
+                SymbolKind::Variable => COMPLETION_VARIABLE,
+            };
+            Json::object(vec![
+                ("label", name.as_str().into()),
+                ("kind", kind.into()),
+                ("detail", describe(&name, &symbol).into()),
+            ])
+        })
+        .collect();
+    Json::Array(items)
+}
+
+// Lists functions and variables as a tree. Declarations in nested blocks belong to the enclosing
+// function, or to the document at the top level
+fn document_symbols(document: &Document) -> Vec<Json> {
+    let mut collector = SymbolCollector {
+        document,
+        symbols: Vec::new(),
+    };
+    collector.visit_program(&document.program);
+    collector.symbols
+}
+
+struct SymbolCollector<'a> {
+    document: &'a Document,
+    symbols: Vec<Json>, // Symbols of the function being walked, or of the document
+}
+
+impl SymbolCollector<'_> {
+    fn symbol(
+        &self,
+        name: &str,
+        detail: String,
+        kind: i32,
+        span: Span,
+        children: Vec<Json>,
+    ) -> Json {
+        let name_span = self
+            .document
+            .tokens
+            .iter()
+            .skip_while(|token| token.span.offset <= span.offset)
+            .find(|token| token.node == Token::Identifier(name.to_string()))
+            .map_or(span, |token| token.span);
+        Json::object(vec![
+            ("name", name.into()),
+            ("detail", detail.into()),
+            ("kind", kind.into()),
+            ("range", self.document.range(span)),
+            ("selectionRange", self.document.range(name_span)),
+            ("children", Json::Array(children)),
+        ])
+    }
+}
+
+impl Visitor for SymbolCollector<'_> {
+    fn visit_function_decl(&mut self, func: &FunctionDecl, span: Span) {
+        let outer = std::mem::take(&mut self.symbols);
+        ast::walk_function_decl(self, func, span);
+        let children = std::mem::replace(&mut self.symbols, outer);
+        let params: Vec<String> = func
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, param.type_))
+            .collect();
+        let detail = format!("({}): {}", params.join(", "), func.type_);
+        let symbol = self.symbol(&func.name, detail, SYMBOL_FUNCTION, span, children);
+        self.symbols.push(symbol);
+    }
+
+    fn visit_var_decl(&mut self, var_decl: &VariableDecl, span: Span) {
+        let detail = var_decl.type_.to_string();
+        let symbol = self.symbol(&var_decl.name, detail, SYMBOL_VARIABLE, span, Vec::new());
+        self.symbols.push(symbol);
+    }
+
+    // No declarations live inside expressions
+    fn visit_expression(&mut self, _expression: &Expression, _span: Span) {}
+}
+
+fn diagnostic(document: &Document, uri: &str, err: &PerryError) -> Json {
+    let mut message = err.message.clone();
+    for note in &err.notes {
+        message.push_str(&format!("\nnote: {}", note));
+    }
+    let related = err
+        .labels
+        .iter()
+        .map(|label| {
+            Json::object(vec![
+                ("location", location(uri, document.range(label.span))),
+                ("message", label.message.as_str().into()),
+            ])
+        })
+        .collect();
+    Json::object(vec![
+        ("range", document.range(err.span)),
+        ("severity", SEVERITY_ERROR.into()),
+        ("code", err.code().into()),
+        ("source", "perry".into()),
+        ("message", message.into()),
+        ("relatedInformation", Json::Array(related)),
+    ])
+}
+
+// The file a `file://` URI names, which imports in the document are resolved against. Characters
+// such as spaces and non-ASCII letters are percent-encoded in URIs
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut index = 0;
+    while index < encoded.len() {
+        let escaped = encoded
+            .get(index + 1..index + 3)
+            .filter(|_| encoded[index] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                index += 3;
+            }
+            None => {
+                bytes.push(encoded[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+fn location(uri: &str, range: Json) -> Json {
+    Json::object(vec![("uri", uri.into()), ("range", range)])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn error_response(id: Json, code: i32, message: &str) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object(vec![("code", code.into()), ("message", message.into())]),
+        ),
+    ])
+}
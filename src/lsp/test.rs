@@ -0,0 +1,346 @@
+#![cfg(test)]
+
+use super::*;
+use crate::analyzer::Analyzer;
+
+const URI: &str = "file:///main.pry";
+
+// Frames the messages, runs a server over them and returns everything it sent back
+pub fn session(messages: Vec<Json>) -> Vec<Json> {
+    let mut input = Vec::new();
+    for message in &messages {
+        write_message(&mut input, message).unwrap();
+    }
+    let mut output = Vec::new();
+    run(input.as_slice(), &mut output).unwrap();
+    let mut reader = output.as_slice();
+    let mut replies = Vec::new();
+    while let Some(body) = read_message(&mut reader).unwrap() {
+        replies.push(Json::parse(&body).unwrap());
+    }
+    replies
+}
+
+pub fn request(id: i32, method: &str, params: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", "2.0".into()),
+        ("id", id.into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+pub fn open(text: &str) -> Json {
+    notification(
+        "textDocument/didOpen",
+        Json::object(vec![(
+            "textDocument",
+            Json::object(vec![
+                ("uri", URI.into()),
+                ("languageId", "perry".into()),
+                ("version", 1.into()),
+                ("text", text.into()),
+            ]),
+        )]),
+    )
+}
+
+// Params naming the test document and a 0-based cursor position
+pub fn at(line: i32, character: i32) -> Json {
+    Json::object(vec![
+        ("textDocument", Json::object(vec![("uri", URI.into())])),
+        (
+            "position",
+            Json::object(vec![("line", line.into()), ("character", character.into())]),
+        ),
+    ])
+}
+
+// Runs the requests against the opened text and returns the result of each, in order
+pub fn results(text: &str, requests: Vec<Json>) -> Vec<Json> {
+    let mut messages = vec![open(text)];
+    messages.extend(requests);
+    session(messages)
+        .into_iter()
+        .filter_map(|reply| reply.get("result").cloned())
+        .collect()
+}
+
+mod tests {
+    use super::*;
+
+    const SOURCE: &str =
+        "func double(n: int): int {\n    return n * 2;\n}\nvar x: int = double(4);\nprintln(x);\n";
+
+    fn range(start: (i32, i32), end: (i32, i32)) -> Json {
+        let position = |(line, character): (i32, i32)| {
+            Json::object(vec![("line", line.into()), ("character", character.into())])
+        };
+        Json::object(vec![("start", position(start)), ("end", position(end))])
+    }
+
+    #[test]
+    fn initialize_and_shutdown() {
+        let replies = session(vec![
+            request(1, "initialize", Json::object(Vec::new())),
+            notification("initialized", Json::object(Vec::new())),
+            request(2, "shutdown", Json::Null),
+            notification("exit", Json::Null),
+            request(3, "shutdown", Json::Null),
+        ]);
+        assert_eq!(replies.len(), 2);
+        let capabilities = replies[0]
+            .get("result")
+            .and_then(|result| result.get("capabilities"))
+            .unwrap();
+        assert_eq!(capabilities.get("hoverProvider"), Some(&Json::Bool(true)));
+        assert_eq!(replies[1].get("id"), Some(&Json::from(2)));
+        assert_eq!(replies[1].get("result"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn unknown_request() {
+        let replies = session(vec![request(1, "workspace/symbol", Json::Null)]);
+        let code = replies[0].get("error").and_then(|error| error.get("code"));
+        assert_eq!(code, Some(&Json::from(METHOD_NOT_FOUND)));
+    }
+
+    #[test]
+    fn diagnostics_published() {
+        let replies = session(vec![
+            open("var x: int = ;\nvar y: int = ;"),
+            notification(
+                "textDocument/didChange",
+                Json::object(vec![
+                    (
+                        "textDocument",
+                        Json::object(vec![("uri", URI.into()), ("version", 2.into())]),
+                    ),
+                    (
+                        "contentChanges",
+                        Json::from(vec![Json::object(vec![(
+                            "text",
+                            "var x: string = 1;".into(),
+                        )])]),
+                    ),
+                ]),
+            ),
+        ]);
+        let diagnostics = |reply: &Json| {
+            reply
+                .get("params")
+                .and_then(|params| params.get("diagnostics"))
+                .and_then(Json::as_array)
+                .unwrap()
+                .to_vec()
+        };
+        let syntax = diagnostics(&replies[0]);
+        assert_eq!(syntax.len(), 2);
+        assert_eq!(syntax[0].get("code"), Some(&Json::from("E02")));
+        assert_eq!(syntax[1].get("range"), Some(&range((1, 13), (1, 14))));
+        let semantic = diagnostics(&replies[1]);
+        assert_eq!(semantic.len(), 1);
+        assert_eq!(semantic[0].get("code"), Some(&Json::from("E03")));
+        assert_eq!(semantic[0].get("range"), Some(&range((0, 16), (0, 17))));
+    }
+
+    #[test]
+    // Used to take the server down
+    fn integer_literal_out_of_range() {
+        let replies = session(vec![
+            open("var x: int = 99999999999;"),
+            request(1, "shutdown", Json::Null),
+        ]);
+        let diagnostics = replies[0]
+            .get("params")
+            .and_then(|params| params.get("diagnostics"))
+            .and_then(Json::as_array)
+            .unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].get("code"), Some(&Json::from("E01")));
+        assert_eq!(
+            diagnostics[0].get("message"),
+            Some(&Json::from("integer literal out of range"))
+        );
+        assert_eq!(diagnostics[0].get("range"), Some(&range((0, 13), (0, 24))));
+        assert_eq!(replies[1].get("result"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn hover() {
+        let results = results(
+            SOURCE,
+            vec![
+                request(1, "textDocument/hover", at(3, 15)),
+                request(2, "textDocument/hover", at(1, 11)),
+                request(3, "textDocument/hover", at(4, 2)),
+                request(4, "textDocument/hover", at(3, 4)),
+                request(5, "textDocument/hover", at(3, 12)),
+            ],
+        );
+        let value = |result: &Json| {
+            result
+                .get("contents")
+                .and_then(|contents| contents.get("value"))
+                .and_then(Json::as_str)
+                .map(str::to_string)
+        };
+        assert_eq!(
+            value(&results[0]).unwrap(),
+            "```perry\nfunc double(n: int): int\n```"
+        );
+        assert_eq!(results[0].get("range"), Some(&range((3, 13), (3, 19))));
+        assert_eq!(value(&results[1]).unwrap(), "```perry\nn: int\n```");
+        assert_eq!(
+            value(&results[2]).unwrap(),
+            "```perry\nfunc println(arg: any): void\n```"
+        );
+        assert_eq!(value(&results[3]).unwrap(), "```perry\nx: int\n```");
+        assert_eq!(results[4], Json::Null);
+    }
+
+    #[test]
+    fn definition() {
+        let results = results(
+            SOURCE,
+            vec![
+                request(1, "textDocument/definition", at(3, 15)),
+                request(2, "textDocument/definition", at(1, 11)),
+                request(3, "textDocument/definition", at(4, 9)),
+                request(4, "textDocument/definition", at(4, 2)),
+            ],
+        );
+        let target = |result: &Json| result.get("range").cloned().unwrap();
+        assert_eq!(target(&results[0]), range((0, 5), (0, 11)));
+        assert_eq!(target(&results[1]), range((0, 12), (0, 13)));
+        assert_eq!(target(&results[2]), range((3, 4), (3, 5)));
+        assert_eq!(results[3], Json::Null);
+    }
+
+    #[test]
+    fn document_symbols() {
+        let source = "func f(n: int): void {\n    if (n > 0) {\n        var inner: bool = true;\n    }\n}\nvar x: int = 1;\n";
+        let params = Json::object(vec![(
+            "textDocument",
+            Json::object(vec![("uri", URI.into())]),
+        )]);
+        let results = results(
+            source,
+            vec![request(1, "textDocument/documentSymbol", params)],
+        );
+        let symbols = results[0].as_array().unwrap();
+        let summary = |symbol: &Json| {
+            (
+                symbol
+                    .get("name")
+                    .and_then(Json::as_str)
+                    .unwrap()
+                    .to_string(),
+                symbol
+                    .get("detail")
+                    .and_then(Json::as_str)
+                    .unwrap()
+                    .to_string(),
+                symbol.get("kind").and_then(Json::as_i64).unwrap(),
+            )
+        };
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(
+            summary(&symbols[0]),
+            ("f".to_string(), "(n: int): void".to_string(), 12)
+        );
+        assert_eq!(symbols[0].get("range"), Some(&range((0, 0), (4, 1))));
+        assert_eq!(
+            symbols[0].get("selectionRange"),
+            Some(&range((0, 5), (0, 6)))
+        );
+        let children = symbols[0].get("children").and_then(Json::as_array).unwrap();
+        assert_eq!(
+            summary(&children[0]),
+            ("inner".to_string(), "bool".to_string(), 13)
+        );
+        assert_eq!(
+            summary(&symbols[1]),
+            ("x".to_string(), "int".to_string(), 13)
+        );
+    }
+
+    #[test]
+    fn completion() {
+        let source = "var a: int = 1;\nfunc f(n: int): int {\n    var b: int = n;\n    return b;\n}\nvar c: int = 2;\n";
+        let results = results(
+            source,
+            vec![
+                request(1, "textDocument/completion", at(3, 11)),
+                request(2, "textDocument/completion", at(5, 0)),
+            ],
+        );
+        let labels = |result: &Json| {
+            result
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| {
+                    item.get("label")
+                        .and_then(Json::as_str)
+                        .unwrap()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+        };
+        // Every built-in is offered as well
+        let built_ins: Vec<String> = Analyzer::new()
+            .globals()
+            .into_iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        let user_labels = |result: &Json| {
+            let mut labels = labels(result);
+            assert!(built_ins.iter().all(|name| labels.contains(name)));
+            labels.retain(|label| !built_ins.contains(label));
+            labels
+        };
+        assert_eq!(user_labels(&results[0]), vec!["a", "b", "f", "n"]);
+        assert_eq!(user_labels(&results[1]), vec!["a", "f"]);
+        let detail = results[0]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item.get("label") == Some(&Json::from("f")))
+            .and_then(|item| item.get("detail"))
+            .cloned();
+        assert_eq!(detail, Some(Json::from("func f(n: int): int")));
+    }
+
+    #[test]
+    // Only the innermost of several declarations with one name is offered
+    fn completion_prefers_inner_declaration() {
+        let source =
+            "var x: int = 1;\nfunc f(): void {\n    var x: string = \"a\";\n    println(x);\n}\n";
+        let results = results(
+            source,
+            vec![request(1, "textDocument/completion", at(3, 4))],
+        );
+        let details: Vec<Json> = results[0]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|item| item.get("label") == Some(&Json::from("x")))
+            .filter_map(|item| item.get("detail").cloned())
+            .collect();
+        assert_eq!(details, vec![Json::from("x: string")]);
+    }
+
+    #[test]
+    fn file_uris_decoded() {
+        assert_eq!(
+            file_path("file:///tmp/my%20dir/caf%C3%A9.pry"),
+            Some(PathBuf::from("/tmp/my dir/café.pry"))
+        );
+        assert_eq!(
+            file_path("file:///tmp/100%.pry"),
+            Some(PathBuf::from("/tmp/100%.pry"))
+        );
+        assert_eq!(file_path("untitled:1"), None);
+    }
+}
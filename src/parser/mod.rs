@@ -3,6 +3,7 @@ pub mod test;
 use std::fmt::Display;
 use std::str::FromStr;
 
+use crate::common::error::{ErrorKind, PerryError};
 use crate::common::token::{SpannedToken, Token};
 
 use crate::common::ast::*;
@@ -10,6 +11,7 @@ use crate::common::ast::*;
 pub struct Parser {
     tokens: Vec<SpannedToken>,
     position: usize,
+    errors: Vec<SyntaxError>,
 }
 
 impl Parser {
@@ -18,27 +20,96 @@ impl Parser {
         Self {
             tokens,
             position: 0,
+            errors: Vec::new(),
         }
     }
 
-    // Basically just a wrapper around parse_body for now. This abstraction might be useful later
-    // to add global file properties like shebangs or imports
-    pub fn parse(&mut self) -> Result<Program, SyntaxError> {
-        Ok(Program {
-            body: self.parse_body()?,
-        })
+    // Parses the entire input, returning every syntax error found if there were any
+    pub fn parse(&mut self) -> Result<Program, Vec<SyntaxError>> {
+        let (program, errors) = self.parse_partial();
+        if errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // Parses the entire input, recovering from syntax errors. Returns whatever statements could be
+    // parsed along with all errors, so tooling can keep working on broken files
+    pub fn parse_partial(&mut self) -> (Program, Vec<SyntaxError>) {
+        let mut body = self.parse_statements();
+        // A stray closing brace ends parse_statements early at the top level
+        while self.curr() == &Token::RightBrace {
+            self.errors.push(SyntaxError::new(
+                format!("unexpected {:?}", Token::RightBrace),
+                self.curr_span(),
+            ));
+            self.advance();
+            body.extend(self.parse_statements());
+        }
+        (Program { body }, std::mem::take(&mut self.errors))
     }
 
-    // Parses the entire input and returns a vector of statements
+    // Parses input consisting of a single expression, optionally followed by a `;`
+    pub fn parse_standalone_expression(&mut self) -> Result<SpannedExpression, SyntaxError> {
+        let expression = self.parse_expression(0)?;
+        if self.curr() == &Token::Semicolon {
+            self.advance();
+        }
+        self.expect(Token::EOF)?;
+        Ok(expression)
+    }
+
+    // Parses the statements of a block and consumes its closing brace
     pub fn parse_body(&mut self) -> Result<Vec<SpannedStatement>, SyntaxError> {
+        let statements = self.parse_statements();
+        self.expect(Token::RightBrace)?;
+        Ok(statements)
+    }
+
+    // Parses statements until the end of the input or the current block. Statements that fail to
+    // parse are recorded as errors and skipped
+    fn parse_statements(&mut self) -> Vec<SpannedStatement> {
         let mut statements: Vec<SpannedStatement> = Vec::new();
         while self.curr() != &Token::EOF && self.curr() != &Token::RightBrace {
-            statements.push(self.parse_statement()?);
+            match self.parse_statement() {
+                Ok(statement) => statements.push(statement),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.synchronize();
+                }
+            }
         }
-        if self.curr() == &Token::RightBrace {
+        statements
+    }
+
+    // Panic-mode recovery: skips tokens until the end of the broken statement. Stops after a `;`,
+    // or after the `}` closing a block opened while skipping. An unmatched `}` is left for the
+    // enclosing block to consume
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.curr() {
+                Token::EOF => return,
+                Token::Semicolon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                Token::LeftBrace => depth += 1,
+                Token::RightBrace => {
+                    if depth == 0 {
+                        return;
+                    }
+                    depth -= 1;
+                    if depth == 0 {
+                        self.advance();
+                        return;
+                    }
+                }
+                _ => {}
+            }
             self.advance();
         }
-        Ok(statements)
     }
 
     // Parses an expression using precedence climbing
@@ -60,66 +131,60 @@ impl Parser {
             self.advance(); // consume the operator
             // Left-associative: use op_prec + 1
             let right = self.parse_expression(op_prec + 1)?;
+            let span = left.span.to(right.span);
             left = Expression::Binary(Box::new(BinaryExpression {
                 left,
                 operator: op,
                 right,
             }))
-            .spanned(self.curr_span());
+            .spanned(span);
         }
         Ok(left)
     }
 
     // Parses a primary expression (e.g., literal, identifier, function call, or parenthesized expression)
     fn parse_primary(&mut self) -> Result<SpannedExpression, SyntaxError> {
+        let start = self.curr_span();
         match self.curr().clone() {
             Token::StringLiteral(value) => {
                 self.advance();
                 Ok(Expression::Literal(LiteralExpression {
                     value: LiteralValue::String(value),
                 })
-                .spanned(self.curr_span()))
+                .spanned(start))
             }
             Token::NumericLiteral(value) => {
                 self.advance();
                 Ok(Expression::Literal(LiteralExpression {
                     value: LiteralValue::Number(value),
                 })
-                .spanned(self.curr_span()))
+                .spanned(start))
             }
             Token::BooleanLiteral(value) => {
                 self.advance();
                 Ok(Expression::Literal(LiteralExpression {
                     value: LiteralValue::Bool(value),
                 })
-                .spanned(self.curr_span()))
+                .spanned(start))
             }
             Token::Identifier(name) => {
                 self.advance();
                 if self.curr() == &Token::LeftParen {
-                    self.advance(); // consume '('
-                    let mut args = Vec::new();
-                    if self.curr() != &Token::RightParen {
-                        args.push(self.parse_expression(0)?);
-                        while self.curr() == &Token::Comma {
-                            self.advance();
-                            args.push(self.parse_expression(0)?);
-                        }
-                    }
-                    self.expect(Token::RightParen)?;
+                    let args = self.parse_arguments()?;
                     Ok(
                         Expression::FunctionCall(Box::new(FunctionCall { callee: name, args }))
-                            .spanned(self.curr_span()),
+                            .spanned(self.span_from(start)),
                     )
                 } else {
-                    Ok(Expression::VariableRef(Box::new(VariableRef { name }))
-                        .spanned(self.curr_span()))
+                    Ok(Expression::VariableRef(Box::new(VariableRef { name })).spanned(start))
                 }
             }
             Token::LeftParen => {
                 self.advance();
-                let expr = self.parse_expression(0)?;
+                let mut expr = self.parse_expression(0)?;
                 self.expect(Token::RightParen)?;
+                // The parentheses are part of the expression's source range
+                expr.span = self.span_from(start);
                 Ok(expr)
             }
             _ => Err(SyntaxError::new(
@@ -129,7 +194,22 @@ impl Parser {
         }
     }
 
-    fn parse_variable_assignemnt(&mut self) -> Result<SpannedStatement, SyntaxError> {
+    // Parses a parenthesized, comma separated argument list
+    fn parse_arguments(&mut self) -> Result<Vec<SpannedExpression>, SyntaxError> {
+        self.expect(Token::LeftParen)?;
+        let mut args = Vec::new();
+        if self.curr() != &Token::RightParen {
+            args.push(self.parse_expression(0)?); // Parse first argument
+            while self.curr() == &Token::Comma {
+                self.advance(); // consume comma
+                args.push(self.parse_expression(0)?); // Parse next argument
+            }
+        }
+        self.expect(Token::RightParen)?;
+        Ok(args)
+    }
+
+    fn parse_variable_assignemnt(&mut self) -> Result<Statement, SyntaxError> {
         let Token::Identifier(name) = self.curr().clone() else {
             return Err(SyntaxError::new(
                 format!("expected identifier, got {:?}", self.curr()),
@@ -140,10 +220,10 @@ impl Parser {
         self.expect(Token::Assign)?;
         let value = self.parse_expression(0)?;
         self.expect(Token::Semicolon)?;
-        Ok(Statement::VarAssignment(VariableAssignment { name, value }).spanned(self.curr_span()))
+        Ok(Statement::VarAssignment(VariableAssignment { name, value }))
     }
 
-    fn parse_variable_declaration(&mut self) -> Result<SpannedStatement, SyntaxError> {
+    fn parse_variable_declaration(&mut self) -> Result<Statement, SyntaxError> {
         let Token::Identifier(name) = self.curr().clone() else {
             return Err(SyntaxError::new(
                 format!("expected identifier, got {:?}", self.curr()),
@@ -152,21 +232,11 @@ impl Parser {
         };
         self.advance();
         self.expect(Token::Colon)?;
-        let Token::Identifier(type_name) = self.curr() else {
-            return Err(SyntaxError::new(
-                format!("expected type identifier, got {:?}", self.curr()),
-                self.curr_span(),
-            ));
-        };
-        let type_ = match Type::from_str(type_name) {
-            Ok(type_) => type_,
-            Err(err) => return Err(SyntaxError::new(err, self.curr_span())),
-        };
-        self.advance();
+        let type_ = self.parse_type("type identifier")?;
         self.expect(Token::Assign)?;
         let value = self.parse_expression(0)?;
         self.expect(Token::Semicolon)?;
-        Ok(Statement::VarDecl(VariableDecl { name, value, type_ }).spanned(self.curr_span()))
+        Ok(Statement::VarDecl(VariableDecl { name, value, type_ }))
     }
 
     // Parses an if statement.
@@ -179,24 +249,29 @@ impl Parser {
         let then_body = self.parse_body()?;
         let else_body: Option<Box<SpannedStatement>> =
             if self.curr() == &Token::Identifier("else".to_string()) {
+                let else_span = self.curr_span();
                 self.advance();
                 if self.curr() == &Token::Identifier("if".to_string()) {
+                    let start = self.curr_span();
                     self.advance();
+                    let else_if = self.parse_if()?;
                     Some(Box::new(
-                        Statement::If(self.parse_if()?).spanned(self.curr_span()),
+                        Statement::If(else_if).spanned(self.span_from(start)),
                     ))
                 } else {
+                    // A plain else is an always-true if spanning the `else` keyword and its block
                     self.expect(Token::LeftBrace)?;
+                    let then_body = self.parse_body()?;
                     Some(Box::new(
                         Statement::If(IfStatement {
                             condition: Expression::Literal(LiteralExpression {
                                 value: LiteralValue::Bool(true),
                             })
-                            .spanned(self.curr_span()),
-                            then_body: self.parse_body()?,
+                            .spanned(else_span),
+                            then_body,
                             else_body: None,
                         })
-                        .spanned(self.curr_span()),
+                        .spanned(self.span_from(else_span)),
                     ))
                 }
             } else {
@@ -209,16 +284,16 @@ impl Parser {
         })
     }
 
-    fn parse_loop(&mut self) -> Result<SpannedStatement, SyntaxError> {
+    fn parse_loop(&mut self) -> Result<Statement, SyntaxError> {
         self.expect(Token::LeftParen)?;
         let condition = self.parse_expression(0)?;
         self.expect(Token::RightParen)?;
         self.expect(Token::LeftBrace)?;
         let body = self.parse_body()?;
-        Ok(Statement::Loop(LoopStatement { condition, body }).spanned(self.curr_span()))
+        Ok(Statement::Loop(LoopStatement { condition, body }))
     }
 
-    fn parse_function(&mut self) -> Result<SpannedStatement, SyntaxError> {
+    fn parse_function(&mut self, public: bool) -> Result<Statement, SyntaxError> {
         let Token::Identifier(name) = self.curr().clone() else {
             return Err(SyntaxError::new(
                 format!("expected identifier, got {:?}", self.curr()),
@@ -237,17 +312,7 @@ impl Parser {
         }
         self.expect(Token::RightParen)?;
         self.expect(Token::Colon)?;
-        let Token::Identifier(return_type_name) = self.curr() else {
-            return Err(SyntaxError::new(
-                format!("expected return type identifier, got {:?}", self.curr()),
-                self.curr_span(),
-            ));
-        };
-        let type_ = match Type::from_str(return_type_name) {
-            Ok(type_) => type_,
-            Err(err) => return Err(SyntaxError::new(err, self.curr_span())),
-        };
-        self.advance();
+        let type_ = self.parse_type("return type identifier")?;
         self.expect(Token::LeftBrace)?;
         let body = self.parse_body()?;
         Ok(Statement::Function(FunctionDecl {
@@ -255,11 +320,37 @@ impl Parser {
             params,
             type_,
             body,
-        })
-        .spanned(self.curr_span()))
+            public,
+        }))
     }
 
-    fn parse_function_call(&mut self) -> Result<SpannedStatement, SyntaxError> {
+    // Parses `import "path";` or `import { a, b } from "path";` after the `import` keyword
+    fn parse_import(&mut self) -> Result<Statement, SyntaxError> {
+        let mut names = None;
+        if self.curr() == &Token::LeftBrace {
+            self.advance();
+            let mut list = vec![self.parse_identifier()?];
+            while self.curr() == &Token::Comma {
+                self.advance();
+                list.push(self.parse_identifier()?);
+            }
+            self.expect(Token::RightBrace)?;
+            self.expect(Token::Identifier("from".to_string()))?;
+            names = Some(list);
+        }
+        let Token::StringLiteral(path) = self.curr().clone() else {
+            return Err(SyntaxError::new(
+                format!("expected import path, got {:?}", self.curr()),
+                self.curr_span(),
+            ));
+        };
+        self.advance();
+        self.expect(Token::Semicolon)?;
+        Ok(Statement::Import(ImportStatement { path, names }))
+    }
+
+    // Parses and consumes a name
+    fn parse_identifier(&mut self) -> Result<String, SyntaxError> {
         let Token::Identifier(name) = self.curr().clone() else {
             return Err(SyntaxError::new(
                 format!("expected identifier, got {:?}", self.curr()),
@@ -267,39 +358,38 @@ impl Parser {
             ));
         };
         self.advance();
-        self.expect(Token::LeftParen)?;
-        let mut args = Vec::new();
-        if self.curr() != &Token::RightParen {
-            args.push(self.parse_expression(0)?); // Parse first argument
-            while self.curr() == &Token::Comma {
-                self.advance(); // consume comma
-                args.push(self.parse_expression(0)?); // Parse next argument
-            }
-        }
+        Ok(name)
+    }
+
+    fn parse_function_call(&mut self) -> Result<Statement, SyntaxError> {
+        let Token::Identifier(name) = self.curr().clone() else {
+            return Err(SyntaxError::new(
+                format!("expected identifier, got {:?}", self.curr()),
+                self.curr_span(),
+            ));
+        };
+        self.advance();
+        let args = self.parse_arguments()?;
         // Consume end of statement
-        self.expect(Token::RightParen)?;
         self.expect(Token::Semicolon)?;
-        Ok(
-            Statement::Expr(Expression::FunctionCall(Box::new(FunctionCall {
-                callee: name,
-                args,
-            })))
-            .spanned(self.curr_span()),
-        )
+        Ok(Statement::Expr(Expression::FunctionCall(Box::new(
+            FunctionCall { callee: name, args },
+        ))))
     }
 
-    fn parse_return(&mut self) -> Result<SpannedStatement, SyntaxError> {
+    fn parse_return(&mut self) -> Result<Statement, SyntaxError> {
         if self.curr() == &Token::Semicolon {
             self.advance();
-            return Ok(Statement::Return(ReturnStatement { value: None }).spanned(self.curr_span()));
+            return Ok(Statement::Return(ReturnStatement { value: None }));
         }
         let value = self.parse_expression(0)?;
         self.expect(Token::Semicolon)?;
-        Ok(Statement::Return(ReturnStatement { value: Some(value) }).spanned(self.curr_span()))
+        Ok(Statement::Return(ReturnStatement { value: Some(value) }))
     }
 
     // Parses a parameter
     fn parse_parameter(&mut self) -> Result<Parameter, SyntaxError> {
+        let start = self.curr_span();
         let Token::Identifier(name) = self.curr().clone() else {
             return Err(SyntaxError::new(
                 format!("expected identifier, got {:?}", self.curr()),
@@ -308,22 +398,34 @@ impl Parser {
         };
         self.advance();
         self.expect(Token::Colon)?;
+        let type_ = self.parse_type("type identifier")?;
+        Ok(Parameter {
+            name,
+            type_,
+            span: self.span_from(start),
+        })
+    }
+
+    // Parses and consumes a type name. `expected` describes the token in error messages
+    fn parse_type(&mut self, expected: &str) -> Result<Type, SyntaxError> {
         let Token::Identifier(type_name) = self.curr() else {
             return Err(SyntaxError::new(
-                format!("expected type identifier, got {:?}", self.curr()),
+                format!("expected {}, got {:?}", expected, self.curr()),
                 self.curr_span(),
             ));
         };
-        let type_ = match Type::from_str(&type_name) {
+        let type_ = match Type::from_str(type_name) {
             Ok(type_) => type_,
             Err(err) => return Err(SyntaxError::new(err, self.curr_span())),
         };
         self.advance();
-        Ok(Parameter { name, type_ })
+        Ok(type_)
     }
 
-    // Parses a statement (e.g., function call)
+    // Parses a statement (e.g., function call). The statement's span covers everything from its
+    // first token up to and including its closing `;` or `}`
     fn parse_statement(&mut self) -> Result<SpannedStatement, SyntaxError> {
+        let start = self.curr_span();
         let token = self.curr();
         let Token::Identifier(name) = token.clone() else {
             return Err(SyntaxError::new(
@@ -334,15 +436,14 @@ impl Parser {
                 self.curr_span(),
             ));
         };
-        match name.as_str() {
+        let statement = match name.as_str() {
             "var" => {
                 self.advance();
                 self.parse_variable_declaration()
             }
             "if" => {
                 self.advance();
-                self.parse_if()
-                    .map(|if_statement| Statement::If(if_statement).spanned(self.curr_span()))
+                self.parse_if().map(Statement::If)
             }
             "return" => {
                 self.advance();
@@ -354,7 +455,16 @@ impl Parser {
             }
             "func" => {
                 self.advance();
-                self.parse_function()
+                self.parse_function(false)
+            }
+            "pub" => {
+                self.advance();
+                self.expect(Token::Identifier("func".to_string()))?;
+                self.parse_function(true)
+            }
+            "import" => {
+                self.advance();
+                self.parse_import()
             }
             _ => match self.peek() {
                 Token::LeftParen => self.parse_function_call(),
@@ -367,7 +477,8 @@ impl Parser {
                     self.curr_span(),
                 )),
             },
-        }
+        }?;
+        Ok(statement.spanned(self.span_from(start)))
     }
 
     // Expects a specific token and consumes it, panicking if the token doesn't match
@@ -392,6 +503,14 @@ impl Parser {
         self.tokens[self.position].span
     }
 
+    // Returns the span from `start` to the end of the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        match self.position.checked_sub(1) {
+            Some(prev) => start.to(self.tokens[prev].span),
+            None => start,
+        }
+    }
+
     fn peek(&self) -> &Token {
         &self.tokens[self.position + 1].node
     }
@@ -419,3 +538,9 @@ impl Display for SyntaxError {
         write!(f, "SyntaxError: {} (at {})", self.message, self.span)
     }
 }
+
+impl From<SyntaxError> for PerryError {
+    fn from(err: SyntaxError) -> Self {
+        PerryError::new(ErrorKind::Syntax, err.message, err.span)
+    }
+}
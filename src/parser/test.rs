@@ -1,8 +1,9 @@
 #![cfg(test)]
 
-use crate::Parser;
-use crate::Tokenizer;
+use crate::common::test::span;
+use crate::parser::Parser;
 use crate::parser::*;
+use crate::tokenizer::Tokenizer;
 
 mod tests {
     use std::panic;
@@ -23,23 +24,14 @@ mod tests {
                     Expression::Literal(LiteralExpression {
                         value: LiteralValue::String("Hello".to_string()),
                     })
-                    .spanned(Span {
-                        line: 1,
-                        column: 16,
-                    }),
+                    .spanned(span(input, (1, 9), (1, 16))),
                     Expression::Literal(LiteralExpression {
                         value: LiteralValue::String("World".to_string()),
                     })
-                    .spanned(Span {
-                        line: 1,
-                        column: 25,
-                    }),
+                    .spanned(span(input, (1, 18), (1, 25))),
                 ],
             })))
-            .spanned(Span {
-                line: 1,
-                column: 27,
-            }),
+            .spanned(span(input, (1, 1), (1, 27))),
         ];
         assert_eq!(ast.unwrap().body, expected);
     }
@@ -73,16 +65,12 @@ mod tests {
                 panic!("Invalid else statement {:?}", statement.as_ref().as_ref());
             };
             walk_if_chain(else_if_statement);
-            return;
         } else {
-            if let Some(first_node) = if_statement.then_body.first() {
-                if let Statement::Expr(expr) = first_node.node.clone() {
-                    if let Expression::FunctionCall(function_call) = expr {
-                        if function_call.callee == "print".to_string() {
-                            return;
-                        }
-                    }
-                };
+            if let Some(first_node) = if_statement.then_body.first()
+                && let Statement::Expr(Expression::FunctionCall(function_call)) = &first_node.node
+                && function_call.callee == "print"
+            {
+                return;
             }
             panic!("Invalid if/else chain structure");
         }
@@ -104,10 +92,7 @@ mod tests {
                 condition: Expression::Literal(LiteralExpression {
                     value: LiteralValue::Bool(true),
                 })
-                .spanned(Span {
-                    line: 2,
-                    column: 20,
-                }),
+                .spanned(span(input, (2, 16), (2, 20))),
                 body: vec![
                     Statement::Expr(Expression::FunctionCall(Box::new(FunctionCall {
                         callee: "println".to_string(),
@@ -115,16 +100,13 @@ mod tests {
                             Expression::Literal(LiteralExpression {
                                 value: LiteralValue::String("Hello".to_string()),
                             })
-                            .spanned(Span {
-                                line: 3,
-                                column: 28,
-                            }),
+                            .spanned(span(input, (3, 21), (3, 28))),
                         ],
                     })))
-                    .spanned(Span { line: 4, column: 9 }),
+                    .spanned(span(input, (3, 13), (3, 30))),
                 ],
             })
-            .spanned(Span { line: 5, column: 9 }),
+            .spanned(span(input, (2, 9), (4, 10))),
         ];
         assert_eq!(ast.unwrap().body, expected);
     }
@@ -142,30 +124,18 @@ mod tests {
                 value: Expression::Literal(LiteralExpression {
                     value: LiteralValue::Bool(true),
                 })
-                .spanned(Span {
-                    line: 1,
-                    column: 25,
-                }),
+                .spanned(span(input, (1, 21), (1, 25))),
                 type_: Type::Bool,
             })
-            .spanned(Span {
-                line: 1,
-                column: 32,
-            }),
+            .spanned(span(input, (1, 1), (1, 26))),
             Statement::VarAssignment(VariableAssignment {
                 name: "testvar".to_string(),
                 value: Expression::Literal(LiteralExpression {
                     value: LiteralValue::Bool(false),
                 })
-                .spanned(Span {
-                    line: 1,
-                    column: 41,
-                }),
+                .spanned(span(input, (1, 36), (1, 41))),
             })
-            .spanned(Span {
-                line: 1,
-                column: 42,
-            }),
+            .spanned(span(input, (1, 26), (1, 42))),
         ];
         assert_eq!(ast.unwrap().body, expected);
     }
@@ -183,6 +153,7 @@ mod tests {
                 params: vec![Parameter {
                     name: "n".to_string(),
                     type_: Type::Int,
+                    span: span(input, (1, 11), (1, 17)),
                 }],
                 type_: Type::Int,
                 body: vec![
@@ -191,26 +162,54 @@ mod tests {
                             Expression::VariableRef(Box::new(VariableRef {
                                 name: "n".to_string(),
                             }))
-                            .spanned(Span {
-                                line: 1,
-                                column: 33,
-                            }),
+                            .spanned(span(input, (1, 32), (1, 33))),
                         ),
                     })
-                    .spanned(Span {
-                        line: 1,
-                        column: 34,
-                    }),
+                    .spanned(span(input, (1, 25), (1, 34))),
                 ],
+                public: false,
             })
-            .spanned(Span {
-                line: 1,
-                column: 35,
-            }),
+            .spanned(span(input, (1, 1), (1, 35))),
         ];
         assert_eq!(ast.unwrap().body, expected);
     }
 
+    #[test]
+    fn ast_imports() {
+        let input = r#"import "lib/math.pry"; import { square, cube } from "math.pry"; pub func f(): void {}"#;
+        let tokens = Tokenizer::new(input.to_string()).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let expected = vec![
+            Statement::Import(ImportStatement {
+                path: "lib/math.pry".to_string(),
+                names: None,
+            })
+            .spanned(span(input, (1, 1), (1, 23))),
+            Statement::Import(ImportStatement {
+                path: "math.pry".to_string(),
+                names: Some(vec!["square".to_string(), "cube".to_string()]),
+            })
+            .spanned(span(input, (1, 24), (1, 64))),
+            Statement::Function(FunctionDecl {
+                name: "f".to_string(),
+                params: vec![],
+                type_: Type::Void,
+                body: vec![],
+                public: true,
+            })
+            .spanned(span(input, (1, 65), (1, 86))),
+        ];
+        assert_eq!(ast.body, expected);
+        for input in [
+            r#"import square from "math.pry";"#,
+            "pub var x: int = 1;",
+            r#"import { } from "m.pry";"#,
+        ] {
+            let tokens = Tokenizer::new(input.to_string()).tokenize().unwrap();
+            assert!(Parser::new(tokens).parse().is_err(), "{}", input);
+        }
+    }
+
     #[test]
     fn ast_function_call() {
         let input = r#"myFunction(true);"#;
@@ -225,16 +224,10 @@ mod tests {
                     Expression::Literal(LiteralExpression {
                         value: LiteralValue::Bool(true),
                     })
-                    .spanned(Span {
-                        line: 1,
-                        column: 16,
-                    }),
+                    .spanned(span(input, (1, 12), (1, 16))),
                 ],
             })))
-            .spanned(Span {
-                line: 1,
-                column: 18,
-            }),
+            .spanned(span(input, (1, 1), (1, 18))),
         ];
         assert_eq!(ast.unwrap().body, expected);
     }
@@ -248,4 +241,93 @@ mod tests {
         let ast = parser.parse();
         assert!(ast.is_err())
     }
+
+    #[test]
+    fn ast_collects_multiple_errors() {
+        let input = r#"print("A";
+        var x: int = ;
+        println("B");
+        y = = 2;"#;
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let errors = parser.parse().unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].span.start.line, 1);
+        assert_eq!(errors[1].span.start.line, 2);
+        assert_eq!(errors[2].span.start.line, 4);
+    }
+
+    #[test]
+    fn ast_partial_program_after_error() {
+        let input = r#"func a(): void {
+            print("A";
+            println("B");
+        }
+        println("C");"#;
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let (program, errors) = parser.parse_partial();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(program.body.len(), 2);
+        let Statement::Function(function) = &program.body[0].node else {
+            panic!("Expected function, got {:?}", program.body[0].node);
+        };
+        assert_eq!(function.body.len(), 1);
+    }
+
+    #[test]
+    fn ast_recovers_past_broken_block() {
+        let input = r#"if (1 > {
+            print("A");
+        }
+        println("B");
+        }
+        println("C");"#;
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let (program, errors) = parser.parse_partial();
+        // The broken if header and the stray closing brace
+        assert_eq!(errors.len(), 2);
+        assert_eq!(program.body.len(), 2);
+    }
+
+    #[test]
+    fn ast_unclosed_block() {
+        let input = r#"while (true) {
+            println("Hello");"#;
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn ast_spans_cover_source_range() {
+        let input = "var x: int = (1 + 2) * 3;\nif (x > 1) {\n} else {\n}";
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let ast = parser.parse().unwrap();
+
+        let Statement::VarDecl(var_decl) = &ast.body[0].node else {
+            panic!("Expected variable declaration, got {:?}", ast.body[0].node);
+        };
+        assert_eq!(ast.body[0].span, span(input, (1, 1), (1, 26)));
+        assert_eq!(var_decl.value.span, span(input, (1, 14), (1, 25)));
+        let Expression::Binary(binary) = &var_decl.value.node else {
+            panic!("Expected binary expression, got {:?}", var_decl.value.node);
+        };
+        assert_eq!(binary.left.span, span(input, (1, 14), (1, 21)));
+
+        let Statement::If(if_statement) = &ast.body[1].node else {
+            panic!("Expected if statement, got {:?}", ast.body[1].node);
+        };
+        assert_eq!(ast.body[1].span, span(input, (2, 1), (4, 2)));
+        assert_eq!(if_statement.condition.span, span(input, (2, 5), (2, 10)));
+        let else_body = if_statement.else_body.as_ref().unwrap();
+        assert_eq!(else_body.span, span(input, (3, 3), (4, 2)));
+    }
 }
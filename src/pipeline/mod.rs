@@ -0,0 +1,174 @@
+pub mod test;
+
+use std::path::{Path, PathBuf};
+
+use crate::analyzer::Analyzer;
+use crate::binary::{self, DecodeError};
+use crate::common::{
+    ast::{Position, Program, Span},
+    error::{ErrorKind, PerryError},
+    token::SpannedToken,
+};
+use crate::interpreter::Interpreter;
+use crate::json::{
+    Json,
+    ast::{FromJson, ToJson},
+};
+use crate::linker::{Linked, Linker};
+use crate::parser::Parser;
+use crate::tokenizer::Tokenizer;
+
+// Source that isn't read from a file imports files relative to the current directory
+pub const INLINE_PATH: &str = "<input>";
+
+// Settings for running a program
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunOptions {
+    pub args: Vec<String>, // Command-line arguments for the program
+    // The directory the file built-ins may access. None, the default, turns them off
+    pub file_root: Option<PathBuf>,
+}
+
+// Runs source code through every stage: tokenizing, parsing, analysis and execution. Returns the
+// program's exit code
+pub fn run_source(source: &str) -> Result<i32, Vec<PerryError>> {
+    run_file(Path::new(INLINE_PATH), source, &RunOptions::default())
+}
+
+// Runs the source of the file at `path` along with the files it imports. Runtime errors in
+// imported files name the file they happened in
+pub fn run_file(path: &Path, source: &str, options: &RunOptions) -> Result<i32, Vec<PerryError>> {
+    let linked = link_file(path, source)?;
+    run_program(&linked.program, options)
+        .map_err(|errors| errors.into_iter().map(|err| linked.locate(err)).collect())
+}
+
+// Runs a syntax tree loaded from JSON, after analyzing it like parsed source
+pub fn run_ast(text: &str, options: &RunOptions) -> Result<i32, Vec<PerryError>> {
+    run_program(&check_ast(text)?, options)
+}
+
+// Runs a program decoded by `load_built`. Its checksum only catches accidental damage, so the
+// program is analyzed again like a tree loaded from JSON before it runs
+pub fn run_built(program: &Program, options: &RunOptions) -> Result<i32, Vec<PerryError>> {
+    analyze(program)?;
+    run_program(program, options)
+}
+
+// Runs a program that has already been analyzed. Returns the code it passed to `exit`, or 0 if it
+// ran to the end
+pub fn run_program(program: &Program, options: &RunOptions) -> Result<i32, Vec<PerryError>> {
+    let mut interpreter = Interpreter::new();
+    let context = interpreter.context_mut();
+    context.args = options.args.clone();
+    context.file_root = options.file_root.clone();
+    interpreter
+        .execute(program)
+        .map_err(|err| vec![err.into()])?;
+    Ok(interpreter.exit_code().unwrap_or(0))
+}
+
+// Tokenizes, parses and analyzes source code without running it
+pub fn check_source(source: &str) -> Result<Program, Vec<PerryError>> {
+    check_file(Path::new(INLINE_PATH), source)
+}
+
+// Checks the source of the file at `path` and the files it imports. Returns the linked program
+pub fn check_file(path: &Path, source: &str) -> Result<Program, Vec<PerryError>> {
+    link_file(path, source).map(|linked| linked.program)
+}
+
+// Loads, checks and links the files imported by the source of the file at `path`
+pub fn link_file(path: &Path, source: &str) -> Result<Linked, Vec<PerryError>> {
+    Linker::new().link(path, source)
+}
+
+// Loads a syntax tree from JSON and analyzes it. A tree built by hand is checked as strictly as
+// one parsed from source, since the interpreter relies on the analyzer
+pub fn check_ast(text: &str) -> Result<Program, Vec<PerryError>> {
+    let program = load_ast(text)?;
+    analyze(&program)?;
+    Ok(program)
+}
+
+fn analyze(program: &Program) -> Result<(), Vec<PerryError>> {
+    let mut analyzer = Analyzer::new();
+    analyzer.analyze(program).map_err(|err| vec![err.into()])
+}
+
+// Checks source code and encodes the analyzed program in the binary format of `binary`. Imported
+// files are linked in, so the result runs on its own
+pub fn build_source(source: &str) -> Result<Vec<u8>, Vec<PerryError>> {
+    build_file(Path::new(INLINE_PATH), source)
+}
+
+// Builds the source of the file at `path`, resolving imports relative to it
+pub fn build_file(path: &Path, source: &str) -> Result<Vec<u8>, Vec<PerryError>> {
+    check_file(path, source).map(|program| binary::encode_program(&program))
+}
+
+// Decodes a program written by `build_source`. It was analyzed before it was written, so it is
+// ready to run
+pub fn load_built(bytes: &[u8]) -> Result<Program, DecodeError> {
+    binary::decode_program(bytes)
+}
+
+// Reads a syntax tree in the JSON format written by `ast_to_json`. Malformed documents are
+// reported as syntax errors; a bad document has no position so it points at the start of the text
+pub fn load_ast(text: &str) -> Result<Program, Vec<PerryError>> {
+    let json = Json::parse(text).map_err(|err| {
+        vec![PerryError::new(
+            ErrorKind::Syntax,
+            format!("invalid JSON: {}", err.message),
+            offset_span(text, err.offset),
+        )]
+    })?;
+    Program::from_json(&json).map_err(|message| {
+        vec![PerryError::new(
+            ErrorKind::Syntax,
+            format!("invalid syntax tree: {}", message),
+            offset_span(text, 0),
+        )]
+    })
+}
+
+// Writes a syntax tree as pretty-printed JSON
+pub fn ast_to_json(program: &Program) -> String {
+    program.to_json().to_pretty()
+}
+
+// Writes tokens as a pretty-printed JSON array
+pub fn tokens_to_json(tokens: &[SpannedToken]) -> String {
+    Json::Array(tokens.iter().map(ToJson::to_json).collect()).to_pretty()
+}
+
+// Tokenizes and parses source code. Every syntax error is returned, not just the first
+pub fn parse_source(source: &str) -> Result<Program, Vec<PerryError>> {
+    let tokens = tokenize_source(source)?;
+    let mut parser = Parser::new(tokens);
+    parser
+        .parse()
+        .map_err(|errors| errors.into_iter().map(PerryError::from).collect())
+}
+
+// Tokenizes source code
+pub fn tokenize_source(source: &str) -> Result<Vec<SpannedToken>, Vec<PerryError>> {
+    let mut tokenizer = Tokenizer::new(source.to_string());
+    tokenizer.tokenize().map_err(|err| vec![err.into()])
+}
+
+// An empty span at a byte offset of the text
+fn offset_span(text: &str, offset: usize) -> Span {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let position = Position {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    };
+    Span {
+        offset: before.len(),
+        len: 0,
+        start: position,
+        end: position,
+    }
+}
@@ -0,0 +1,96 @@
+#![cfg(test)]
+
+use super::*;
+
+mod tests {
+    use crate::common::error::ErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn run_valid_source() {
+        let input = r#"var x: int = 1 + 2;"#;
+        assert_eq!(run_source(input).unwrap(), 0);
+    }
+
+    #[test]
+    fn exit_code_returned() {
+        let program = check_source(r#"if (arg(0) == "fail") { exit(9); }"#).unwrap();
+        let options = |arg: &str| RunOptions {
+            args: vec![arg.to_string()],
+            ..RunOptions::default()
+        };
+        assert_eq!(run_program(&program, &options("fail")).unwrap(), 9);
+        assert_eq!(run_program(&program, &options("ok")).unwrap(), 0);
+    }
+
+    #[test]
+    fn error_kinds_per_stage() {
+        let cases = vec![
+            (r#"var x: int = 1 ? 2;"#, ErrorKind::Lexical),
+            (r#"var x: int = ;"#, ErrorKind::Syntax),
+            (r#"var x: string = 1;"#, ErrorKind::Semantic),
+        ];
+        for (input, kind) in cases {
+            let errors = run_source(input).unwrap_err();
+            assert_eq!(errors[0].kind, kind, "{}", input);
+            assert_eq!(errors[0].code(), kind.code());
+        }
+    }
+
+    #[test]
+    fn runtime_errors_located() {
+        let input = "var zero: int = 0;\nprintln(1 / zero);";
+        let errors = run_source(input).unwrap_err();
+        assert_eq!(errors[0].kind, ErrorKind::Runtime);
+        assert_eq!(errors[0].message, "division by zero");
+        assert_eq!(errors[0].location(), "2:9");
+        let span = errors[0].span;
+        assert_eq!(&input[span.offset..span.offset + span.len], "1 / zero");
+    }
+
+    #[test]
+    // The checksum is only a guard against damage, so a file edited into an invalid program, with
+    // its checksum fixed up, must be rejected before it runs
+    fn built_programs_analyzed() {
+        let built = build_source("var q: int = 1;\nq = 2;\nexit(q);").unwrap();
+        let program = load_built(&built).unwrap();
+        assert_eq!(run_built(&program, &RunOptions::default()).unwrap(), 2);
+        let cases = [
+            (
+                "func f(): int {\n    return 1;\n}\nf = 2;",
+                "cannot assign to function f",
+            ),
+            (
+                "func f(): int {\n    return 1;\n}\nprintln(f);",
+                "f is not a variable",
+            ),
+            ("println(q);", "use of undefined variable q"),
+        ];
+        for (source, message) in cases {
+            let crafted = binary::encode_program(&parse_source(source).unwrap());
+            let program = load_built(&crafted).unwrap();
+            let errors = run_built(&program, &RunOptions::default()).unwrap_err();
+            assert_eq!(errors[0].kind, ErrorKind::Semantic, "{}", source);
+            assert_eq!(errors[0].message, message, "{}", source);
+        }
+    }
+
+    #[test]
+    fn all_syntax_errors_returned() {
+        let input = r#"var x: int = ; var y: int = ;"#;
+        let errors = run_source(input).unwrap_err();
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn signature_note_on_bad_call() {
+        let input = r#"func name(n: int): int { return n; } name(1, 2);"#;
+        let errors = check_source(input).unwrap_err();
+        assert_eq!(
+            errors[0].notes,
+            vec!["expected signature: name(n: int): int"]
+        );
+        assert_eq!(errors[0].labels[0].message, "function declared here");
+    }
+}
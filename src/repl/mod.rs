@@ -0,0 +1,203 @@
+pub mod test;
+
+use std::io::{self, BufRead, Write};
+
+use crate::analyzer::Analyzer;
+use crate::common::{
+    ast::{LiteralValue, Program, SpannedExpression},
+    error::PerryError,
+    token::{SpannedToken, Token},
+};
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::tokenizer::Tokenizer;
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+// An interactive session. Declarations from earlier entries stay visible to later ones
+#[derive(Default)]
+pub struct Repl {
+    analyzer: Analyzer,
+    interpreter: Interpreter,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Creates a session that reads entries from `input` and prints to `output`
+    pub fn with_io(output: Box<dyn Write>, input: Box<dyn BufRead>) -> Self {
+        Self {
+            analyzer: Analyzer::new(),
+            interpreter: Interpreter::with_io(output, input),
+        }
+    }
+
+    // Reads and evaluates entries until the input ends or an entry calls `exit`. Errors are
+    // rendered inline so the session can continue. Returns the code passed to `exit`, or 0
+    pub fn run(&mut self, color: bool) -> io::Result<i32> {
+        let mut entry = String::new();
+        loop {
+            let context = self.interpreter.context_mut();
+            let prompt = if entry.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+            write!(context.output, "{}", prompt)?;
+            context.output.flush()?;
+            let mut line = String::new();
+            if context.input.read_line(&mut line)? == 0 {
+                writeln!(context.output)?;
+                return Ok(0);
+            }
+            entry.push_str(&line);
+            if !is_complete(&entry) {
+                continue;
+            }
+
+            let result = self.eval(&entry);
+            let output = &mut self.interpreter.context_mut().output;
+            match result {
+                Ok(Some(text)) => writeln!(output, "{}", text)?,
+                Ok(None) => {}
+                Err(errors) => {
+                    for err in errors {
+                        write!(output, "{}", err.render(&entry, color))?;
+                    }
+                }
+            }
+            entry.clear();
+            if let Some(code) = self.interpreter.exit_code() {
+                return Ok(code);
+            }
+        }
+    }
+
+    // Evaluates one complete entry: a command, a bare expression or a list of statements. Returns
+    // the text to show for it, if any. Nothing is declared if the entry fails analysis
+    pub fn eval(&mut self, entry: &str) -> Result<Option<String>, Vec<PerryError>> {
+        let trimmed = entry.trim();
+        if trimmed.is_empty() {
+            return Ok(None);
+        }
+        if let Some(command) = trimmed.strip_prefix(':') {
+            return self.eval_command(entry, command);
+        }
+
+        let tokens = tokenize(entry)?;
+        if let Ok(expression) = Parser::new(tokens.clone()).parse_standalone_expression() {
+            return self.eval_expression(&expression);
+        }
+        let program = parse(tokens)?;
+        let mut analyzer = self.analyzer.clone();
+        analyzer.analyze(&program).map_err(|err| vec![err.into()])?;
+        self.interpreter
+            .execute_or_undo(&program)
+            .map_err(|err| vec![err.into()])?;
+        self.analyzer = analyzer;
+        Ok(None)
+    }
+
+    // Runs a `:command`. Its argument is parsed at its original position in the entry so
+    // diagnostics point at the right column
+    fn eval_command(
+        &mut self,
+        entry: &str,
+        command: &str,
+    ) -> Result<Option<String>, Vec<PerryError>> {
+        let name = command.split_whitespace().next().unwrap_or_default();
+        let argument_start = entry.find(':').unwrap_or_default()
+            + 1
+            + command.find(name).unwrap_or_default()
+            + name.len();
+        let argument = format!(
+            "{}{}",
+            blank_out(&entry[..argument_start]),
+            &entry[argument_start..]
+        );
+        match name {
+            "type" => {
+                let expression = parse_expression(&argument)?;
+                let type_ = self
+                    .analyzer
+                    .analyze_expression(&expression)
+                    .map_err(|err| vec![err.into()])?;
+                Ok(Some(type_.to_string()))
+            }
+            "ast" => {
+                let expression = parse_expression(&argument)?;
+                Ok(Some(format!("{:#?}", expression)))
+            }
+            "reset" => {
+                self.analyzer = Analyzer::new();
+                self.interpreter.reset();
+                Ok(None)
+            }
+            _ => Ok(Some(format!(
+                "unknown command :{}, expected :type, :ast or :reset",
+                name
+            ))),
+        }
+    }
+
+    // Analyzes and evaluates a bare expression. Void results print nothing
+    fn eval_expression(
+        &mut self,
+        expression: &SpannedExpression,
+    ) -> Result<Option<String>, Vec<PerryError>> {
+        self.analyzer
+            .analyze_expression(expression)
+            .map_err(|err| vec![err.into()])?;
+        let value = self
+            .interpreter
+            .evaluate(expression)
+            .map_err(|err| vec![err.into()])?;
+        Ok(match value {
+            LiteralValue::Void => None,
+            LiteralValue::String(value) => Some(format!("{:?}", value)),
+            value => Some(value.to_string()),
+        })
+    }
+}
+
+// Returns false while the entry has unclosed braces, meaning more lines should be read. Entries
+// that fail to tokenize count as complete so their errors get reported
+pub fn is_complete(entry: &str) -> bool {
+    let Ok(tokens) = Tokenizer::new(entry.to_string()).tokenize() else {
+        return true;
+    };
+    let depth = tokens.iter().fold(0i32, |depth, token| match token.node {
+        Token::LeftBrace => depth + 1,
+        Token::RightBrace => depth - 1,
+        _ => depth,
+    });
+    depth <= 0
+}
+
+// Replaces everything but line breaks with spaces, keeping positions in the rest of the text
+fn blank_out(text: &str) -> String {
+    text.chars()
+        .map(|ch| if ch == '\n' { ch } else { ' ' })
+        .collect()
+}
+
+fn tokenize(source: &str) -> Result<Vec<SpannedToken>, Vec<PerryError>> {
+    Tokenizer::new(source.to_string())
+        .tokenize()
+        .map_err(|err| vec![err.into()])
+}
+
+fn parse(tokens: Vec<SpannedToken>) -> Result<Program, Vec<PerryError>> {
+    Parser::new(tokens)
+        .parse()
+        .map_err(|errors| errors.into_iter().map(PerryError::from).collect())
+}
+
+fn parse_expression(source: &str) -> Result<SpannedExpression, Vec<PerryError>> {
+    Parser::new(tokenize(source)?)
+        .parse_standalone_expression()
+        .map_err(|err| vec![err.into()])
+}
@@ -0,0 +1,100 @@
+#![cfg(test)]
+
+use super::*;
+use crate::common::error::ErrorKind;
+use crate::interpreter::OutputBuffer;
+
+// Feeds the input to a session and returns everything it printed, prompts included
+pub fn run_session(input: &'static str) -> String {
+    let output = OutputBuffer::new();
+    let mut repl = Repl::with_io(Box::new(output.clone()), Box::new(input.as_bytes()));
+    repl.run(false).unwrap();
+    output.contents()
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_kept_between_entries() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.eval("var x: int = 2;").unwrap(), None);
+        repl.eval("func double(n: int): int { return n * 2; }")
+            .unwrap();
+        assert_eq!(repl.eval("double(x) + 1").unwrap(), Some("5".to_string()));
+        assert_eq!(repl.eval(r#""hi";"#).unwrap(), Some("\"hi\"".to_string()));
+    }
+
+    #[test]
+    fn failed_entry_declares_nothing() {
+        let mut repl = Repl::new();
+        let errors = repl
+            .eval(r#"var x: int = 1; var y: string = 2;"#)
+            .unwrap_err();
+        assert_eq!(errors[0].kind, ErrorKind::Semantic);
+        repl.eval("var x: string = \"a\";").unwrap();
+        assert_eq!(repl.eval("x").unwrap(), Some("\"a\"".to_string()));
+    }
+
+    #[test]
+    fn runtime_failure_declares_nothing() {
+        let mut repl = Repl::new();
+        repl.eval("var x: int = 1;").unwrap();
+        let errors = repl.eval("x = 2; var y: int = 1 / 0;").unwrap_err();
+        assert_eq!(errors[0].kind, ErrorKind::Runtime);
+        assert_eq!(repl.eval("x").unwrap(), Some("1".to_string()));
+        repl.eval("var y: int = 3;").unwrap();
+        assert_eq!(repl.eval("y").unwrap(), Some("3".to_string()));
+    }
+
+    #[test]
+    fn commands() {
+        let mut repl = Repl::new();
+        repl.eval("var flag: bool = true;").unwrap();
+        assert_eq!(repl.eval(":type 1 + 2").unwrap(), Some("int".to_string()));
+        assert_eq!(repl.eval(":type flag").unwrap(), Some("bool".to_string()));
+        assert!(repl.eval(":ast 1 + 2").unwrap().unwrap().contains("Binary"));
+
+        repl.eval(":reset").unwrap();
+        let errors = repl.eval(":type flag").unwrap_err();
+        assert_eq!(errors[0].kind, ErrorKind::Semantic);
+        // The argument keeps its column in the entry
+        assert_eq!(errors[0].span.start.column, 7);
+    }
+
+    #[test]
+    fn incomplete_entries() {
+        assert!(!is_complete("func f(): int {"));
+        assert!(!is_complete("func f(): int {\n if (true) { return 1; }"));
+        assert!(is_complete("func f(): int {\n return 1;\n}"));
+        assert!(is_complete("x = 1;"));
+    }
+
+    #[test]
+    fn session() {
+        let output = run_session(
+            "func add(a: int, b: int): int {\nreturn a + b;\n}\nprintln(add(1, 2));\nadd(2, 2)\nnope\n",
+        );
+        assert!(output.starts_with("> ... ... > 3\n> 4\n> "));
+        assert!(output.contains("use of undefined variable nope"));
+        assert!(output.ends_with("> \n"));
+    }
+
+    #[test]
+    fn loop_scope_does_not_leak() {
+        let mut repl = Repl::new();
+        repl.eval("var i: int = 0; while (i < 3) { var t: int = i; i = i + 1; }")
+            .unwrap();
+        assert_eq!(repl.eval("i").unwrap(), Some("3".to_string()));
+        assert!(repl.eval("t").is_err());
+    }
+
+    #[test]
+    fn exit_ends_session() {
+        let output = OutputBuffer::new();
+        let input = "println(1);\nexit(2)\nprintln(3);\n";
+        let mut repl = Repl::with_io(Box::new(output.clone()), Box::new(input.as_bytes()));
+        assert_eq!(repl.run(false).unwrap(), 2);
+        assert_eq!(output.contents(), "> 1\n> ");
+    }
+}
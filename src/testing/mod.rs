@@ -0,0 +1,129 @@
+pub mod test;
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::common::{
+    ast::Statement,
+    error::{ErrorKind, PerryError},
+};
+use crate::interpreter::{Interpreter, OutputBuffer};
+use crate::linker::Linker;
+
+// Functions whose name starts with this are run by `perry test`
+pub const TEST_PREFIX: &str = "test_";
+
+// The result of running one test, or of a file whose tests couldn't be run
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestOutcome {
+    pub name: String,            // `file::function`, or the file if it failed to load
+    pub output: String,          // Everything the test printed
+    pub errors: Vec<PerryError>, // Why it failed. Each names the file it points into
+}
+
+impl TestOutcome {
+    pub fn passed(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+// Finds the `.pry` files in a directory and its subdirectories, sorted so runs are repeatable. A
+// path that isn't a directory is returned as is
+pub fn find_test_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(find_test_files(&path)?);
+        } else if path.extension().is_some_and(|extension| extension == "pry") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// Runs every top-level `test_*` function in the file at `path`. Each test gets a fresh
+// interpreter that runs the file's top-level code first, so tests can't affect each other
+pub fn run_tests(path: &Path, source: &str) -> Vec<TestOutcome> {
+    let file = path.display().to_string();
+    let linked = match Linker::new().link(path, source) {
+        Ok(linked) => linked,
+        Err(errors) => {
+            return vec![TestOutcome {
+                name: file.clone(),
+                output: String::new(),
+                errors: in_file(errors, &file),
+            }];
+        }
+    };
+    let tests = linked.program.body.iter().filter_map(|statement| {
+        match &statement.node {
+            // Imported functions are renamed, so only the file's own tests match
+            Statement::Function(function) if function.name.starts_with(TEST_PREFIX) => {
+                Some((function, statement.span))
+            }
+            _ => None,
+        }
+    });
+    tests
+        .map(|(function, span)| {
+            let name = format!("{}::{}", file, function.name);
+            if !function.params.is_empty() {
+                let err = PerryError::new(
+                    ErrorKind::Semantic,
+                    format!("test function {} takes parameters", function.name),
+                    span,
+                );
+                return TestOutcome {
+                    name,
+                    output: String::new(),
+                    errors: in_file(vec![err], &file),
+                };
+            }
+            let output = OutputBuffer::new();
+            let mut interpreter =
+                Interpreter::with_io(Box::new(output.clone()), Box::new(io::empty()));
+            // A program exiting from its top-level code never gets to the test
+            let result = match interpreter.execute(&linked.program) {
+                Ok(_) if interpreter.exit_code().is_none() => interpreter
+                    .call_function(&function.name, Vec::new())
+                    .map(|_| ()),
+                result => result,
+            };
+            let mut errors = match result {
+                Ok(_) => Vec::new(),
+                Err(err) => vec![linked.locate(err.into())],
+            };
+            if let Some(code) = interpreter.exit_code().filter(|code| *code != 0) {
+                errors.push(PerryError::new(
+                    ErrorKind::Runtime,
+                    format!("test exited with code {}", code),
+                    span,
+                ));
+            }
+            TestOutcome {
+                name,
+                output: output.contents(),
+                errors: in_file(errors, &file),
+            }
+        })
+        .collect()
+}
+
+// Names `file` in errors pointing into the file being tested, since several files are reported
+// together
+fn in_file(errors: Vec<PerryError>, file: &str) -> Vec<PerryError> {
+    errors
+        .into_iter()
+        .map(|err| match err.file {
+            Some(_) => err,
+            None => err.with_file(file.to_string()),
+        })
+        .collect()
+}
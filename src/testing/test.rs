@@ -0,0 +1,221 @@
+#![cfg(test)]
+
+use super::*;
+use crate::interpreter::test::test_directory;
+
+// Writes `files` under a fresh directory and returns it
+pub fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = test_directory(name);
+    for (path, source) in files {
+        let path = directory.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    directory
+}
+
+// Runs the tests in one file of `directory`, returning each test's name without the directory,
+// whether it passed and its error messages
+pub fn outcomes(directory: &Path, file: &str) -> Vec<(String, bool, Vec<String>)> {
+    let path = directory.join(file);
+    let source = fs::read_to_string(&path).unwrap();
+    let prefix = format!("{}/", directory.display());
+    run_tests(&path, &source)
+        .into_iter()
+        .map(|outcome| {
+            let messages = outcome.errors.iter().map(|err| err.message.clone());
+            (
+                outcome.name.replacen(&prefix, "", 1),
+                outcome.passed(),
+                messages.collect(),
+            )
+        })
+        .collect()
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pass_and_fail() {
+        let directory = write_files(
+            "testing-pass-and-fail",
+            &[(
+                "math.pry",
+                r#"func square(n: int): int {
+    return n * n;
+}
+
+func test_square(): void {
+    assert_eq(square(3), 9);
+}
+
+func test_wrong(): void {
+    println("checking");
+    assert_eq(square(3), 6);
+}
+
+func test_strings(): void {
+    assert_ne("1", "2");
+    assert(len("abc") == 3);
+}
+"#,
+            )],
+        );
+        assert_eq!(
+            outcomes(&directory, "math.pry"),
+            vec![
+                ("math.pry::test_square".to_string(), true, vec![]),
+                (
+                    "math.pry::test_wrong".to_string(),
+                    false,
+                    vec!["assert_eq failed: 9 != 6".to_string()]
+                ),
+                ("math.pry::test_strings".to_string(), true, vec![]),
+            ]
+        );
+        let path = directory.join("math.pry");
+        let results = run_tests(&path, &fs::read_to_string(&path).unwrap());
+        assert_eq!(results[1].output, "checking\n");
+        // The error points at the failing call in the tested file
+        let err = &results[1].errors[0];
+        assert_eq!(err.file, Some(path.display().to_string()));
+        assert_eq!((err.span.start.line, err.span.start.column), (11, 5));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn tests_are_isolated() {
+        let directory = write_files(
+            "testing-isolated",
+            &[(
+                "count.pry",
+                r#"var count: int = 0;
+
+func test_first(): void {
+    count = count + 1;
+    assert_eq(count, 1);
+}
+
+func test_second(): void {
+    count = count + 1;
+    assert_eq(count, 1);
+}
+"#,
+            )],
+        );
+        let results = outcomes(&directory, "count.pry");
+        assert!(
+            results.iter().all(|(_, passed, _)| *passed),
+            "{:?}",
+            results
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn imported_tests_are_not_run() {
+        let directory = write_files(
+            "testing-imports",
+            &[
+                (
+                    "lib.pry",
+                    "pub func test_helper(): void {\n    assert(false);\n}\n",
+                ),
+                (
+                    "main.pry",
+                    "import \"lib.pry\";\n\nfunc test_main(): void {\n    assert(true);\n}\n",
+                ),
+            ],
+        );
+        assert_eq!(
+            outcomes(&directory, "main.pry"),
+            vec![("main.pry::test_main".to_string(), true, vec![])]
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn failures_outside_assertions() {
+        let directory = write_files(
+            "testing-failures",
+            &[
+                (
+                    "bad.pry",
+                    r#"func test_params(n: int): void {
+}
+
+func test_exit(): void {
+    exit(3);
+}
+
+func test_exit_zero(): void {
+    exit(0);
+}
+
+func test_divide(): void {
+    println(1 / 0);
+}
+"#,
+                ),
+                ("broken.pry", "func test_broken(): void {\n    x = 1;\n}\n"),
+            ],
+        );
+        assert_eq!(
+            outcomes(&directory, "bad.pry"),
+            vec![
+                (
+                    "bad.pry::test_params".to_string(),
+                    false,
+                    vec!["test function test_params takes parameters".to_string()]
+                ),
+                (
+                    "bad.pry::test_exit".to_string(),
+                    false,
+                    vec!["test exited with code 3".to_string()]
+                ),
+                ("bad.pry::test_exit_zero".to_string(), true, vec![]),
+                (
+                    "bad.pry::test_divide".to_string(),
+                    false,
+                    vec!["division by zero".to_string()]
+                ),
+            ]
+        );
+        // A file that doesn't analyze fails as a whole
+        let results = outcomes(&directory, "broken.pry");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "broken.pry");
+        assert!(!results[0].1);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn find_files() {
+        let directory = write_files(
+            "testing-find",
+            &[
+                ("b.pry", ""),
+                ("a.pry", ""),
+                ("notes.txt", ""),
+                ("nested/c.pry", ""),
+            ],
+        );
+        let files = find_test_files(&directory).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|file| file.strip_prefix(&directory).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                PathBuf::from("a.pry"),
+                PathBuf::from("b.pry"),
+                PathBuf::from("nested/c.pry")
+            ]
+        );
+        let file = directory.join("a.pry");
+        assert_eq!(find_test_files(&file).unwrap(), vec![file]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
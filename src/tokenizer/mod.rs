@@ -1,15 +1,18 @@
 pub mod test;
 
 use crate::common::{
-    ast::{Span, Spannable},
-    token::{BinaryOperator, SpannedToken, Token, UnaryOperator},
+    ast::{Position, Span, Spannable},
+    error::{ErrorKind, PerryError},
+    token::{
+        BinaryOperator, LosslessToken, SpannedToken, Token, Trivia, TriviaKind, UnaryOperator,
+    },
 };
 use std::fmt::Display;
 
 pub struct Tokenizer {
     input: String,
     position: usize,
-    current_span: Span,
+    current_position: Position, // Position of the next char
 }
 
 impl Tokenizer {
@@ -17,7 +20,7 @@ impl Tokenizer {
         Tokenizer {
             input,
             position: 0,
-            current_span: Span { line: 1, column: 0 },
+            current_position: Position { line: 1, column: 1 },
         }
     }
 
@@ -26,7 +29,7 @@ impl Tokenizer {
         let mut tokens: Vec<SpannedToken> = Vec::new();
 
         loop {
-            let token = self.next_token()?;
+            let (_, token) = self.next_token()?;
             if token.as_ref() == &Token::EOF {
                 tokens.push(token);
                 break;
@@ -37,20 +40,56 @@ impl Tokenizer {
         Ok(tokens)
     }
 
-    // Consumes and returns the next token
-    fn next_token(&mut self) -> Result<SpannedToken, LexicalError> {
-        self.skip_empty();
+    // Tokenizes the input keeping whitespace and comments as trivia. Writing out every token in
+    // order reproduces the input exactly
+    pub fn tokenize_lossless(&mut self) -> Result<Vec<LosslessToken>, LexicalError> {
+        let mut tokens: Vec<LosslessToken> = Vec::new();
 
+        loop {
+            let (mut leading, token) = self.next_token()?;
+            if let Some(previous) = tokens.last_mut() {
+                // Trivia up to and including the first line break trails the previous token
+                let split = leading
+                    .iter()
+                    .position(|trivia| trivia.kind == TriviaKind::Newline)
+                    .map_or(leading.len(), |index| index + 1);
+                let rest = leading.split_off(split);
+                previous.trailing = leading;
+                leading = rest;
+            }
+            let text =
+                self.input[token.span.offset..token.span.offset + token.span.len].to_string();
+            let is_eof = token.as_ref() == &Token::EOF;
+            tokens.push(LosslessToken {
+                leading,
+                token,
+                text,
+                trailing: Vec::new(),
+            });
+            if is_eof {
+                break;
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    // Consumes and returns the next token, along with the trivia skipped before it
+    fn next_token(&mut self) -> Result<(Vec<Trivia>, SpannedToken), LexicalError> {
+        let trivia = self.skip_empty();
+
+        let start_offset = self.position;
+        let start = self.current_position;
         let Some(next_char) = self.peek_next() else {
-            return Ok(Token::EOF.spanned(Span {
-                line: self.current_span.line,
-                column: self.current_span.column + 1,
-            }));
+            return Ok((
+                trivia,
+                Token::EOF.spanned(self.span_from(start_offset, start)),
+            ));
         };
 
         let token = match next_char {
             'a'..='z' | 'A'..='Z' | '_' => self.consume_identifier(),
-            '0'..='9' => self.consume_numeric_literal(),
+            '0'..='9' => self.consume_numeric_literal(start_offset, start)?,
             '"' => self.consume_string_literal(),
             '=' => {
                 self.advance();
@@ -140,15 +179,29 @@ impl Tokenizer {
                 self.advance();
                 return Err(LexicalError::new(
                     format!("invalid symbol: {next_char}"),
-                    self.current_span,
+                    self.span_from(start_offset, start),
                 ));
             }
         };
 
-        Ok(token.spanned(self.current_span))
+        Ok((trivia, token.spanned(self.span_from(start_offset, start))))
+    }
+
+    // Builds the span from the given start up to the current position
+    fn span_from(&self, offset: usize, start: Position) -> Span {
+        Span {
+            offset,
+            len: self.position - offset,
+            start,
+            end: self.current_position,
+        }
     }
 
-    fn consume_numeric_literal(&mut self) -> Token {
+    fn consume_numeric_literal(
+        &mut self,
+        start_offset: usize,
+        start: Position,
+    ) -> Result<Token, LexicalError> {
         let mut literal = String::new();
         while let Some(next) = self.peek_next() {
             match next {
@@ -159,8 +212,13 @@ impl Tokenizer {
                 _ => break,
             }
         }
-        // NOTE: Unwrap is safe here because of the char match
-        Token::NumericLiteral(literal.parse().unwrap())
+        // Only digits were consumed, so parsing fails only when the number doesn't fit in an int
+        literal.parse().map(Token::NumericLiteral).map_err(|_| {
+            LexicalError::new(
+                "integer literal out of range".to_string(),
+                self.span_from(start_offset, start),
+            )
+        })
     }
 
     fn consume_string_literal(&mut self) -> Token {
@@ -194,39 +252,58 @@ impl Tokenizer {
         }
     }
 
-    // Skip over whitespace, newlines, and comments
-    fn skip_empty(&mut self) {
+    // Skip over whitespace, newlines, and comments, returning them as trivia
+    fn skip_empty(&mut self) -> Vec<Trivia> {
+        let mut trivia = Vec::new();
         while let Some(ch) = self.peek_next() {
-            if ch.is_whitespace() {
-                self.advance(); // Skip
-            } else if ch == '/' {
-                match self.peek_next_n(1) {
-                    Some('/') => {
-                        while let Some(ch) = self.peek_next() {
-                            self.advance();
-                            if ch == '\n' {
-                                break;
-                            }
-                        }
-                    }
-                    Some('*') => {
-                        self.advance();
+            let start_offset = self.position;
+            let start = self.current_position;
+            let kind = if self.at_newline() {
+                if ch == '\r' {
+                    self.advance();
+                }
+                self.advance();
+                TriviaKind::Newline
+            } else if ch.is_whitespace() {
+                while self.peek_next().is_some_and(char::is_whitespace) && !self.at_newline() {
+                    self.advance();
+                }
+                TriviaKind::Whitespace
+            } else if ch == '/' && self.peek_next_n(1) == Some('/') {
+                // The line break is not part of the comment
+                while self.peek_next().is_some() && !self.at_newline() {
+                    self.advance();
+                }
+                TriviaKind::LineComment
+            } else if ch == '/' && self.peek_next_n(1) == Some('*') {
+                self.advance();
+                self.advance();
+                while let Some(ch) = self.peek_next() {
+                    self.advance();
+                    if ch == '*' && self.peek_next() == Some('/') {
                         self.advance();
-                        while let Some(ch) = self.peek_next() {
-                            self.advance();
-                            if ch == '*' {
-                                if self.peek_next() == Some('/') {
-                                    self.advance();
-                                    break;
-                                }
-                            }
-                        }
+                        break;
                     }
-                    _ => break,
                 }
+                TriviaKind::BlockComment
             } else {
                 break;
-            }
+            };
+            trivia.push(Trivia {
+                kind,
+                text: self.input[start_offset..self.position].to_string(),
+                span: self.span_from(start_offset, start),
+            });
+        }
+        trivia
+    }
+
+    // Whether the next chars are a `\n` or `\r\n` line break
+    fn at_newline(&self) -> bool {
+        match self.peek_next() {
+            Some('\n') => true,
+            Some('\r') => self.peek_next_n(1) == Some('\n'),
+            _ => false,
         }
     }
 
@@ -244,11 +321,13 @@ impl Tokenizer {
     fn advance(&mut self) -> Option<char> {
         let current_char = self.peek_next();
         // Update position
-        if current_char == Some('\n') {
-            self.current_span.line += 1;
-            self.current_span.column = 0;
-        } else {
-            self.current_span.column += 1;
+        match current_char {
+            Some('\n') => {
+                self.current_position.line += 1;
+                self.current_position.column = 1;
+            }
+            Some(_) => self.current_position.column += 1,
+            None => {}
         }
         self.position += current_char.map_or(0, |c| c.len_utf8());
         current_char
@@ -272,3 +351,9 @@ impl Display for LexicalError {
         write!(f, "LexicalError: {} (at {})", self.message, self.span)
     }
 }
+
+impl From<LexicalError> for PerryError {
+    fn from(err: LexicalError) -> Self {
+        PerryError::new(ErrorKind::Lexical, err.message, err.span)
+    }
+}
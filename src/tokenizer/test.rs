@@ -1,6 +1,7 @@
 #![cfg(test)]
 
 use super::*;
+use crate::common::test::span;
 
 mod tests {
     use super::*;
@@ -11,32 +12,14 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input.to_string());
         let tokens = tokenizer.tokenize().unwrap();
         let expected_tokens = vec![
-            Token::Identifier("print".to_string()).spanned(Span { line: 1, column: 5 }),
-            Token::LeftParen.spanned(Span { line: 1, column: 6 }),
-            Token::StringLiteral("Hello".to_string()).spanned(Span {
-                line: 1,
-                column: 13,
-            }),
-            Token::Comma.spanned(Span {
-                line: 1,
-                column: 14,
-            }),
-            Token::StringLiteral("World!".to_string()).spanned(Span {
-                line: 1,
-                column: 23,
-            }),
-            Token::RightParen.spanned(Span {
-                line: 1,
-                column: 24,
-            }),
-            Token::Semicolon.spanned(Span {
-                line: 1,
-                column: 25,
-            }),
-            Token::EOF.spanned(Span {
-                line: 1,
-                column: 26,
-            }),
+            Token::Identifier("print".to_string()).spanned(span(input, (1, 1), (1, 6))),
+            Token::LeftParen.spanned(span(input, (1, 6), (1, 7))),
+            Token::StringLiteral("Hello".to_string()).spanned(span(input, (1, 7), (1, 14))),
+            Token::Comma.spanned(span(input, (1, 14), (1, 15))),
+            Token::StringLiteral("World!".to_string()).spanned(span(input, (1, 16), (1, 24))),
+            Token::RightParen.spanned(span(input, (1, 24), (1, 25))),
+            Token::Semicolon.spanned(span(input, (1, 25), (1, 26))),
+            Token::EOF.spanned(span(input, (1, 26), (1, 26))),
         ];
         assert_eq!(tokens, expected_tokens);
     }
@@ -48,32 +31,14 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input.to_string());
         let tokens = tokenizer.tokenize().unwrap();
         let expected_tokens = vec![
-            Token::Identifier("print".to_string()).spanned(Span { line: 1, column: 5 }),
-            Token::LeftParen.spanned(Span { line: 1, column: 6 }),
-            Token::StringLiteral("Hello".to_string()).spanned(Span {
-                line: 1,
-                column: 13,
-            }),
-            Token::Comma.spanned(Span {
-                line: 1,
-                column: 14,
-            }),
-            Token::StringLiteral("World!".to_string()).spanned(Span {
-                line: 1,
-                column: 23,
-            }),
-            Token::RightParen.spanned(Span {
-                line: 1,
-                column: 24,
-            }),
-            Token::Semicolon.spanned(Span {
-                line: 1,
-                column: 25,
-            }),
-            Token::EOF.spanned(Span {
-                line: 2,
-                column: 42,
-            }),
+            Token::Identifier("print".to_string()).spanned(span(input, (1, 1), (1, 6))),
+            Token::LeftParen.spanned(span(input, (1, 6), (1, 7))),
+            Token::StringLiteral("Hello".to_string()).spanned(span(input, (1, 7), (1, 14))),
+            Token::Comma.spanned(span(input, (1, 14), (1, 15))),
+            Token::StringLiteral("World!".to_string()).spanned(span(input, (1, 16), (1, 24))),
+            Token::RightParen.spanned(span(input, (1, 24), (1, 25))),
+            Token::Semicolon.spanned(span(input, (1, 25), (1, 26))),
+            Token::EOF.spanned(span(input, (2, 42), (2, 42))),
         ];
         assert_eq!(tokens, expected_tokens);
     }
@@ -85,32 +50,14 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input.to_string());
         let tokens = tokenizer.tokenize().unwrap();
         let expected_tokens = vec![
-            Token::Identifier("print".to_string()).spanned(Span { line: 1, column: 5 }),
-            Token::LeftParen.spanned(Span { line: 1, column: 6 }),
-            Token::StringLiteral("Hello".to_string()).spanned(Span {
-                line: 1,
-                column: 13,
-            }),
-            Token::Comma.spanned(Span {
-                line: 1,
-                column: 14,
-            }),
-            Token::StringLiteral("World!".to_string()).spanned(Span {
-                line: 1,
-                column: 34,
-            }),
-            Token::RightParen.spanned(Span {
-                line: 1,
-                column: 35,
-            }),
-            Token::Semicolon.spanned(Span {
-                line: 1,
-                column: 36,
-            }),
-            Token::EOF.spanned(Span {
-                line: 2,
-                column: 42,
-            }),
+            Token::Identifier("print".to_string()).spanned(span(input, (1, 1), (1, 6))),
+            Token::LeftParen.spanned(span(input, (1, 6), (1, 7))),
+            Token::StringLiteral("Hello".to_string()).spanned(span(input, (1, 7), (1, 14))),
+            Token::Comma.spanned(span(input, (1, 14), (1, 15))),
+            Token::StringLiteral("World!".to_string()).spanned(span(input, (1, 27), (1, 35))),
+            Token::RightParen.spanned(span(input, (1, 35), (1, 36))),
+            Token::Semicolon.spanned(span(input, (1, 36), (1, 37))),
+            Token::EOF.spanned(span(input, (2, 42), (2, 42))),
         ];
 
         assert_eq!(tokens, expected_tokens);
@@ -122,53 +69,20 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input.to_string());
         let tokens = tokenizer.tokenize().unwrap();
         let expected_tokens = vec![
-            Token::Identifier("println".to_string()).spanned(Span { line: 1, column: 7 }),
-            Token::LeftParen.spanned(Span { line: 1, column: 8 }),
-            Token::LeftParen.spanned(Span { line: 1, column: 9 }),
-            Token::NumericLiteral(1).spanned(Span {
-                line: 1,
-                column: 10,
-            }),
-            Token::BinaryOperator(BinaryOperator::Add).spanned(Span {
-                line: 1,
-                column: 12,
-            }),
-            Token::NumericLiteral(2).spanned(Span {
-                line: 1,
-                column: 14,
-            }),
-            Token::RightParen.spanned(Span {
-                line: 1,
-                column: 15,
-            }),
-            Token::BinaryOperator(BinaryOperator::Multiply).spanned(Span {
-                line: 1,
-                column: 17,
-            }),
-            Token::NumericLiteral(3).spanned(Span {
-                line: 1,
-                column: 19,
-            }),
-            Token::BinaryOperator(BinaryOperator::Divide).spanned(Span {
-                line: 1,
-                column: 21,
-            }),
-            Token::NumericLiteral(4).spanned(Span {
-                line: 1,
-                column: 23,
-            }),
-            Token::RightParen.spanned(Span {
-                line: 1,
-                column: 24,
-            }),
-            Token::Semicolon.spanned(Span {
-                line: 1,
-                column: 25,
-            }),
-            Token::EOF.spanned(Span {
-                line: 1,
-                column: 26,
-            }),
+            Token::Identifier("println".to_string()).spanned(span(input, (1, 1), (1, 8))),
+            Token::LeftParen.spanned(span(input, (1, 8), (1, 9))),
+            Token::LeftParen.spanned(span(input, (1, 9), (1, 10))),
+            Token::NumericLiteral(1).spanned(span(input, (1, 10), (1, 11))),
+            Token::BinaryOperator(BinaryOperator::Add).spanned(span(input, (1, 12), (1, 13))),
+            Token::NumericLiteral(2).spanned(span(input, (1, 14), (1, 15))),
+            Token::RightParen.spanned(span(input, (1, 15), (1, 16))),
+            Token::BinaryOperator(BinaryOperator::Multiply).spanned(span(input, (1, 17), (1, 18))),
+            Token::NumericLiteral(3).spanned(span(input, (1, 19), (1, 20))),
+            Token::BinaryOperator(BinaryOperator::Divide).spanned(span(input, (1, 21), (1, 22))),
+            Token::NumericLiteral(4).spanned(span(input, (1, 23), (1, 24))),
+            Token::RightParen.spanned(span(input, (1, 24), (1, 25))),
+            Token::Semicolon.spanned(span(input, (1, 25), (1, 26))),
+            Token::EOF.spanned(span(input, (1, 26), (1, 26))),
         ];
         assert_eq!(tokens, expected_tokens);
     }
@@ -179,67 +93,26 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input.to_string());
         let tokens = tokenizer.tokenize().unwrap();
         let expected_tokens = vec![
-            Token::Identifier("var".to_string()).spanned(Span { line: 1, column: 3 }),
-            Token::Identifier("testvar".to_string()).spanned(Span {
-                line: 1,
-                column: 11,
-            }),
-            Token::Colon.spanned(Span {
-                line: 1,
-                column: 12,
-            }),
-            Token::Identifier("bool".to_string()).spanned(Span {
-                line: 1,
-                column: 17,
-            }),
-            Token::Assign.spanned(Span {
-                line: 1,
-                column: 19,
-            }),
-            Token::LeftParen.spanned(Span {
-                line: 1,
-                column: 21,
-            }),
-            Token::NumericLiteral(5).spanned(Span {
-                line: 1,
-                column: 22,
-            }),
-            Token::BinaryOperator(BinaryOperator::GreaterThanOrEqual).spanned(Span {
-                line: 1,
-                column: 25,
-            }),
-            Token::LeftParen.spanned(Span {
-                line: 1,
-                column: 27,
-            }),
-            Token::NumericLiteral(4).spanned(Span {
-                line: 1,
-                column: 28,
-            }),
-            Token::BinaryOperator(BinaryOperator::Subtract).spanned(Span {
-                line: 1,
-                column: 30,
-            }),
-            Token::NumericLiteral(3).spanned(Span {
-                line: 1,
-                column: 32,
-            }),
-            Token::RightParen.spanned(Span {
-                line: 1,
-                column: 33,
-            }),
-            Token::RightParen.spanned(Span {
-                line: 1,
-                column: 34,
-            }),
-            Token::Semicolon.spanned(Span {
-                line: 1,
-                column: 35,
-            }),
-            Token::EOF.spanned(Span {
-                line: 1,
-                column: 36,
-            }),
+            Token::Identifier("var".to_string()).spanned(span(input, (1, 1), (1, 4))),
+            Token::Identifier("testvar".to_string()).spanned(span(input, (1, 5), (1, 12))),
+            Token::Colon.spanned(span(input, (1, 12), (1, 13))),
+            Token::Identifier("bool".to_string()).spanned(span(input, (1, 14), (1, 18))),
+            Token::Assign.spanned(span(input, (1, 19), (1, 20))),
+            Token::LeftParen.spanned(span(input, (1, 21), (1, 22))),
+            Token::NumericLiteral(5).spanned(span(input, (1, 22), (1, 23))),
+            Token::BinaryOperator(BinaryOperator::GreaterThanOrEqual).spanned(span(
+                input,
+                (1, 24),
+                (1, 26),
+            )),
+            Token::LeftParen.spanned(span(input, (1, 27), (1, 28))),
+            Token::NumericLiteral(4).spanned(span(input, (1, 28), (1, 29))),
+            Token::BinaryOperator(BinaryOperator::Subtract).spanned(span(input, (1, 30), (1, 31))),
+            Token::NumericLiteral(3).spanned(span(input, (1, 32), (1, 33))),
+            Token::RightParen.spanned(span(input, (1, 33), (1, 34))),
+            Token::RightParen.spanned(span(input, (1, 34), (1, 35))),
+            Token::Semicolon.spanned(span(input, (1, 35), (1, 36))),
+            Token::EOF.spanned(span(input, (1, 36), (1, 36))),
         ];
         assert_eq!(tokens, expected_tokens);
     }
@@ -250,35 +123,14 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input.to_string());
         let tokens = tokenizer.tokenize().unwrap();
         let expected_tokens = vec![
-            Token::Identifier("var".to_string()).spanned(Span { line: 1, column: 3 }),
-            Token::Identifier("testvar".to_string()).spanned(Span {
-                line: 1,
-                column: 11,
-            }),
-            Token::Colon.spanned(Span {
-                line: 1,
-                column: 12,
-            }),
-            Token::Identifier("bool".to_string()).spanned(Span {
-                line: 1,
-                column: 17,
-            }),
-            Token::Assign.spanned(Span {
-                line: 1,
-                column: 19,
-            }),
-            Token::Identifier("testvar".to_string()).spanned(Span {
-                line: 1,
-                column: 27,
-            }),
-            Token::Semicolon.spanned(Span {
-                line: 1,
-                column: 28,
-            }),
-            Token::EOF.spanned(Span {
-                line: 1,
-                column: 29,
-            }),
+            Token::Identifier("var".to_string()).spanned(span(input, (1, 1), (1, 4))),
+            Token::Identifier("testvar".to_string()).spanned(span(input, (1, 5), (1, 12))),
+            Token::Colon.spanned(span(input, (1, 12), (1, 13))),
+            Token::Identifier("bool".to_string()).spanned(span(input, (1, 14), (1, 18))),
+            Token::Assign.spanned(span(input, (1, 19), (1, 20))),
+            Token::Identifier("testvar".to_string()).spanned(span(input, (1, 21), (1, 28))),
+            Token::Semicolon.spanned(span(input, (1, 28), (1, 29))),
+            Token::EOF.spanned(span(input, (1, 29), (1, 29))),
         ];
         assert_eq!(tokens, expected_tokens);
     }
@@ -288,63 +140,21 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input.to_string());
         let tokens = tokenizer.tokenize().unwrap();
         let expected_tokens = vec![
-            Token::Identifier("var".to_string()).spanned(Span { line: 1, column: 3 }),
-            Token::Identifier("testvar".to_string()).spanned(Span {
-                line: 1,
-                column: 11,
-            }),
-            Token::Colon.spanned(Span {
-                line: 1,
-                column: 12,
-            }),
-            Token::Identifier("bool".to_string()).spanned(Span {
-                line: 1,
-                column: 17,
-            }),
-            Token::Assign.spanned(Span {
-                line: 1,
-                column: 19,
-            }),
-            Token::BooleanLiteral(true).spanned(Span {
-                line: 1,
-                column: 24,
-            }),
-            Token::Semicolon.spanned(Span {
-                line: 1,
-                column: 25,
-            }),
-            Token::Identifier("var".to_string()).spanned(Span {
-                line: 1,
-                column: 29,
-            }),
-            Token::Identifier("testvar".to_string()).spanned(Span {
-                line: 1,
-                column: 37,
-            }),
-            Token::Colon.spanned(Span {
-                line: 1,
-                column: 38,
-            }),
-            Token::Identifier("bool".to_string()).spanned(Span {
-                line: 1,
-                column: 43,
-            }),
-            Token::Assign.spanned(Span {
-                line: 1,
-                column: 45,
-            }),
-            Token::BooleanLiteral(false).spanned(Span {
-                line: 1,
-                column: 51,
-            }),
-            Token::Semicolon.spanned(Span {
-                line: 1,
-                column: 52,
-            }),
-            Token::EOF.spanned(Span {
-                line: 1,
-                column: 53,
-            }),
+            Token::Identifier("var".to_string()).spanned(span(input, (1, 1), (1, 4))),
+            Token::Identifier("testvar".to_string()).spanned(span(input, (1, 5), (1, 12))),
+            Token::Colon.spanned(span(input, (1, 12), (1, 13))),
+            Token::Identifier("bool".to_string()).spanned(span(input, (1, 14), (1, 18))),
+            Token::Assign.spanned(span(input, (1, 19), (1, 20))),
+            Token::BooleanLiteral(true).spanned(span(input, (1, 21), (1, 25))),
+            Token::Semicolon.spanned(span(input, (1, 25), (1, 26))),
+            Token::Identifier("var".to_string()).spanned(span(input, (1, 27), (1, 30))),
+            Token::Identifier("testvar".to_string()).spanned(span(input, (1, 31), (1, 38))),
+            Token::Colon.spanned(span(input, (1, 38), (1, 39))),
+            Token::Identifier("bool".to_string()).spanned(span(input, (1, 40), (1, 44))),
+            Token::Assign.spanned(span(input, (1, 45), (1, 46))),
+            Token::BooleanLiteral(false).spanned(span(input, (1, 47), (1, 52))),
+            Token::Semicolon.spanned(span(input, (1, 52), (1, 53))),
+            Token::EOF.spanned(span(input, (1, 53), (1, 53))),
         ];
         assert_eq!(tokens, expected_tokens);
     }
@@ -357,104 +167,44 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input.to_string());
         let tokens = tokenizer.tokenize().unwrap();
         let expected = vec![
-            Token::Identifier("ab1".to_string()).spanned(Span { line: 1, column: 3 }),
-            Token::StringLiteral("string".to_string()).spanned(Span {
-                line: 1,
-                column: 11,
-            }),
-            Token::NumericLiteral(123).spanned(Span {
-                line: 1,
-                column: 14,
-            }),
-            Token::BooleanLiteral(false).spanned(Span {
-                line: 1,
-                column: 20,
-            }),
-            Token::BinaryOperator(BinaryOperator::Add).spanned(Span {
-                line: 1,
-                column: 22,
-            }),
-            Token::BinaryOperator(BinaryOperator::Subtract).spanned(Span {
-                line: 1,
-                column: 24,
-            }),
-            Token::BinaryOperator(BinaryOperator::Multiply).spanned(Span {
-                line: 1,
-                column: 26,
-            }),
-            Token::BinaryOperator(BinaryOperator::Divide).spanned(Span {
-                line: 1,
-                column: 28,
-            }),
-            Token::BinaryOperator(BinaryOperator::Modulo).spanned(Span {
-                line: 1,
-                column: 30,
-            }),
-            Token::BinaryOperator(BinaryOperator::NotEqual).spanned(Span {
-                line: 1,
-                column: 33,
-            }),
-            Token::BinaryOperator(BinaryOperator::Equal).spanned(Span {
-                line: 1,
-                column: 36,
-            }),
-            Token::BinaryOperator(BinaryOperator::GreaterThan).spanned(Span {
-                line: 1,
-                column: 38,
-            }),
-            Token::BinaryOperator(BinaryOperator::LessThan).spanned(Span {
-                line: 1,
-                column: 40,
-            }),
-            Token::BinaryOperator(BinaryOperator::GreaterThanOrEqual).spanned(Span {
-                line: 1,
-                column: 43,
-            }),
-            Token::BinaryOperator(BinaryOperator::LessThanOrEqual).spanned(Span {
-                line: 1,
-                column: 46,
-            }),
-            Token::UnaryOperator(UnaryOperator::Not).spanned(Span { line: 2, column: 9 }),
-            Token::Comma.spanned(Span {
-                line: 2,
-                column: 11,
-            }),
-            Token::Assign.spanned(Span {
-                line: 2,
-                column: 13,
-            }),
-            Token::LeftParen.spanned(Span {
-                line: 2,
-                column: 15,
-            }),
-            Token::RightParen.spanned(Span {
-                line: 2,
-                column: 16,
-            }),
-            Token::LeftBrace.spanned(Span {
-                line: 2,
-                column: 18,
-            }),
-            Token::RightBrace.spanned(Span {
-                line: 2,
-                column: 19,
-            }),
-            Token::Semicolon.spanned(Span {
-                line: 2,
-                column: 21,
-            }),
-            Token::Colon.spanned(Span {
-                line: 2,
-                column: 23,
-            }),
-            Token::Assign.spanned(Span {
-                line: 2,
-                column: 25,
-            }),
-            Token::EOF.spanned(Span {
-                line: 2,
-                column: 26,
-            }),
+            Token::Identifier("ab1".to_string()).spanned(span(&input, (1, 1), (1, 4))),
+            Token::StringLiteral("string".to_string()).spanned(span(&input, (1, 4), (1, 12))),
+            Token::NumericLiteral(123).spanned(span(&input, (1, 12), (1, 15))),
+            Token::BooleanLiteral(false).spanned(span(&input, (1, 16), (1, 21))),
+            Token::BinaryOperator(BinaryOperator::Add).spanned(span(&input, (1, 22), (1, 23))),
+            Token::BinaryOperator(BinaryOperator::Subtract).spanned(span(&input, (1, 24), (1, 25))),
+            Token::BinaryOperator(BinaryOperator::Multiply).spanned(span(&input, (1, 26), (1, 27))),
+            Token::BinaryOperator(BinaryOperator::Divide).spanned(span(&input, (1, 28), (1, 29))),
+            Token::BinaryOperator(BinaryOperator::Modulo).spanned(span(&input, (1, 30), (1, 31))),
+            Token::BinaryOperator(BinaryOperator::NotEqual).spanned(span(&input, (1, 32), (1, 34))),
+            Token::BinaryOperator(BinaryOperator::Equal).spanned(span(&input, (1, 35), (1, 37))),
+            Token::BinaryOperator(BinaryOperator::GreaterThan).spanned(span(
+                &input,
+                (1, 38),
+                (1, 39),
+            )),
+            Token::BinaryOperator(BinaryOperator::LessThan).spanned(span(&input, (1, 40), (1, 41))),
+            Token::BinaryOperator(BinaryOperator::GreaterThanOrEqual).spanned(span(
+                &input,
+                (1, 42),
+                (1, 44),
+            )),
+            Token::BinaryOperator(BinaryOperator::LessThanOrEqual).spanned(span(
+                &input,
+                (1, 45),
+                (1, 47),
+            )),
+            Token::UnaryOperator(UnaryOperator::Not).spanned(span(&input, (2, 9), (2, 10))),
+            Token::Comma.spanned(span(&input, (2, 11), (2, 12))),
+            Token::Assign.spanned(span(&input, (2, 13), (2, 14))),
+            Token::LeftParen.spanned(span(&input, (2, 15), (2, 16))),
+            Token::RightParen.spanned(span(&input, (2, 16), (2, 17))),
+            Token::LeftBrace.spanned(span(&input, (2, 18), (2, 19))),
+            Token::RightBrace.spanned(span(&input, (2, 19), (2, 20))),
+            Token::Semicolon.spanned(span(&input, (2, 21), (2, 22))),
+            Token::Colon.spanned(span(&input, (2, 23), (2, 24))),
+            Token::Assign.spanned(span(&input, (2, 25), (2, 26))),
+            Token::EOF.spanned(span(&input, (2, 26), (2, 26))),
         ];
         assert_eq!(tokens, expected);
     }
@@ -466,4 +216,101 @@ mod tests {
         let tokens = tokenizer.tokenize();
         assert!(tokens.is_err());
     }
+
+    #[test]
+    fn integer_literal_out_of_range() {
+        let input = "var x: int = 99999999999;";
+        let err = Tokenizer::new(input.to_string()).tokenize().unwrap_err();
+        assert_eq!(err.message, "integer literal out of range");
+        assert_eq!(err.span, span(input, (1, 14), (1, 25)));
+        // The largest int still fits
+        let tokens = Tokenizer::new("2147483647".to_string()).tokenize().unwrap();
+        assert_eq!(tokens[0].node, Token::NumericLiteral(2147483647));
+    }
+
+    #[test]
+    fn trivia_attached_to_tokens() {
+        let input = "x = 1; // one\n\n/* two */ y";
+        let mut tokenizer = Tokenizer::new(input.to_string());
+        let tokens = tokenizer.tokenize_lossless().unwrap();
+        let semicolon = &tokens[3];
+        assert_eq!(semicolon.text, ";");
+        let trailing: Vec<(TriviaKind, &str)> = semicolon
+            .trailing
+            .iter()
+            .map(|trivia| (trivia.kind, trivia.text.as_str()))
+            .collect();
+        assert_eq!(
+            trailing,
+            vec![
+                (TriviaKind::Whitespace, " "),
+                (TriviaKind::LineComment, "// one"),
+                (TriviaKind::Newline, "\n"),
+            ]
+        );
+        let y = &tokens[4];
+        let leading: Vec<TriviaKind> = y.leading.iter().map(|trivia| trivia.kind).collect();
+        assert_eq!(
+            leading,
+            vec![
+                TriviaKind::Newline,
+                TriviaKind::BlockComment,
+                TriviaKind::Whitespace
+            ]
+        );
+        assert_eq!(y.leading[1].span, span(input, (3, 1), (3, 10)));
+        assert_eq!(tokens[5].token.node, Token::EOF);
+    }
+
+    #[test]
+    // Every comment is kept as trivia, in source order, with its span
+    fn comments_collected() {
+        let input = "x = 1; // one\n/* two\n*/ y = 2;";
+        let tokens = Tokenizer::new(input.to_string())
+            .tokenize_lossless()
+            .unwrap();
+        let comments: Vec<(&str, Span)> = tokens
+            .iter()
+            .flat_map(|token| token.leading.iter().chain(&token.trailing))
+            .filter(|trivia| trivia.kind.is_comment())
+            .map(|trivia| (trivia.text.as_str(), trivia.span))
+            .collect();
+        assert_eq!(
+            comments,
+            vec![
+                ("// one", span(input, (1, 8), (1, 14))),
+                ("/* two\n*/", span(input, (2, 1), (3, 3))),
+            ]
+        );
+    }
+
+    #[test]
+    fn lossless_round_trip() {
+        let mut inputs = vec![
+            "\t var x: int = 1;\r\n  // c\r\n/* a\n b */\n\n  x = x +\t2;  \n".to_string(),
+            String::new(),
+            "   ".to_string(),
+        ];
+        for entry in std::fs::read_dir("working_examples").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "pry") {
+                inputs.push(std::fs::read_to_string(path).unwrap());
+            }
+        }
+        for input in inputs {
+            let mut tokenizer = Tokenizer::new(input.clone());
+            let tokens = tokenizer.tokenize_lossless().unwrap();
+            let output: String = tokens.iter().map(|token| token.to_string()).collect();
+            assert_eq!(output, input);
+        }
+    }
+
+    #[test]
+    fn lossless_matches_tokenize() {
+        let input = std::fs::read_to_string("working_examples/complex.pry").unwrap();
+        let tokens = Tokenizer::new(input.clone()).tokenize().unwrap();
+        let lossless = Tokenizer::new(input).tokenize_lossless().unwrap();
+        let lossless: Vec<SpannedToken> = lossless.into_iter().map(|token| token.token).collect();
+        assert_eq!(tokens, lossless);
+    }
 }
@@ -0,0 +1,108 @@
+use std::fmt::{Display, Write};
+
+use crate::common::{
+    ast::{LiteralValue, Span},
+    token::BinaryOperator,
+};
+
+// A single VM instruction. Operands index into the tables of the enclosing `Bytecode`, so every
+// instruction fits in 8 bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Constant(u32),                        // Pushes a value from the constant pool
+    Load { hops: u16, slot: u16 },        // Pushes a local of the frame `hops` static links up
+    Store { hops: u16, slot: u16 },       // Pops a value into a local
+    Pop,                                  // Discards the top of the stack
+    Binary(BinaryOperator),               // Pops the right then the left operand, pushes the result
+    Jump(u32),                            // Continues at an instruction index
+    JumpIfFalse(u32),                     // Pops a condition and jumps unless it is `true`
+    Call { function: u32, hops: u16 },    // Calls a compiled function with its arguments on top
+    CallBuiltIn { name: u32, argc: u16 }, // Calls a built-in by its index in the name table
+    Return,                               // Pops the return value and leaves the frame
+}
+
+// A compiled function. Parameters are the first locals; the rest start out as void
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub arity: u16,
+    pub locals: u16,
+    pub code: Vec<Instruction>,
+    pub spans: Vec<Span>, // Source span of each instruction, for runtime errors
+}
+
+impl Function {
+    pub fn new(name: String, arity: u16) -> Self {
+        Self {
+            name,
+            arity,
+            locals: arity,
+            code: Vec::new(),
+            spans: Vec::new(),
+        }
+    }
+}
+
+// A whole compiled program. Function 0 is the top level of the program
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Bytecode {
+    pub constants: Vec<LiteralValue>,
+    pub names: Vec<String>, // Names of the built-ins the program calls
+    pub functions: Vec<Function>,
+}
+
+impl Bytecode {
+    // Lists every function's instructions with their operands resolved, one per line
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+        for (index, function) in self.functions.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "fn {} {} (arity {}, locals {})",
+                index, function.name, function.arity, function.locals
+            );
+            for (offset, instruction) in function.code.iter().enumerate() {
+                let _ = write!(out, "  {:04} ", offset);
+                let _ = match *instruction {
+                    Instruction::Constant(index) => match &self.constants[index as usize] {
+                        LiteralValue::String(value) => writeln!(out, "Constant {:?}", value),
+                        value => writeln!(out, "Constant {}", value),
+                    },
+                    Instruction::Call { function, hops } => writeln!(
+                        out,
+                        "Call {} hops {}",
+                        self.functions[function as usize].name, hops
+                    ),
+                    Instruction::CallBuiltIn { name, argc } => {
+                        writeln!(
+                            out,
+                            "CallBuiltIn {} argc {}",
+                            self.names[name as usize], argc
+                        )
+                    }
+                    instruction => writeln!(out, "{}", instruction),
+                };
+            }
+        }
+        out
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Constant(index) => write!(f, "Constant #{}", index),
+            Instruction::Load { hops, slot } => write!(f, "Load {} hops {}", slot, hops),
+            Instruction::Store { hops, slot } => write!(f, "Store {} hops {}", slot, hops),
+            Instruction::Pop => write!(f, "Pop"),
+            Instruction::Binary(operator) => write!(f, "Binary {}", operator),
+            Instruction::Jump(target) => write!(f, "Jump {:04}", target),
+            Instruction::JumpIfFalse(target) => write!(f, "JumpIfFalse {:04}", target),
+            Instruction::Call { function, hops } => write!(f, "Call #{} hops {}", function, hops),
+            Instruction::CallBuiltIn { name, argc } => {
+                write!(f, "CallBuiltIn #{} argc {}", name, argc)
+            }
+            Instruction::Return => write!(f, "Return"),
+        }
+    }
+}
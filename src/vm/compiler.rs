@@ -0,0 +1,299 @@
+use crate::common::ast::{
+    Expression, FunctionDecl, LiteralValue, Program, Span, SpannedExpression, SpannedStatement,
+    Statement,
+};
+use crate::interpreter::RuntimeError;
+
+use super::bytecode::{Bytecode, Function, Instruction};
+
+// Compiles an analyzed program. Names are resolved here, so the VM never looks anything up by
+// name except built-ins. Only names the analyzer would have rejected fail to compile
+pub fn compile(program: &Program) -> Result<Bytecode, RuntimeError> {
+    let mut compiler = Compiler {
+        bytecode: Bytecode::default(),
+        scopes: Vec::new(),
+    };
+    compiler
+        .bytecode
+        .functions
+        .push(Function::new("<main>".to_string(), 0));
+    compiler.scopes.push(FunctionScope {
+        function: 0,
+        blocks: vec![Vec::new()],
+    });
+    compiler.statements(&program.body)?;
+    compiler.implicit_return(Span::default())?;
+    Ok(compiler.bytecode)
+}
+
+#[derive(Clone, Copy)]
+enum Binding {
+    Local(u16),
+    Function(u32),
+}
+
+// The names visible in a function being compiled, one list per block, innermost last
+struct FunctionScope {
+    function: usize,
+    blocks: Vec<Vec<(String, Binding)>>,
+}
+
+struct Compiler {
+    bytecode: Bytecode,
+    scopes: Vec<FunctionScope>, // Lexically enclosing functions, innermost last
+}
+
+impl Compiler {
+    fn statements(&mut self, statements: &[SpannedStatement]) -> Result<(), RuntimeError> {
+        for statement in statements {
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    // Compiles statements in a block of their own, so their declarations end with it
+    fn block(&mut self, statements: &[SpannedStatement]) -> Result<(), RuntimeError> {
+        self.scope().blocks.push(Vec::new());
+        let result = self.statements(statements);
+        self.scope().blocks.pop();
+        result
+    }
+
+    fn statement(&mut self, statement: &SpannedStatement) -> Result<(), RuntimeError> {
+        let span = statement.span;
+        match &statement.node {
+            Statement::Function(func) => self.function(func, span)?,
+            Statement::VarDecl(var_decl) => {
+                // The value is compiled first, so it cannot see the variable it initializes
+                self.expression(&var_decl.value)?;
+                let slot = self.declare_local(&var_decl.name, span)?;
+                self.emit(Instruction::Store { hops: 0, slot }, span);
+            }
+            Statement::VarAssignment(var_assignment) => {
+                self.expression(&var_assignment.value)?;
+                let Some((hops, Binding::Local(slot))) = self.resolve(&var_assignment.name) else {
+                    return Err(RuntimeError::new(
+                        format!("cannot assign undeclared variable {}", var_assignment.name),
+                        span,
+                    ));
+                };
+                self.emit(Instruction::Store { hops, slot }, span);
+            }
+            Statement::If(if_statement) => {
+                self.expression(&if_statement.condition)?;
+                let skip_then = self.emit(Instruction::JumpIfFalse(0), span);
+                self.block(&if_statement.then_body)?;
+                let skip_else = self.emit(Instruction::Jump(0), span);
+                self.patch(skip_then)?;
+                if let Some(else_body) = &if_statement.else_body {
+                    self.block(std::slice::from_ref(else_body))?;
+                }
+                self.patch(skip_else)?;
+            }
+            Statement::Loop(loop_statement) => {
+                let start = self.next_offset()?;
+                self.expression(&loop_statement.condition)?;
+                let exit = self.emit(Instruction::JumpIfFalse(0), span);
+                self.block(&loop_statement.body)?;
+                self.emit(Instruction::Jump(start), span);
+                self.patch(exit)?;
+            }
+            Statement::Return(return_statement) => {
+                match &return_statement.value {
+                    Some(value) => self.expression(value)?,
+                    None => {
+                        let void = self.constant(LiteralValue::Void, span)?;
+                        self.emit(void, span);
+                    }
+                }
+                self.emit(Instruction::Return, span);
+            }
+            Statement::Expr(expression) => {
+                self.expression_at(expression, span)?;
+                self.emit(Instruction::Pop, span);
+            }
+            // Imports are resolved by linking files together before they are compiled
+            Statement::Import(_) => {}
+        }
+        Ok(())
+    }
+
+    // Compiles a function into its own entry of the function table. Its name is bound before the
+    // body is compiled so that it can call itself
+    fn function(&mut self, func: &FunctionDecl, span: Span) -> Result<(), RuntimeError> {
+        let index = u32::try_from(self.bytecode.functions.len())
+            .map_err(|_| RuntimeError::new("too many functions".to_string(), span))?;
+        let arity = u16::try_from(func.params.len())
+            .map_err(|_| RuntimeError::new("too many parameters".to_string(), span))?;
+        self.bytecode
+            .functions
+            .push(Function::new(func.name.clone(), arity));
+        self.bind(&func.name, Binding::Function(index));
+        let params = func
+            .params
+            .iter()
+            .enumerate()
+            .map(|(slot, param)| (param.name.clone(), Binding::Local(slot as u16)))
+            .collect();
+        self.scopes.push(FunctionScope {
+            function: index as usize,
+            blocks: vec![params],
+        });
+        let result = self
+            .statements(&func.body)
+            .and_then(|_| self.implicit_return(span));
+        self.scopes.pop();
+        result
+    }
+
+    // Returns void from the end of a function that didn't return on every path
+    fn implicit_return(&mut self, span: Span) -> Result<(), RuntimeError> {
+        let void = self.constant(LiteralValue::Void, span)?;
+        self.emit(void, span);
+        self.emit(Instruction::Return, span);
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &SpannedExpression) -> Result<(), RuntimeError> {
+        self.expression_at(&expression.node, expression.span)
+    }
+
+    fn expression_at(&mut self, expression: &Expression, span: Span) -> Result<(), RuntimeError> {
+        match expression {
+            Expression::Literal(literal) => {
+                let constant = self.constant(literal.value.clone(), span)?;
+                self.emit(constant, span);
+            }
+            Expression::VariableRef(var_ref) => {
+                let Some((hops, Binding::Local(slot))) = self.resolve(&var_ref.name) else {
+                    return Err(RuntimeError::new(
+                        format!("use of undefined variable {}", var_ref.name),
+                        span,
+                    ));
+                };
+                self.emit(Instruction::Load { hops, slot }, span);
+            }
+            Expression::Binary(binary) => {
+                self.expression(&binary.left)?;
+                self.expression(&binary.right)?;
+                self.emit(Instruction::Binary(binary.operator), span);
+            }
+            Expression::FunctionCall(call) => {
+                for arg in &call.args {
+                    self.expression(arg)?;
+                }
+                // User functions shadow built-ins of the same name
+                if let Some((hops, Binding::Function(function))) = self.resolve(&call.callee) {
+                    self.emit(Instruction::Call { function, hops }, span);
+                } else {
+                    let name = self.name(&call.callee, span)?;
+                    let argc = u16::try_from(call.args.len())
+                        .map_err(|_| RuntimeError::new("too many arguments".to_string(), span))?;
+                    self.emit(Instruction::CallBuiltIn { name, argc }, span);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn scope(&mut self) -> &mut FunctionScope {
+        self.scopes
+            .last_mut()
+            .expect("compiler always has a function scope")
+    }
+
+    fn current_function(&mut self) -> &mut Function {
+        let index = self.scope().function;
+        &mut self.bytecode.functions[index]
+    }
+
+    fn bind(&mut self, name: &str, binding: Binding) {
+        let block = self
+            .scope()
+            .blocks
+            .last_mut()
+            .expect("function scope has a block");
+        block.push((name.to_string(), binding));
+    }
+
+    fn declare_local(&mut self, name: &str, span: Span) -> Result<u16, RuntimeError> {
+        let function = self.current_function();
+        let slot = function.locals;
+        function.locals = slot
+            .checked_add(1)
+            .ok_or_else(|| RuntimeError::new("too many local variables".to_string(), span))?;
+        self.bind(name, Binding::Local(slot));
+        Ok(slot)
+    }
+
+    // Finds the innermost binding of a name and how many functions out it was declared
+    fn resolve(&self, name: &str) -> Option<(u16, Binding)> {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(hops, scope)| {
+                scope
+                    .blocks
+                    .iter()
+                    .rev()
+                    .flat_map(|block| block.iter().rev())
+                    .find(|(bound, _)| bound == name)
+                    .map(|(_, binding)| (hops as u16, *binding))
+            })
+    }
+
+    // Returns an instruction pushing the value, adding it to the constant pool if it is new
+    fn constant(&mut self, value: LiteralValue, span: Span) -> Result<Instruction, RuntimeError> {
+        let constants = &mut self.bytecode.constants;
+        let index = match constants.iter().position(|constant| *constant == value) {
+            Some(index) => index,
+            None => {
+                constants.push(value);
+                constants.len() - 1
+            }
+        };
+        u32::try_from(index)
+            .map(Instruction::Constant)
+            .map_err(|_| RuntimeError::new("too many constants".to_string(), span))
+    }
+
+    fn name(&mut self, name: &str, span: Span) -> Result<u32, RuntimeError> {
+        let names = &mut self.bytecode.names;
+        let index = match names.iter().position(|existing| existing == name) {
+            Some(index) => index,
+            None => {
+                names.push(name.to_string());
+                names.len() - 1
+            }
+        };
+        u32::try_from(index).map_err(|_| RuntimeError::new("too many built-ins".to_string(), span))
+    }
+
+    fn emit(&mut self, instruction: Instruction, span: Span) -> usize {
+        let function = self.current_function();
+        function.code.push(instruction);
+        function.spans.push(span);
+        function.code.len() - 1
+    }
+
+    fn next_offset(&mut self) -> Result<u32, RuntimeError> {
+        let function = self.current_function();
+        u32::try_from(function.code.len()).map_err(|_| {
+            RuntimeError::new(
+                format!("function {} is too long", function.name),
+                Span::default(),
+            )
+        })
+    }
+
+    // Points a jump emitted earlier at the next instruction
+    fn patch(&mut self, jump: usize) -> Result<(), RuntimeError> {
+        let target = self.next_offset()?;
+        match &mut self.current_function().code[jump] {
+            Instruction::Jump(offset) | Instruction::JumpIfFalse(offset) => *offset = target,
+            instruction => unreachable!("patched {} is not a jump", instruction),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+pub mod bytecode;
+pub mod compiler;
+pub mod test;
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
+
+use crate::common::ast::{LiteralValue, Program};
+use crate::interpreter::{BuiltInFn, Context, RuntimeError, binary_operation, standard_built_ins};
+
+pub use bytecode::{Bytecode, Function, Instruction};
+pub use compiler::compile;
+
+// A call in progress. Locals live on the value stack from `base`; `parent` is the frame of the
+// function the callee was declared in, which is where names from outside the callee are found
+#[derive(Clone, Copy)]
+struct Frame {
+    function: usize,
+    ip: usize,
+    base: usize,
+    parent: usize,
+}
+
+// A stack-based virtual machine running compiled bytecode. It behaves like the tree-walking
+// `Interpreter` but doesn't touch the syntax tree while running
+pub struct Vm {
+    built_ins: HashMap<String, BuiltInFn>,
+    context: Context,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    // Creates a VM that prints to stdout and reads from stdin
+    pub fn new() -> Self {
+        Self::with_context(Context::default())
+    }
+
+    // Creates a VM that prints to `output` and reads from `input`
+    pub fn with_io(output: Box<dyn Write>, input: Box<dyn BufRead>) -> Self {
+        Self::with_context(Context::new(output, input))
+    }
+
+    fn with_context(context: Context) -> Self {
+        let mut vm = Self {
+            built_ins: HashMap::new(),
+            context,
+        };
+        for (name, function) in standard_built_ins() {
+            vm.register_built_in(name.to_string(), function);
+        }
+        vm
+    }
+
+    // Registers a built-in function, replacing any existing one with the same name
+    pub fn register_built_in(&mut self, name: String, function: BuiltInFn) {
+        self.built_ins.insert(name, function);
+    }
+
+    // Gives access to the output and input used by built-ins
+    pub fn context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    // Compiles and runs an analyzed program
+    pub fn execute(&mut self, program: &Program) -> Result<(), RuntimeError> {
+        self.run(&compile(program)?)
+    }
+
+    // Runs compiled bytecode from the top of its main function
+    pub fn run(&mut self, bytecode: &Bytecode) -> Result<(), RuntimeError> {
+        self.context.exit_code = None;
+        let result = self.run_main(bytecode);
+        // Output written without a trailing newline may still be buffered
+        let _ = self.context.output.flush();
+        // A call to `exit` ends the program without failing it
+        match result {
+            Err(_) if self.context.exit_code.is_some() => Ok(()),
+            result => result,
+        }
+    }
+
+    // The code the last run program passed to `exit`, if it called it
+    pub fn exit_code(&self) -> Option<i32> {
+        self.context.exit_code
+    }
+
+    // Makes `random_int` return the same numbers every run, as the `seed` built-in does
+    pub fn set_seed(&mut self, seed: u64) {
+        self.context.random.seed(seed);
+    }
+
+    fn run_main(&mut self, bytecode: &Bytecode) -> Result<(), RuntimeError> {
+        let built_ins: Vec<Option<BuiltInFn>> = bytecode
+            .names
+            .iter()
+            .map(|name| self.built_ins.get(name).cloned())
+            .collect();
+        let mut stack: Vec<LiteralValue> = Vec::new();
+        let mut frames = vec![Frame {
+            function: 0,
+            ip: 0,
+            base: 0,
+            parent: 0,
+        }];
+        stack.resize(bytecode.functions[0].locals as usize, LiteralValue::Void);
+
+        loop {
+            let current = frames.len() - 1;
+            let frame = frames[current];
+            let function = &bytecode.functions[frame.function];
+            let instruction = function.code[frame.ip];
+            let span = function.spans[frame.ip];
+            frames[current].ip += 1;
+
+            match instruction {
+                Instruction::Constant(index) => {
+                    stack.push(bytecode.constants[index as usize].clone());
+                }
+                Instruction::Load { hops, slot } => {
+                    let base = frames[ancestor(&frames, current, hops)].base;
+                    stack.push(stack[base + slot as usize].clone());
+                }
+                Instruction::Store { hops, slot } => {
+                    let base = frames[ancestor(&frames, current, hops)].base;
+                    stack[base + slot as usize] = pop(&mut stack);
+                }
+                Instruction::Pop => {
+                    pop(&mut stack);
+                }
+                Instruction::Binary(operator) => {
+                    let right = pop(&mut stack);
+                    let left = pop(&mut stack);
+                    stack.push(binary_operation(operator, left, right, span)?);
+                }
+                Instruction::Jump(target) => frames[current].ip = target as usize,
+                Instruction::JumpIfFalse(target) => {
+                    if pop(&mut stack) != LiteralValue::Bool(true) {
+                        frames[current].ip = target as usize;
+                    }
+                }
+                Instruction::Call { function, hops } => {
+                    let callee = &bytecode.functions[function as usize];
+                    let base = stack.len() - callee.arity as usize;
+                    stack.resize(base + callee.locals as usize, LiteralValue::Void);
+                    frames.push(Frame {
+                        function: function as usize,
+                        ip: 0,
+                        base,
+                        parent: ancestor(&frames, current, hops),
+                    });
+                }
+                Instruction::CallBuiltIn { name, argc } => {
+                    let args = stack.split_off(stack.len() - argc as usize);
+                    let Some(built_in) = &built_ins[name as usize] else {
+                        return Err(RuntimeError::new(
+                            format!(
+                                "use of undefined function {}",
+                                bytecode.names[name as usize]
+                            ),
+                            span,
+                        ));
+                    };
+                    stack.push(built_in(&mut self.context, args, span)?);
+                }
+                Instruction::Return => {
+                    let value = pop(&mut stack);
+                    frames.pop();
+                    if frames.is_empty() {
+                        return Ok(());
+                    }
+                    stack.truncate(frame.base);
+                    stack.push(value);
+                }
+            }
+        }
+    }
+}
+
+// Follows `hops` static links up from a frame
+fn ancestor(frames: &[Frame], mut index: usize, hops: u16) -> usize {
+    for _ in 0..hops {
+        index = frames[index].parent;
+    }
+    index
+}
+
+fn pop(stack: &mut Vec<LiteralValue>) -> LiteralValue {
+    stack
+        .pop()
+        .expect("compiled code never pops an empty stack")
+}
@@ -0,0 +1,124 @@
+#![cfg(test)]
+
+use super::*;
+use crate::interpreter::test::run_with_output;
+use crate::interpreter::{Interpreter, OutputBuffer};
+use crate::pipeline::check_source;
+
+const EXAMPLES: [(&str, &str); 3] = [
+    (
+        "complex",
+        include_str!("../../working_examples/complex.pry"),
+    ),
+    (
+        "factorial",
+        include_str!("../../working_examples/factorial.pry"),
+    ),
+    (
+        "fizzbuzz",
+        include_str!("../../working_examples/fizzbuzz.pry"),
+    ),
+];
+
+// Runs a program on the VM alone and returns what it printed, or the error it stopped with
+pub fn run_vm(source: &str) -> (String, Result<(), RuntimeError>) {
+    let program = check_source(source).unwrap();
+    let output = OutputBuffer::new();
+    let mut vm = Vm::with_io(Box::new(output.clone()), Box::new(&b""[..]));
+    let result = vm.execute(&program);
+    (output.contents(), result)
+}
+
+// Like `run_vm`, on the tree-walking interpreter
+pub fn run_interpreter(source: &str) -> (String, Result<(), RuntimeError>) {
+    let program = check_source(source).unwrap();
+    let output = OutputBuffer::new();
+    let mut interpreter = Interpreter::with_io(Box::new(output.clone()), Box::new(&b""[..]));
+    let result = interpreter.execute(&program);
+    (output.contents(), result)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn working_examples_match_interpreter() {
+        for (name, source) in EXAMPLES {
+            let (expected, result) = run_interpreter(source);
+            result.unwrap();
+            assert!(!expected.is_empty(), "{}", name);
+            let (output, result) = run_vm(source);
+            result.unwrap();
+            assert_eq!(output, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    // Nested functions reach the locals of the function they were declared in, even when called
+    // through a recursive helper
+    fn nested_functions() {
+        let input = r#"func outer(n: int): int {
+            var total: int = 0;
+            func add(k: int): void { total = total + k; }
+            func walk(k: int): void { if (k > 0) { add(k); walk(k - 1); } }
+            walk(n);
+            return total;
+        }
+        println(outer(4));
+        println(outer(10));"#;
+        assert_eq!(run_with_output(input), "10\n55\n");
+    }
+
+    #[test]
+    fn block_scoped_locals() {
+        let input = r#"var i: int = 0;
+        while (i < 3) { var square: int = i * i; print(square); i = i + 1; }
+        if (i == 3) { var done: string = "done"; println(done); } else { println("no"); }"#;
+        assert_eq!(run_with_output(input), "014done\n");
+    }
+
+    #[test]
+    fn disassembly() {
+        let program = check_source(
+            "func twice(n: int): int { return n * 2; }\nvar x: int = twice(4);\nprintln(x);",
+        )
+        .unwrap();
+        let expected = "fn 0 <main> (arity 0, locals 1)
+  0000 Constant 4
+  0001 Call twice hops 0
+  0002 Store 0 hops 0
+  0003 Load 0 hops 0
+  0004 CallBuiltIn println argc 1
+  0005 Pop
+  0006 Constant void
+  0007 Return
+fn 1 twice (arity 1, locals 1)
+  0000 Load 0 hops 0
+  0001 Constant 2
+  0002 Binary *
+  0003 Return
+  0004 Constant void
+  0005 Return
+";
+        assert_eq!(compile(&program).unwrap().disassemble(), expected);
+    }
+
+    #[test]
+    fn runtime_error_keeps_output() {
+        let (output, result) = run_vm("println(1);\nprintln(2 % (1 - 1));\nprintln(3);");
+        assert_eq!(output, "1\n");
+        let err = result.unwrap_err();
+        assert_eq!(err.message, "division by zero");
+        assert_eq!(err.span.start.line, 2);
+    }
+
+    #[test]
+    // Recursion is bounded by the heap, not the Rust stack
+    fn deep_recursion() {
+        let (output, result) = run_vm(
+            "func depth(n: int): int { if (n == 0) { return 0; } return 1 + depth(n - 1); }\nprintln(depth(200000));",
+        );
+        result.unwrap();
+        assert_eq!(output, "200000\n");
+    }
+}
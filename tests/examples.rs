@@ -0,0 +1,102 @@
+// Runs every example program on both backends and compares what it printed with the `.out` file
+// next to it, and the errors it reported with the `.err` file. Examples that run cleanly have no
+// `.err` file. Run with UPDATE_GOLDEN=1 to write the files from the current behavior instead
+use std::env;
+use std::fs;
+use std::io::empty;
+use std::path::{Path, PathBuf};
+
+use perry::{Interpreter, PerryError, Vm, interpreter::OutputBuffer, pipeline::link_file};
+
+// Directories of examples, relative to the crate root, which is where cargo runs tests
+const EXAMPLE_DIRECTORIES: &[&str] = &["working_examples", "tests/golden"];
+const EXAMPLE_FILES: &[&str] = &["test.pry"];
+
+#[test]
+fn examples_match_golden_files() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let mut mismatches = Vec::new();
+    for path in examples() {
+        let (output, errors) = run(&path);
+        let expected = [("out", Some(output)), ("err", errors)];
+        for (extension, actual) in expected {
+            let golden = path.with_extension(extension);
+            if update {
+                match &actual {
+                    Some(actual) => fs::write(&golden, actual).unwrap(),
+                    None if golden.exists() => fs::remove_file(&golden).unwrap(),
+                    None => {}
+                }
+                continue;
+            }
+            let wanted = fs::read_to_string(&golden).ok();
+            if wanted != actual {
+                mismatches.push(format!(
+                    "{}\n--- expected ---\n{}\n--- actual ---\n{}",
+                    golden.display(),
+                    wanted.as_deref().unwrap_or("<no file>"),
+                    actual.as_deref().unwrap_or("<no file>")
+                ));
+            }
+        }
+    }
+    assert!(
+        mismatches.is_empty(),
+        "examples differ from their golden files, rerun with UPDATE_GOLDEN=1 if that is \
+         intended:\n\n{}",
+        mismatches.join("\n\n")
+    );
+}
+
+// Every example, sorted so failures are reported in a stable order
+fn examples() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = EXAMPLE_FILES.iter().map(PathBuf::from).collect();
+    for directory in EXAMPLE_DIRECTORIES {
+        for entry in fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "pry") {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    paths
+}
+
+// Runs an example, returning what it printed and its rendered errors, if any. The backends must
+// agree on both
+fn run(path: &Path) -> (String, Option<String>) {
+    let source = fs::read_to_string(path).unwrap();
+    let linked = match link_file(path, &source) {
+        Ok(linked) => linked,
+        Err(errors) => return (String::new(), Some(render(&errors, &source))),
+    };
+    let output = OutputBuffer::new();
+    let mut interpreter = Interpreter::with_io(Box::new(output.clone()), Box::new(empty()));
+    let result = interpreter.execute(&linked.program);
+    let vm_output = OutputBuffer::new();
+    let mut vm = Vm::with_io(Box::new(vm_output.clone()), Box::new(empty()));
+    let vm_result = vm.execute(&linked.program);
+    assert_eq!(
+        output.contents(),
+        vm_output.contents(),
+        "{}: VM output differs",
+        path.display()
+    );
+    assert_eq!(result, vm_result, "{}: VM result differs", path.display());
+    let errors = result
+        .err()
+        .map(|err| render(&[linked.locate(err.into())], &source));
+    (output.contents(), errors)
+}
+
+// Renders errors without color, against the file each one points into
+fn render(errors: &[PerryError], source: &str) -> String {
+    errors
+        .iter()
+        .map(|err| match &err.file {
+            Some(file) => err.render(&fs::read_to_string(file).unwrap(), false),
+            None => err.render(source, false),
+        })
+        .collect()
+}